@@ -1,23 +1,246 @@
 use crate::fourier::Candle;
-use crate::strategy::{CandleData, Executioner, Strategy, TraderConfig};
+use crate::journal::{Journal, TradeRecord};
+use crate::portfolio::PortfolioConfig;
+use crate::risk::RiskLimits;
+use crate::strategy::{CandleData, Executioner, Quote, Strategy, TraderConfig};
 use anyhow::Result;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::mpsc;
-use tokio::time::{Duration, sleep};
+
+// equity curve points are one minute apart
+pub(crate) const PERIODS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0;
 
 pub struct BackTester<T> {
     strategy: T,
+    risk_limits: RiskLimits,
+    portfolio: Option<PortfolioConfig>,
+    quotes: HashMap<String, Arc<Vec<Quote>>>,
+    symbol_strategies: HashMap<String, Box<dyn Strategy>>,
+    history: Vec<(String, Vec<Candle>)>,
+    verbose: bool,
+    flat_at_end: bool,
+}
+
+/// Summary of a single backtest run.
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestReport {
+    pub initial_capital: f64,
+    pub final_equity: f64,
+    pub total_return_pct: f64,
+    pub max_drawdown_pct: f64,
+    pub sharpe: f64, // annualised from the sampled equity curve
+    pub trade_count: usize,
+    pub win_rate: f64,
+    #[serde(skip)]
+    pub trades: Vec<TradeRecord>,
+    #[serde(skip)]
+    pub equity_curve: Vec<(u64, f64)>,
+}
+
+impl BacktestReport {
+    pub fn from_journal(initial_capital: f64, final_equity: f64, journal: &Journal) -> Self {
+        let equity: Vec<f64> = journal.equity_curve.iter().map(|(_, e)| *e).collect();
+        let wins = journal.trades.iter().filter(|t| t.pnl > 0.0).count();
+        let trade_count = journal.trades.len();
+
+        BacktestReport {
+            initial_capital,
+            final_equity,
+            total_return_pct: if initial_capital > 0.0 {
+                (final_equity / initial_capital - 1.0) * 100.0
+            } else {
+                0.0
+            },
+            max_drawdown_pct: max_drawdown_pct(&equity),
+            sharpe: sharpe_ratio(&period_returns(&equity), PERIODS_PER_YEAR),
+            trade_count,
+            win_rate: if trade_count > 0 {
+                wins as f64 / trade_count as f64
+            } else {
+                0.0
+            },
+            trades: journal.trades.clone(),
+            equity_curve: journal.equity_curve.clone(),
+        }
+    }
+
+    /// Return over max drawdown; zero drawdown counts as a tiny one
+    pub fn calmar(&self) -> f64 {
+        self.total_return_pct / self.max_drawdown_pct.max(1e-9)
+    }
+}
+
+/// Simple returns between consecutive equity points
+pub fn period_returns(equity: &[f64]) -> Vec<f64> {
+    equity
+        .windows(2)
+        .filter(|w| w[0] > 0.0)
+        .map(|w| w[1] / w[0] - 1.0)
+        .collect()
+}
+
+/// Largest peak-to-trough fall of `equity`, in percent
+pub fn max_drawdown_pct(equity: &[f64]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut worst = 0.0f64;
+    for &value in equity {
+        peak = peak.max(value);
+        if peak > 0.0 {
+            worst = worst.max((peak - value) / peak * 100.0);
+        }
+    }
+    worst
+}
+
+/// Annualised Sharpe ratio of per-period `returns` (risk-free rate of zero)
+pub fn sharpe_ratio(returns: &[f64], periods_per_year: f64) -> f64 {
+    if returns.len() < 2 {
+        return 0.0;
+    }
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    if variance <= 0.0 {
+        return 0.0;
+    }
+    mean / variance.sqrt() * periods_per_year.sqrt()
+}
+
+/// Read every candle from a historical CSV
+pub fn load_candles(csv_file: &str) -> Result<Vec<Candle>> {
+    let mut reader = csv::Reader::from_path(csv_file)?;
+    let mut candles = Vec::new();
+    for row in reader.deserialize::<Candle>() {
+        match row {
+            Ok(candle) => candles.push(candle),
+            Err(e) => {
+                println!("[ERROR][BACKTEST] Failed to parse candle: {}", e);
+            }
+        }
+    }
+    Ok(candles)
+}
+
+/// Read a recorded Roostoo ticker for one symbol, oldest first
+pub fn load_quotes(csv_file: &str) -> Result<Vec<Quote>> {
+    let mut reader = csv::Reader::from_path(csv_file)?;
+    let mut quotes = Vec::new();
+    for row in reader.deserialize::<Quote>() {
+        match row {
+            Ok(quote) => quotes.push(quote),
+            Err(e) => {
+                println!("[ERROR][BACKTEST] Failed to parse quote: {}", e);
+            }
+        }
+    }
+    quotes.sort_by_key(|quote| quote.time);
+    Ok(quotes)
 }
 
 impl<T: Strategy + Send + 'static> BackTester<T> {
     pub fn create(strategy: T) -> Self {
-        BackTester { strategy }
+        BackTester {
+            strategy,
+            risk_limits: RiskLimits::default(),
+            portfolio: None,
+            quotes: HashMap::new(),
+            symbol_strategies: HashMap::new(),
+            history: Vec::new(),
+            verbose: true,
+            flat_at_end: false,
+        }
+    }
+
+    /// Silence per-candle and per-fill logs, e.g. for parameter sweeps
+    pub fn quiet(mut self) -> Self {
+        self.verbose = false;
+        self
     }
 
-    pub async fn begin(self, csv_file: &str, symbol: &str, initial_capital: f64) -> Result<f64> {
-        let reader = csv::Reader::from_path(csv_file)?;
+    /// Replace the default portfolio limits, e.g. with the live `risk` section
+    pub fn with_risk_limits(mut self, limits: RiskLimits) -> Self {
+        self.risk_limits = limits;
+        self
+    }
+
+    /// Trade target weights instead of the strategy's entry signals
+    pub fn with_portfolio(mut self, portfolio: PortfolioConfig) -> Self {
+        self.portfolio = Some(portfolio);
+        self
+    }
+
+    /// Replay a recorded ticker for `symbol` alongside its candles. Each
+    /// candle carries the latest quote at or before its close, and resting
+    /// orders fill against the quote instead of the candle's range.
+    pub fn with_quotes(mut self, symbol: &str, quotes: Vec<Quote>) -> Self {
+        self.quotes.insert(symbol.to_string(), Arc::new(quotes));
+        self
+    }
+
+    /// Trade `symbol` with its own strategy instead of the tester's
+    pub fn with_symbol_strategy(mut self, symbol: &str, strategy: Box<dyn Strategy>) -> Self {
+        self.symbol_strategies.insert(symbol.to_string(), strategy);
+        self
+    }
+
+    /// Sell whatever is still held at the last close, with fees, once the
+    /// candles run out, so the final equity can be carried on as cash
+    pub fn flat_at_end(mut self) -> Self {
+        self.flat_at_end = true;
+        self
+    }
 
-        let (candle_tx, candle_rx) = mpsc::channel(32);
+    /// Load `candles` into `symbol`'s context before the replay, without
+    /// trading, so indicators start warm as they do live
+    pub fn with_history(mut self, symbol: &str, candles: Vec<Candle>) -> Self {
+        self.history.push((symbol.to_string(), candles));
+        self
+    }
+
+    pub async fn begin(
+        self,
+        csv_file: &str,
+        symbol: &str,
+        initial_capital: f64,
+    ) -> Result<BacktestReport> {
+        let candles = Arc::new(load_candles(csv_file)?);
+        Ok(self.run_candles(candles, symbol, initial_capital).await)
+    }
+
+    /// Replay already loaded candles; the same `Arc` can feed many runs.
+    pub async fn run_candles(
+        self,
+        candles: Arc<Vec<Candle>>,
+        symbol: &str,
+        initial_capital: f64,
+    ) -> BacktestReport {
+        self.run_universe(vec![(symbol.to_string(), candles)], initial_capital)
+            .await
+    }
+
+    /// Load one CSV per symbol and replay them together
+    pub async fn begin_universe(
+        self,
+        csv_files: &[(String, String)], // symbol, path
+        initial_capital: f64,
+    ) -> Result<BacktestReport> {
+        let mut series = Vec::with_capacity(csv_files.len());
+        for (symbol, path) in csv_files {
+            series.push((symbol.clone(), Arc::new(load_candles(path)?)));
+        }
+        Ok(self.run_universe(series, initial_capital).await)
+    }
+
+    /// Replay several symbols through one executioner, interleaved by candle
+    /// close time, so strategies see the same cross-symbol picture as live.
+    pub async fn run_universe(
+        self,
+        series: Vec<(String, Arc<Vec<Candle>>)>,
+        initial_capital: f64,
+    ) -> BacktestReport {
+        let (candle_tx, candle_rx) = mpsc::channel(1024);
         let (oe_tx, _oe_rx) = mpsc::channel(1);
 
         let config = TraderConfig {
@@ -28,37 +251,65 @@ impl<T: Strategy + Send + 'static> BackTester<T> {
             api_key: "BACKTEST".to_string(),
             api_secret: "BACKTEST".to_string(),
             initial_positions: HashMap::new(),
+            risk_limits: self.risk_limits,
+            portfolio: self.portfolio,
+            symbol_strategies: self.symbol_strategies,
+            shadows: None,
+            config_watch: None,
+            snapshot: None,
         };
 
         let mut executioner = Executioner::new(config);
-        executioner.add_symbol(symbol.to_string(), 3);
+        executioner.set_verbose(self.verbose);
+        executioner.set_flat_at_end(self.flat_at_end);
+        for (symbol, _) in &series {
+            executioner.add_symbol(symbol.clone(), 3);
+        }
+        executioner.warm_up(&self.history);
 
-        let symbol_name = symbol.to_string();
+        let quotes = self.quotes;
         let producer = tokio::spawn(async move {
             let tx = candle_tx;
-            let mut reader = reader;
-            for row in reader.deserialize::<Candle>() {
-                match row {
-                    Ok(candle) => {
-                        let candle_data = CandleData {
-                            symbol: symbol_name.clone(),
-                            candle,
-                        };
-
-                        if tx.send(candle_data).await.is_err() {
-                            break;
-                        }
-                        sleep(Duration::from_millis(1)).await;
-                    }
-                    Err(e) => {
-                        println!("[ERROR][BACKTEST] Failed to parse candle: {}", e);
+            // (close time, series, index), stable so ties keep series order
+            let mut schedule: Vec<(u64, usize, usize)> = series
+                .iter()
+                .enumerate()
+                .flat_map(|(s, (_, candles))| {
+                    candles
+                        .iter()
+                        .enumerate()
+                        .map(move |(i, c)| (c.close_secs(), s, i))
+                })
+                .collect();
+            schedule.sort_by_key(|(time, _, _)| *time);
+            // next unsent quote per series
+            let mut cursors = vec![0usize; series.len()];
+
+            for (time, s, i) in schedule {
+                let (symbol, candles) = &series[s];
+                let quote = quotes.get(symbol).and_then(|recorded| {
+                    let cursor = &mut cursors[s];
+                    while *cursor < recorded.len() && recorded[*cursor].time <= time {
+                        *cursor += 1;
                     }
+                    cursor.checked_sub(1).map(|latest| recorded[latest])
+                });
+                let candle_data = CandleData {
+                    symbol: symbol.clone(),
+                    candle: candles[i],
+                    quote,
+                };
+
+                if tx.send(candle_data).await.is_err() {
+                    break;
                 }
             }
         });
 
         executioner.run(true).await;
         let _ = producer.await;
-        Ok(initial_capital)
+
+        let final_equity = executioner.equity().await;
+        BacktestReport::from_journal(initial_capital, final_equity, executioner.journal())
     }
 }
@@ -0,0 +1,287 @@
+use crate::config::{ConfigError, ConfigFile, SymbolParams, Validate, check_ordered, check_range};
+use crate::exits::{ExitPolicies, ExitPolicy};
+use crate::indicators::Indicators;
+use crate::roostoo::{OrderSide, OrderType};
+use crate::sizing::{PositionSizer, Sizing, SizingInput};
+use crate::strategy::{ExecContext, Order, SharedState, Strategy};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Tuning for `Bollinger`, loaded from the `bollinger` section of the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BollingerParams {
+    pub period: usize,      // candles in the band's SMA and standard deviation
+    pub entry_z: f64,       // enter when close is this many deviations below the SMA
+    pub exit_z: f64,        // leave once close is back above SMA + exit_z deviations
+    pub vol_fast: usize,    // candles of recent volatility for the regime filter
+    pub vol_slow: usize,    // candles of baseline volatility
+    pub max_vol_ratio: f64, // skip entries while fast vol exceeds slow vol by this
+    pub max_annualised_vol: f64,
+    pub sizing: Sizing,
+    pub max_notional: f64,     // hard cap on entry size in USD
+    pub warmup_candles: usize, // candles needed before the first entry
+    pub exits: ExitPolicies,   // protective exits on top of the mean exit
+}
+
+impl Default for BollingerParams {
+    fn default() -> Self {
+        BollingerParams {
+            period: 20,
+            entry_z: 2.0,
+            exit_z: 0.0,
+            vol_fast: 60,
+            vol_slow: 600,
+            max_vol_ratio: 1.5,
+            max_annualised_vol: 3.0,
+            sizing: Sizing::default(),
+            max_notional: 10_000.0,
+            warmup_candles: 601,
+            exits: ExitPolicies::new(vec![
+                ExitPolicy::AtrStop {
+                    period: 14,
+                    multiple: 3.0,
+                },
+                ExitPolicy::MaxHoldingTime { seconds: 3_600 },
+            ]),
+        }
+    }
+}
+
+impl Validate for BollingerParams {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        check_range(scope, "period", self.period as f64, 2.0, 2048.0)?;
+        check_range(scope, "entry_z", self.entry_z, 0.0, 10.0)?;
+        // exiting below the entry band would sell straight after buying
+        check_range(scope, "exit_z", self.exit_z, -self.entry_z, 10.0)?;
+        check_range(scope, "vol_fast", self.vol_fast as f64, 2.0, 2047.0)?;
+        check_range(scope, "vol_slow", self.vol_slow as f64, 2.0, 2047.0)?;
+        check_ordered(
+            scope,
+            "vol_fast",
+            self.vol_fast as f64,
+            self.vol_slow as f64,
+            "vol_slow",
+        )?;
+        check_range(scope, "max_vol_ratio", self.max_vol_ratio, 0.0, 100.0)?;
+        check_range(
+            scope,
+            "max_annualised_vol",
+            self.max_annualised_vol,
+            0.0,
+            f64::MAX,
+        )?;
+        self.sizing.validate(scope)?;
+        check_range(scope, "max_notional", self.max_notional, 0.0, f64::MAX)?;
+        check_range(
+            scope,
+            "warmup_candles",
+            self.warmup_candles as f64,
+            self.period.max(self.vol_slow + 1) as f64,
+            2048.0,
+        )?;
+        self.exits.validate(scope)
+    }
+}
+
+/// Mean reversion on Bollinger bands: buy when the close is `entry_z`
+/// standard deviations below its SMA and sell when it is back at the mean.
+/// Entries are skipped while volatility is expanding, when a stretched band
+/// is more likely a breakout than an overreaction.
+#[derive(Default)]
+pub struct Bollinger {
+    pub params: SymbolParams<BollingerParams>,
+}
+
+impl Bollinger {
+    pub const SECTION: &'static str = "bollinger";
+
+    pub fn new(params: SymbolParams<BollingerParams>) -> Self {
+        Bollinger { params }
+    }
+
+    pub fn from_config(config: &ConfigFile) -> Result<Self, ConfigError> {
+        Ok(Self::new(config.section_or_default(Self::SECTION)?))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::from_config(&ConfigFile::load(path)?)
+    }
+
+    /// Deviations of the last close from the band's mean
+    pub fn zscore(params: &BollingerParams, ctx: &ExecContext) -> Option<f64> {
+        let indicators = Indicators::new(&ctx.candles);
+        let mean = indicators.sma(params.period)?;
+        let std = indicators.stddev_series(ctx.candles.iter().map(|c| c.close), params.period)?;
+        (std > 0.0).then(|| (ctx.last_close - mean) / std)
+    }
+
+    /// True while volatility is calm enough to fade moves
+    pub fn calm_regime(params: &BollingerParams, ctx: &ExecContext) -> bool {
+        let indicators = Indicators::new(&ctx.candles);
+        match (
+            indicators.annualised_volatility(params.vol_fast),
+            indicators.annualised_volatility(params.vol_slow),
+        ) {
+            (Some(fast), Some(slow)) if slow > 0.0 => {
+                fast / slow <= params.max_vol_ratio && fast <= params.max_annualised_vol
+            }
+            _ => false,
+        }
+    }
+}
+
+#[async_trait]
+impl Strategy for Bollinger {
+    async fn should_long(
+        &self,
+        ctx: &mut ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        if ctx.position.is_open() {
+            return false;
+        }
+
+        let params = self.params.get(&ctx.symbol);
+        if ctx.candles.len() < params.warmup_candles {
+            return false;
+        }
+        let has_capital = {
+            let guard = shared_state.lock().await;
+            guard.available_capital() > 0.0
+        };
+        if !has_capital {
+            return false;
+        }
+
+        match Self::zscore(params, ctx) {
+            Some(z) => z <= -params.entry_z && Self::calm_regime(params, ctx),
+            None => false,
+        }
+    }
+
+    async fn go_long(
+        &self,
+        ctx: &ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> Option<Order> {
+        let params = self.params.get(&ctx.symbol);
+        let size = {
+            let guard = shared_state.lock().await;
+            let trade_returns: Vec<f64> = guard.trade_returns.iter().copied().collect();
+            params.sizing.size(&SizingInput {
+                capital: guard.available_capital(),
+                price: ctx.last_close,
+                candles: &ctx.candles,
+                trade_returns: &trade_returns,
+                open_positions: guard.open_positions,
+            })
+        };
+        let cap = if ctx.last_close > 0.0 {
+            params.max_notional / ctx.last_close
+        } else {
+            0.0
+        };
+        let quantity = size.min(cap);
+        if quantity <= 0.0 {
+            return None;
+        }
+
+        Some(Order {
+            pair: ctx.pair(),
+            side: OrderSide::Buy,
+            order_type: OrderType::Market,
+            quantity,
+            price: None,
+        })
+    }
+
+    async fn update_position(
+        &self,
+        ctx: &mut ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        let params = self.params.get(&ctx.symbol);
+        if params.exits.evaluate(ctx).is_some() {
+            return true;
+        }
+        ctx.position.is_open() && Self::zscore(params, ctx).is_some_and(|z| z >= params.exit_z)
+    }
+
+    fn protective_exits(&self, symbol: &str) -> Option<&ExitPolicies> {
+        Some(&self.params.get(symbol).exits)
+    }
+
+    fn reload(&mut self, config: &ConfigFile) -> Result<(), ConfigError> {
+        self.params = config.section_or_default(Self::SECTION)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fourier::Candle;
+
+    fn context(closes: &[f64]) -> ExecContext {
+        let candles: Vec<Candle> = closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| Candle {
+                close_time: (1_700_000_000 + i as u64) * 1_000,
+                open: close,
+                high: close,
+                low: close,
+                close,
+                ..Default::default()
+            })
+            .collect();
+        ExecContext::for_test("BTC", candles)
+    }
+
+    #[tokio::test]
+    async fn buys_the_lower_band_in_calm_markets_and_sells_at_the_mean() {
+        let params = BollingerParams {
+            period: 10,
+            vol_fast: 25,
+            vol_slow: 100,
+            warmup_candles: 101,
+            max_annualised_vol: f64::MAX,
+            ..Default::default()
+        };
+        let strategy = Bollinger::new(SymbolParams::new(params));
+        let shared = Arc::new(Mutex::new(SharedState::new(10_000.0)));
+
+        // steady chop, then a dip of a few deviations
+        let mut closes: Vec<f64> = (0..120)
+            .map(|i| if i % 2 == 0 { 100.2 } else { 99.8 })
+            .collect();
+        closes.push(99.0);
+        let mut ctx = context(&closes);
+        assert!(strategy.should_long(&mut ctx, shared.clone()).await);
+
+        // the same dip just after a volatile stretch is left alone
+        let mut wild = closes.clone();
+        for (i, close) in wild.iter_mut().rev().skip(10).take(12).enumerate() {
+            *close = if i % 2 == 0 { 103.0 } else { 97.0 };
+        }
+        assert!(
+            !strategy
+                .should_long(&mut context(&wild), shared.clone())
+                .await
+        );
+
+        // holding, price recovers to the mean
+        ctx.position
+            .open_new(1.0, 99.0, 0.0, Some(1_700_000_060))
+            .unwrap();
+        assert!(!strategy.update_position(&mut ctx, shared.clone()).await);
+        closes.push(100.1);
+        let mut recovered = context(&closes);
+        recovered.position = ctx.position.clone();
+        assert!(strategy.update_position(&mut recovered, shared).await);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::config::{ConfigError, ConfigFile, SymbolParams, Validate, check_range};
+use crate::exits::ExitPolicies;
+use crate::fourier::Candle;
+use crate::indicators::{Indicators, aggregate};
+use crate::roostoo::{OrderSide, OrderType};
+use crate::strategy::{ExecContext, Order, SharedState, Strategy};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Tuning for `Breakout`, loaded from the `breakout` section of the config file.
+/// Periods count bars of `timeframe_secs`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BreakoutParams {
+    pub timeframe_secs: u64,  // 1 trades the raw candles
+    pub entry_period: usize,  // enter above the high of this many bars
+    pub exit_period: usize,   // exit below the low of this many bars
+    pub volume_multiple: f64, // breakout bar volume vs the channel's average
+    pub trade_count_multiple: f64,
+    pub atr_period: usize,
+    pub atr_multiple: f64,   // ATRs of adverse move one risk unit covers
+    pub risk_fraction: f64,  // capital lost if price moves one risk unit
+    pub max_notional: f64,   // hard cap on entry size in USD
+    pub exits: ExitPolicies, // protective exits on top of the channel exit
+}
+
+impl Default for BreakoutParams {
+    fn default() -> Self {
+        BreakoutParams {
+            timeframe_secs: 60,
+            entry_period: 20,
+            exit_period: 10,
+            volume_multiple: 1.5,
+            trade_count_multiple: 1.2,
+            atr_period: 14,
+            atr_multiple: 2.0,
+            risk_fraction: 0.01,
+            max_notional: 10_000.0,
+            exits: ExitPolicies::default(),
+        }
+    }
+}
+
+impl Validate for BreakoutParams {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        check_range(
+            scope,
+            "timeframe_secs",
+            self.timeframe_secs as f64,
+            1.0,
+            86_400.0,
+        )?;
+        check_range(scope, "entry_period", self.entry_period as f64, 1.0, 1024.0)?;
+        check_range(scope, "exit_period", self.exit_period as f64, 1.0, 1024.0)?;
+        check_range(scope, "volume_multiple", self.volume_multiple, 0.0, 100.0)?;
+        check_range(
+            scope,
+            "trade_count_multiple",
+            self.trade_count_multiple,
+            0.0,
+            100.0,
+        )?;
+        check_range(scope, "atr_period", self.atr_period as f64, 1.0, 1024.0)?;
+        check_range(scope, "atr_multiple", self.atr_multiple, 0.0, 100.0)?;
+        check_range(scope, "risk_fraction", self.risk_fraction, 0.0, 1.0)?;
+        check_range(scope, "max_notional", self.max_notional, 0.0, f64::MAX)?;
+        self.exits.validate(scope)
+    }
+}
+
+/// Donchian channel breakout: buy when the close clears the highest high of
+/// the last `entry_period` bars on above-average volume and trade count, and
+/// sell when it falls through the lowest low of the last `exit_period` bars.
+/// One risk unit of `atr_multiple` ATRs costs `risk_fraction` of capital.
+#[derive(Default)]
+pub struct Breakout {
+    pub params: SymbolParams<BreakoutParams>,
+}
+
+impl Breakout {
+    pub const SECTION: &'static str = "breakout";
+
+    pub fn new(params: SymbolParams<BreakoutParams>) -> Self {
+        Breakout { params }
+    }
+
+    pub fn from_config(config: &ConfigFile) -> Result<Self, ConfigError> {
+        Ok(Self::new(config.section_or_default(Self::SECTION)?))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::from_config(&ConfigFile::load(path)?)
+    }
+
+    // bars on the strategy's timeframe; the last one is the bar forming now
+    fn bars(params: &BreakoutParams, ctx: &ExecContext) -> Vec<Candle> {
+        aggregate(&ctx.candles, params.timeframe_secs)
+    }
+
+    /// True when the forming bar breaks the channel with volume behind it
+    pub fn breaks_out(params: &BreakoutParams, bars: &[Candle]) -> bool {
+        let period = params.entry_period;
+        let (Some(high), Some(current)) =
+            (Indicators::new(bars).highest_high(period, 1), bars.last())
+        else {
+            return false;
+        };
+        let channel = &bars[bars.len() - 1 - period..bars.len() - 1];
+        let avg_volume = channel.iter().map(|b| b.volume).sum::<f64>() / period as f64;
+        let avg_trades = channel.iter().map(|b| b.trade_count as f64).sum::<f64>() / period as f64;
+
+        current.close > high
+            && current.volume >= params.volume_multiple * avg_volume
+            && current.trade_count as f64 >= params.trade_count_multiple * avg_trades
+    }
+}
+
+#[async_trait]
+impl Strategy for Breakout {
+    async fn should_long(
+        &self,
+        ctx: &mut ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        if ctx.position.is_open() {
+            return false;
+        }
+        let has_capital = {
+            let guard = shared_state.lock().await;
+            guard.available_capital() > 0.0
+        };
+        if !has_capital {
+            return false;
+        }
+
+        let params = self.params.get(&ctx.symbol);
+        Self::breaks_out(params, &Self::bars(params, ctx))
+    }
+
+    async fn go_long(
+        &self,
+        ctx: &ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> Option<Order> {
+        let params = self.params.get(&ctx.symbol);
+        let atr = Indicators::new(&Self::bars(params, ctx)).atr(params.atr_period)?;
+        let unit = atr * params.atr_multiple;
+        if unit <= 0.0 || ctx.last_close <= 0.0 {
+            return None;
+        }
+
+        let capital = shared_state.lock().await.available_capital();
+        let quantity =
+            (capital * params.risk_fraction / unit).min(params.max_notional / ctx.last_close);
+        if quantity <= 0.0 {
+            return None;
+        }
+
+        Some(Order {
+            pair: ctx.pair(),
+            side: OrderSide::Buy,
+            order_type: OrderType::Market,
+            quantity,
+            price: None,
+        })
+    }
+
+    async fn update_position(
+        &self,
+        ctx: &mut ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        let params = self.params.get(&ctx.symbol);
+        if params.exits.evaluate(ctx).is_some() {
+            return true;
+        }
+        if !ctx.position.is_open() {
+            return false;
+        }
+        let bars = Self::bars(params, ctx);
+        Indicators::new(&bars)
+            .lowest_low(params.exit_period, 1)
+            .is_some_and(|low| ctx.last_close < low)
+    }
+
+    fn protective_exits(&self, symbol: &str) -> Option<&ExitPolicies> {
+        Some(&self.params.get(symbol).exits)
+    }
+
+    fn reload(&mut self, config: &ConfigFile) -> Result<(), ConfigError> {
+        self.params = config.section_or_default(Self::SECTION)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one candle a second: (close, volume, trade count)
+    fn context(candles: &[(f64, f64, i64)]) -> ExecContext {
+        let candles: Vec<Candle> = candles
+            .iter()
+            .enumerate()
+            .map(|(i, &(close, volume, trade_count))| Candle {
+                close_time: (1_700_000_000 + i as u64) * 1_000,
+                open: close,
+                high: close + 0.5,
+                low: close - 0.5,
+                close,
+                volume,
+                trade_count,
+                ..Default::default()
+            })
+            .collect();
+        ExecContext::for_test("BTC", candles)
+    }
+
+    #[tokio::test]
+    async fn enters_on_confirmed_breaks_of_aggregated_bars_and_exits_on_the_low() {
+        let params = BreakoutParams {
+            timeframe_secs: 10,
+            entry_period: 5,
+            exit_period: 3,
+            atr_period: 3,
+            ..Default::default()
+        };
+        let strategy = Breakout::new(SymbolParams::new(params));
+        let shared = Arc::new(Mutex::new(SharedState::new(10_000.0)));
+
+        // 60 seconds of range trading around 100, i.e. six 10s bars
+        let mut feed: Vec<(f64, f64, i64)> = (0..60)
+            .map(|i| (100.0 + if i % 2 == 0 { 1.0 } else { -1.0 }, 1.0, 10))
+            .collect();
+        assert_eq!(aggregate(&context(&feed).candles, 10).len(), 6);
+
+        // a quiet push through the channel high is ignored
+        let mut quiet = feed.clone();
+        quiet.push((102.0, 1.0, 10));
+        assert!(
+            !strategy
+                .should_long(&mut context(&quiet), shared.clone())
+                .await
+        );
+
+        // the same push on heavy volume enters
+        feed.push((102.0, 30.0, 200));
+        let mut ctx = context(&feed);
+        assert!(strategy.should_long(&mut ctx, shared.clone()).await);
+        let order = strategy.go_long(&ctx, shared.clone()).await.unwrap();
+        assert!(order.quantity > 0.0);
+
+        // held through the next bars, out once price drops under their lows
+        feed.extend((0..30).map(|_| (102.0, 1.0, 10)));
+        let mut held = context(&feed);
+        held.position
+            .open_new(1.0, 102.0, 0.0, Some(1_700_000_060))
+            .unwrap();
+        assert!(!strategy.update_position(&mut held, shared.clone()).await);
+        feed.push((100.0, 1.0, 10));
+        let mut falling = context(&feed);
+        falling.position = held.position.clone();
+        assert!(strategy.update_position(&mut falling, shared).await);
+    }
+}
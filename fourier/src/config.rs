@@ -0,0 +1,457 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+use thiserror::Error;
+
+type Result<T> = std::result::Result<T, ConfigError>;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Could not read {0}: {1}")]
+    ReadError(String, std::io::Error),
+
+    #[error("YAML error: {0}")]
+    YamlError(#[from] serde_yaml::Error),
+
+    #[error("Missing section: {0}")]
+    MissingSection(String),
+
+    #[error("Unknown strategy: {0}")]
+    UnknownStrategy(String),
+
+    #[error("Script {0}: {1}")]
+    ScriptError(String, String),
+
+    #[error("Cannot reload: {0}")]
+    ReloadError(String),
+
+    #[error("[{scope}] {field} = {value} is out of range, expected {expected}")]
+    OutOfRange {
+        scope: String,
+        field: &'static str,
+        value: f64,
+        expected: String,
+    },
+}
+
+/// Typed parameters that can check their own ranges.
+/// `scope` names where they came from (e.g. `fourier.BTC`) for error messages.
+pub trait Validate {
+    fn validate(&self, scope: &str) -> Result<()>;
+}
+
+/// Fail with `OutOfRange` unless `min <= value <= max`.
+pub fn check_range(scope: &str, field: &'static str, value: f64, min: f64, max: f64) -> Result<()> {
+    if value.is_finite() && value >= min && value <= max {
+        return Ok(());
+    }
+    Err(ConfigError::OutOfRange {
+        scope: scope.to_string(),
+        field,
+        value,
+        expected: format!("[{}, {}]", min, max),
+    })
+}
+
+/// Fail with `OutOfRange` unless `lower < upper`, e.g. fast EMA below slow EMA.
+pub fn check_ordered(
+    scope: &str,
+    field: &'static str,
+    lower: f64,
+    upper: f64,
+    upper_name: &str,
+) -> Result<()> {
+    if lower < upper {
+        return Ok(());
+    }
+    Err(ConfigError::OutOfRange {
+        scope: scope.to_string(),
+        field,
+        value: lower,
+        expected: format!("< {} ({})", upper_name, upper),
+    })
+}
+
+/// Whole configuration file, kept as YAML until a strategy asks for its section.
+/// The same file drives live runs and `BackTester`.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigFile {
+    root: Value,
+}
+
+impl ConfigFile {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)
+            .map_err(|e| ConfigError::ReadError(path.display().to_string(), e))?;
+        Self::parse(&data)
+    }
+
+    pub fn parse(data: &str) -> Result<Self> {
+        let root: Value = serde_yaml::from_str(data)?;
+        Ok(Self { root })
+    }
+
+    pub fn raw(&self, name: &str) -> Option<&Value> {
+        self.root.get(name)
+    }
+
+    /// Parameters for strategy section `name` with per-symbol overrides resolved.
+    pub fn section<P>(&self, name: &str) -> Result<SymbolParams<P>>
+    where
+        P: DeserializeOwned + Serialize + Default + Validate,
+    {
+        let section = self
+            .raw(name)
+            .ok_or_else(|| ConfigError::MissingSection(name.to_string()))?;
+        SymbolParams::from_value(name, section)
+    }
+
+    /// A section without per-symbol overrides, merged onto `P::default()`.
+    /// None when the file has no such section.
+    pub fn settings<P>(&self, name: &str) -> Result<Option<P>>
+    where
+        P: DeserializeOwned + Serialize + Default + Validate,
+    {
+        let Some(section) = self.raw(name) else {
+            return Ok(None);
+        };
+        let mut base = serde_yaml::to_value(P::default())?;
+        merge(&mut base, section);
+        let settings: P = serde_yaml::from_value(base)?;
+        settings.validate(name)?;
+        Ok(Some(settings))
+    }
+
+    /// Like `section`, but an absent section yields defaults.
+    pub fn section_or_default<P>(&self, name: &str) -> Result<SymbolParams<P>>
+    where
+        P: DeserializeOwned + Serialize + Default + Validate,
+    {
+        match self.raw(name) {
+            Some(section) => SymbolParams::from_value(name, section),
+            None => Ok(SymbolParams::new(P::default())),
+        }
+    }
+}
+
+/// Default parameters plus resolved per-symbol overrides.
+///
+/// In YAML a section looks like
+/// ```yaml
+/// fourier:
+///   default:
+///     ema_fast: 12
+///   symbols:
+///     BONK:
+///       rsi_max: 50
+/// ```
+/// Symbol entries only list the fields that differ from `default`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolParams<P> {
+    pub default: P,
+    pub symbols: HashMap<String, P>,
+}
+
+impl<P> SymbolParams<P> {
+    pub fn new(default: P) -> Self {
+        Self {
+            default,
+            symbols: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, symbol: &str) -> &P {
+        self.symbols.get(symbol).unwrap_or(&self.default)
+    }
+}
+
+impl<P: Default> Default for SymbolParams<P> {
+    fn default() -> Self {
+        Self::new(P::default())
+    }
+}
+
+impl<P> SymbolParams<P>
+where
+    P: DeserializeOwned + Serialize + Default + Validate,
+{
+    pub fn from_value(name: &str, section: &Value) -> Result<Self> {
+        // start from the built-in defaults so the file only needs what it changes
+        let mut base = serde_yaml::to_value(P::default())?;
+        if let Some(default) = section.get("default") {
+            merge(&mut base, default);
+        }
+        let default: P = serde_yaml::from_value(base.clone())?;
+        default.validate(name)?;
+
+        let mut symbols = HashMap::new();
+        if let Some(Value::Mapping(overrides)) = section.get("symbols") {
+            for (symbol, patch) in overrides {
+                let Some(symbol) = symbol.as_str() else {
+                    continue;
+                };
+                let mut merged = base.clone();
+                merge(&mut merged, patch);
+                let params: P = serde_yaml::from_value(merged)?;
+                params.validate(&format!("{}.{}", name, symbol))?;
+                symbols.insert(symbol.to_string(), params);
+            }
+        }
+
+        Ok(Self { default, symbols })
+    }
+}
+
+/// Modification time and length of `path`, to notice edits to it
+pub fn fingerprint(path: &str) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Every setting that differs between `old` and `new`, one line each as
+/// `section.key: old -> new`. Lists are compared whole.
+pub fn diff(old: &ConfigFile, new: &ConfigFile) -> Vec<String> {
+    let mut changes = Vec::new();
+    diff_values("", Some(&old.root), Some(&new.root), &mut changes);
+    changes
+}
+
+fn diff_values(path: &str, old: Option<&Value>, new: Option<&Value>, changes: &mut Vec<String>) {
+    if let (Some(Value::Mapping(old)), Some(Value::Mapping(new))) = (old, new) {
+        let added = new.keys().filter(|key| !old.contains_key(*key));
+        for key in old.keys().chain(added) {
+            let name = match key {
+                Value::String(name) => name.clone(),
+                other => serde_json::to_string(other).unwrap_or_default(),
+            };
+            let child = if path.is_empty() {
+                name
+            } else {
+                format!("{}.{}", path, name)
+            };
+            diff_values(&child, old.get(key), new.get(key), changes);
+        }
+        return;
+    }
+    if old != new {
+        let show = |value: Option<&Value>| match value {
+            Some(value) => serde_json::to_string(value).unwrap_or_default(),
+            None => "(unset)".to_string(),
+        };
+        changes.push(format!("{}: {} -> {}", path, show(old), show(new)));
+    }
+}
+
+/// How often a watched config file is checked for changes
+pub const CONFIG_POLL_SECS: u64 = 5;
+
+/// The config file a live bot was started from, re-read when it changes on
+/// disk. `current` is what the bot is running with.
+pub struct ConfigWatch {
+    path: String,
+    current: ConfigFile,
+    fingerprint: Option<(SystemTime, u64)>,
+    next_check: u64,
+}
+
+impl ConfigWatch {
+    pub fn new(path: &str, current: ConfigFile) -> Self {
+        Self {
+            path: path.to_string(),
+            current,
+            fingerprint: fingerprint(path),
+            next_check: 0,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn current(&self) -> &ConfigFile {
+        &self.current
+    }
+
+    /// The file's new contents if it changed since the last check. Files
+    /// that cannot be read or parsed are logged and skipped until they
+    /// change again.
+    pub fn poll(&mut self, now: u64) -> Option<ConfigFile> {
+        if now < self.next_check {
+            return None;
+        }
+        self.next_check = now + CONFIG_POLL_SECS;
+        let current = fingerprint(&self.path);
+        if current == self.fingerprint {
+            return None;
+        }
+        self.fingerprint = current;
+        match ConfigFile::load(&self.path) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                println!("[ERROR][CONFIG] Ignoring edit to {}: {}", self.path, e);
+                None
+            }
+        }
+    }
+
+    /// Make `config` what the bot is running with
+    pub fn accept(&mut self, config: ConfigFile) {
+        self.current = config;
+    }
+}
+
+// recursively overlay `patch` onto `base`. sequences are replaced, not
+// appended, and so are mappings that switch to another `kind` of tagged enum,
+// whose fields would not fit the new variant
+pub(crate) fn merge(base: &mut Value, patch: &Value) {
+    match (base, patch) {
+        (Value::Mapping(base), Value::Mapping(patch))
+            if patch
+                .get("kind")
+                .is_none_or(|kind| base.get("kind").is_none_or(|base| base == kind)) =>
+        {
+            for (key, value) in patch {
+                match base.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, patch) => *base = patch.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[serde(default)]
+    struct Params {
+        period: usize,
+        threshold: f64,
+    }
+
+    impl Default for Params {
+        fn default() -> Self {
+            Self {
+                period: 14,
+                threshold: 60.0,
+            }
+        }
+    }
+
+    impl Validate for Params {
+        fn validate(&self, scope: &str) -> Result<()> {
+            check_range(scope, "threshold", self.threshold, 0.0, 100.0)
+        }
+    }
+
+    #[test]
+    fn symbol_overrides_merge_onto_default() {
+        let file = ConfigFile::parse(
+            "test:\n  default:\n    period: 20\n  symbols:\n    BONK:\n      threshold: 40\n",
+        )
+        .unwrap();
+        let params: SymbolParams<Params> = file.section("test").unwrap();
+        assert_eq!(params.get("BTC").period, 20);
+        assert_eq!(params.get("BTC").threshold, 60.0);
+        assert_eq!(params.get("BONK").period, 20);
+        assert_eq!(params.get("BONK").threshold, 40.0);
+    }
+
+    #[test]
+    fn sizer_kind_can_change_in_default_and_per_symbol() {
+        use crate::fourier::FourierParams;
+        use crate::sizing::{EqualRisk, Sizing, VolatilityTarget};
+        let section: Value = serde_yaml::from_str(
+            "default:\n  sizing:\n    kind: volatility_target\n    target_vol: 0.4\n\
+             symbols:\n  BONK:\n    sizing:\n      kind: equal_risk\n",
+        )
+        .unwrap();
+        let params = SymbolParams::<FourierParams>::from_value("fourier", &section).unwrap();
+        assert_eq!(
+            params.get("BTC").sizing,
+            Sizing::VolatilityTarget(VolatilityTarget {
+                target_vol: 0.4,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            params.get("BONK").sizing,
+            Sizing::EqualRisk(EqualRisk::default())
+        );
+    }
+
+    #[test]
+    fn out_of_range_override_is_rejected() {
+        let file =
+            ConfigFile::parse("test:\n  symbols:\n    WIF:\n      threshold: 140\n").unwrap();
+        match file.section::<Params>("test") {
+            Err(ConfigError::OutOfRange { scope, field, .. }) => {
+                assert_eq!(scope, "test.WIF");
+                assert_eq!(field, "threshold");
+            }
+            other => panic!("expected OutOfRange, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sample_config_is_valid() {
+        let file = ConfigFile::parse(include_str!("../config.yaml")).unwrap();
+        let fourier = crate::fourier::Fourier::from_config(&file).unwrap();
+        assert_eq!(fourier.params.get("BTC").ema_fast, 12);
+        assert_eq!(
+            fourier.params.get("BONK").sizing,
+            crate::sizing::Sizing::FixedFractional(crate::sizing::FixedFractional {
+                risk_fraction: 0.01,
+                atr_period: 14,
+            })
+        );
+        crate::bollinger::Bollinger::from_config(&file).unwrap();
+        crate::breakout::Breakout::from_config(&file).unwrap();
+        crate::ensemble::Ensemble::from_config(&file).unwrap();
+        crate::grid::Grid::from_config(&file).unwrap();
+        crate::market_maker::MarketMaker::from_config(&file).unwrap();
+        crate::momentum::Momentum::from_config(&file).unwrap();
+        crate::pairs::PairsTrading::from_config(&file).unwrap();
+        crate::scripted::ScriptedStrategy::from_config(&file).unwrap();
+        crate::warmup::WarmupConfig::from_config(&file).unwrap();
+        crate::snapshot::SnapshotConfig::from_config(&file)
+            .unwrap()
+            .unwrap();
+    }
+
+    #[test]
+    fn watch_reports_edits_as_a_diff() {
+        let path = std::env::temp_dir().join(format!("fourier-watch-{}.yaml", std::process::id()));
+        let path = path.display().to_string();
+        fs::write(&path, "risk:\n  max_symbol_notional: 100.0\n").unwrap();
+        let mut watch = ConfigWatch::new(&path, ConfigFile::load(&path).unwrap());
+        assert!(watch.poll(0).is_none());
+
+        fs::write(
+            &path,
+            "risk:\n  max_symbol_notional: 250.0\nfourier:\n  default:\n    ema_slow: 30\n",
+        )
+        .unwrap();
+        assert!(watch.poll(1).is_none());
+        let edited = watch.poll(CONFIG_POLL_SECS).unwrap();
+        assert_eq!(
+            diff(watch.current(), &edited),
+            [
+                "risk.max_symbol_notional: 100.0 -> 250.0",
+                "fourier: (unset) -> {\"default\":{\"ema_slow\":30}}",
+            ]
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Rolling return statistics across every traded symbol.
+///
+/// Candles for different symbols arrive at slightly different times, so the
+/// latest close of each symbol is sampled on a shared clock every
+/// `sample_secs`. Each sample becomes one row of log returns; statistics use
+/// the last `window` rows in which both symbols have a return.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorrelationEstimator {
+    sample_secs: u64,
+    window: usize,
+    latest: HashMap<String, f64>,         // last close seen per symbol
+    previous: HashMap<String, f64>,       // closes at the last sample
+    rows: VecDeque<HashMap<String, f64>>, // log returns per sample, oldest first
+    bucket: Option<u64>,
+}
+
+impl CorrelationEstimator {
+    pub fn new(sample_secs: u64, window: usize) -> Self {
+        Self {
+            sample_secs: sample_secs.max(1),
+            window: window.max(2),
+            latest: HashMap::new(),
+            previous: HashMap::new(),
+            rows: VecDeque::new(),
+            bucket: None,
+        }
+    }
+
+    /// Feed a close for `symbol` at unix seconds `time`.
+    pub fn update(&mut self, symbol: &str, time: u64, close: f64) {
+        if close <= 0.0 || !close.is_finite() {
+            return;
+        }
+        let bucket = time / self.sample_secs;
+        match self.bucket {
+            None => self.bucket = Some(bucket),
+            Some(current) if bucket > current => {
+                self.sample();
+                self.bucket = Some(bucket);
+            }
+            Some(_) => {}
+        }
+        self.latest.insert(symbol.to_string(), close);
+    }
+
+    // close the current bucket: one row of returns from the previous sample
+    fn sample(&mut self) {
+        let row: HashMap<String, f64> = self
+            .latest
+            .iter()
+            .filter_map(|(symbol, close)| {
+                let previous = self.previous.get(symbol)?;
+                Some((symbol.clone(), (close / previous).ln()))
+            })
+            .collect();
+        self.previous = self.latest.clone();
+        if row.is_empty() {
+            return;
+        }
+        self.rows.push_back(row);
+        if self.rows.len() > self.window {
+            self.rows.pop_front();
+        }
+    }
+
+    // aligned returns of `a` and `b`
+    fn pairs(&self, a: &str, b: &str) -> Vec<(f64, f64)> {
+        self.rows
+            .iter()
+            .filter_map(|row| Some((*row.get(a)?, *row.get(b)?)))
+            .collect()
+    }
+
+    // sample count, covariance and both variances over the shared samples
+    fn moments(&self, a: &str, b: &str) -> Option<(usize, f64, f64, f64)> {
+        let pairs = self.pairs(a, b);
+        if pairs.len() < 2 {
+            return None;
+        }
+        let n = pairs.len() as f64;
+        let mean_a = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_b = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+        let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+        for (x, y) in &pairs {
+            cov += (x - mean_a) * (y - mean_b);
+            var_a += (x - mean_a).powi(2);
+            var_b += (y - mean_b).powi(2);
+        }
+        let d = n - 1.0;
+        Some((pairs.len(), cov / d, var_a / d, var_b / d))
+    }
+
+    /// Sample count and covariance of the returns of `a` and `b`
+    pub fn covariance(&self, a: &str, b: &str) -> Option<(usize, f64)> {
+        self.moments(a, b).map(|(n, cov, _, _)| (n, cov))
+    }
+
+    /// Pearson correlation, None with fewer than `min_samples` shared returns
+    pub fn correlation(&self, a: &str, b: &str, min_samples: usize) -> Option<f64> {
+        if a == b {
+            return Some(1.0);
+        }
+        let (n, cov, var_a, var_b) = self.moments(a, b)?;
+        if n < min_samples || var_a <= 0.0 || var_b <= 0.0 {
+            return None;
+        }
+        Some((cov / (var_a * var_b).sqrt()).clamp(-1.0, 1.0))
+    }
+
+    /// Beta of `symbol` against `benchmark`
+    pub fn beta(&self, symbol: &str, benchmark: &str, min_samples: usize) -> Option<f64> {
+        if symbol == benchmark {
+            return Some(1.0);
+        }
+        let (n, cov, _, var) = self.moments(symbol, benchmark)?;
+        if n < min_samples || var <= 0.0 {
+            return None;
+        }
+        Some(cov / var)
+    }
+
+    /// Covariance matrix of `symbols` in the given order; pairs without
+    /// enough shared history get zero covariance
+    pub fn covariance_matrix(&self, symbols: &[String], min_samples: usize) -> Vec<Vec<f64>> {
+        symbols
+            .iter()
+            .map(|a| {
+                symbols
+                    .iter()
+                    .map(|b| match self.covariance(a, b) {
+                        Some((n, cov)) if n >= min_samples => cov,
+                        _ => 0.0,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Mean per-sample log return of `symbol`
+    pub fn mean_return(&self, symbol: &str) -> Option<f64> {
+        let returns: Vec<f64> = self
+            .rows
+            .iter()
+            .filter_map(|row| row.get(symbol).copied())
+            .collect();
+        if returns.is_empty() {
+            return None;
+        }
+        Some(returns.iter().sum::<f64>() / returns.len() as f64)
+    }
+
+    /// Samples of returns currently held
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn sample_secs(&self) -> u64 {
+        self.sample_secs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn beta_and_correlation_of_a_levered_follower() {
+        let mut estimator = CorrelationEstimator::new(60, 100);
+        let mut btc = 100.0;
+        let mut alt = 10.0;
+        for i in 0..60u64 {
+            // alt moves twice as much as BTC, in the same direction
+            let step: f64 = if i % 3 == 0 { 0.01 } else { -0.004 };
+            btc *= step.exp();
+            alt *= (2.0 * step).exp();
+            let time = 1_700_000_000 + i * 60;
+            estimator.update("BTC", time, btc);
+            estimator.update("ALT", time + 1, alt);
+        }
+        let beta = estimator.beta("ALT", "BTC", 10).unwrap();
+        let corr = estimator.correlation("ALT", "BTC", 10).unwrap();
+        assert!((beta - 2.0).abs() < 1e-6, "beta {}", beta);
+        assert!((corr - 1.0).abs() < 1e-6, "corr {}", corr);
+        assert!(estimator.beta("ALT", "BTC", 1000).is_none());
+    }
+}
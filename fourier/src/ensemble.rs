@@ -0,0 +1,514 @@
+use crate::bollinger::Bollinger;
+use crate::breakout::Breakout;
+use crate::config::{ConfigError, ConfigFile, Validate, check_range};
+use crate::fourier::Fourier;
+use crate::grid::Grid;
+use crate::market_maker::MarketMaker;
+use crate::momentum::Momentum;
+use crate::pairs::PairsTrading;
+use crate::registry;
+use crate::roostoo::OrderDetail;
+use crate::strategy::{ExecContext, Order, SharedState, Strategy};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// How member votes become one decision.
+///
+/// ```yaml
+/// entry:
+///   kind: weighted
+///   threshold: 0.6
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Vote {
+    Unanimous,
+    Majority,
+    /// Yes once the voting members hold `threshold` of the total weight.
+    Weighted {
+        threshold: f64,
+    },
+}
+
+impl Vote {
+    /// True when members with `weights` voting `yes` carry the decision
+    pub fn carries(&self, weights: &[f64], yes: &[bool]) -> bool {
+        let voters = yes.iter().filter(|&&v| v).count();
+        match self {
+            Vote::Unanimous => !yes.is_empty() && voters == yes.len(),
+            Vote::Majority => voters * 2 > yes.len(),
+            Vote::Weighted { threshold } => {
+                let total: f64 = weights.iter().sum();
+                let agreed: f64 = weights
+                    .iter()
+                    .zip(yes)
+                    .filter(|(_, v)| **v)
+                    .map(|(w, _)| w)
+                    .sum();
+                total > 0.0 && agreed / total >= *threshold
+            }
+        }
+    }
+}
+
+/// How the entry sizes of the members that voted to enter are combined.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeRule {
+    Average,
+    Min,
+    /// Each voter's size times its share of the total weight, so an entry
+    /// only some members back is scaled down.
+    WeightedSum,
+}
+
+impl SizeRule {
+    /// `sizes` are (weight, quantity) of the voters; `total_weight` is over
+    /// every member
+    pub fn combine(&self, sizes: &[(f64, f64)], total_weight: f64) -> Option<f64> {
+        if sizes.is_empty() {
+            return None;
+        }
+        let quantity = match self {
+            SizeRule::Average => sizes.iter().map(|(_, q)| q).sum::<f64>() / sizes.len() as f64,
+            SizeRule::Min => sizes.iter().map(|(_, q)| *q).fold(f64::INFINITY, f64::min),
+            SizeRule::WeightedSum if total_weight > 0.0 => {
+                sizes.iter().map(|(w, q)| w * q).sum::<f64>() / total_weight
+            }
+            SizeRule::WeightedSum => 0.0,
+        };
+        (quantity > 0.0).then_some(quantity)
+    }
+}
+
+/// One member of the ensemble; its own parameters come from its section.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MemberParams {
+    pub strategy: String, // section name, e.g. fourier
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+/// Tuning for `Ensemble`, loaded from the `ensemble` section of the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnsembleParams {
+    pub entry: Vote,
+    pub exit: Vote, // over the members' exit signals; their stops need no vote
+    pub size: SizeRule,
+    pub members: Vec<MemberParams>,
+}
+
+impl Default for EnsembleParams {
+    fn default() -> Self {
+        EnsembleParams {
+            entry: Vote::Majority,
+            exit: Vote::Majority,
+            size: SizeRule::Average,
+            members: [Fourier::SECTION, Bollinger::SECTION, Breakout::SECTION]
+                .into_iter()
+                .map(|name| MemberParams {
+                    strategy: name.to_string(),
+                    weight: 1.0,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Validate for EnsembleParams {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        for vote in [&self.entry, &self.exit] {
+            if let Vote::Weighted { threshold } = vote {
+                check_range(scope, "threshold", *threshold, 0.0, 1.0)?;
+            }
+        }
+        check_range(scope, "members", self.members.len() as f64, 1.0, 16.0)?;
+        for member in &self.members {
+            check_range(scope, "weight", member.weight, 0.0, 1_000.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// Combines the entry and exit signals of several candle strategies by
+/// vote, e.g. only buying when `Fourier` and a mean-reversion strategy
+/// agree. The members that voted to enter each size the entry and their
+/// sizes are merged by `SizeRule`. Exit signals are voted on too, but any
+/// member's protective exit, e.g. a stop loss, closes the position by
+/// itself. Every lifecycle hook reaches every member.
+pub struct Ensemble {
+    pub params: EnsembleParams,
+    members: Vec<Box<dyn Strategy>>,
+    // members that voted for the pending entry, by symbol
+    voters: std::sync::Mutex<HashMap<String, Vec<usize>>>,
+}
+
+impl Ensemble {
+    pub const SECTION: &'static str = "ensemble";
+
+    /// `members` pair up with `params.members` for their weights
+    pub fn new(params: EnsembleParams, members: Vec<Box<dyn Strategy>>) -> Self {
+        Ensemble {
+            params,
+            members,
+            voters: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_config(config: &ConfigFile) -> Result<Self, ConfigError> {
+        let params: EnsembleParams = config.settings(Self::SECTION)?.unwrap_or_default();
+        let members = params
+            .members
+            .iter()
+            .map(|m| match m.strategy.as_str() {
+                // an ensemble inside itself would never finish building, and
+                // resting orders and cross-symbol allocation are not voted on,
+                // so those strategies would never trade as members
+                Self::SECTION
+                | Grid::SECTION
+                | MarketMaker::SECTION
+                | Momentum::SECTION
+                | PairsTrading::SECTION => Err(ConfigError::UnknownStrategy(format!(
+                    "{} as an ensemble member",
+                    m.strategy
+                ))),
+                name => registry::build(name, config),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(params, members))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::from_config(&ConfigFile::load(path)?)
+    }
+
+    fn weights(&self) -> Vec<f64> {
+        self.params.members.iter().map(|m| m.weight).collect()
+    }
+}
+
+#[async_trait]
+impl Strategy for Ensemble {
+    async fn should_long(
+        &self,
+        ctx: &mut ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        let mut yes = Vec::with_capacity(self.members.len());
+        for member in &self.members {
+            yes.push(member.should_long(ctx, shared_state.clone()).await);
+        }
+        let carried = self.params.entry.carries(&self.weights(), &yes);
+        let voters = yes
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| **v)
+            .map(|(i, _)| i)
+            .collect();
+        if let Ok(mut pending) = self.voters.lock() {
+            pending.insert(ctx.symbol.clone(), voters);
+        }
+        carried
+    }
+
+    async fn go_long(
+        &self,
+        ctx: &ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> Option<Order> {
+        let voters = self
+            .voters
+            .lock()
+            .ok()
+            .and_then(|mut pending| pending.remove(&ctx.symbol))
+            .unwrap_or_default();
+        let weights = self.weights();
+        let mut sizes = Vec::with_capacity(voters.len());
+        let mut template = None;
+        for i in voters {
+            if let Some(order) = self.members[i].go_long(ctx, shared_state.clone()).await {
+                sizes.push((weights[i], order.quantity));
+                template.get_or_insert(order);
+            }
+        }
+        let quantity = self.params.size.combine(&sizes, weights.iter().sum())?;
+        Some(Order {
+            quantity,
+            ..template?
+        })
+    }
+
+    async fn update_position(
+        &self,
+        ctx: &mut ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        // every member sees every candle, so exit state stays current
+        let mut yes = Vec::with_capacity(self.members.len());
+        for member in &self.members {
+            yes.push(member.update_position(ctx, shared_state.clone()).await);
+        }
+        let protected = self.members.iter().zip(&yes).any(|(member, exit)| {
+            *exit
+                && member
+                    .protective_exits(&ctx.symbol)
+                    .is_some_and(|exits| exits.triggered(ctx).is_some())
+        });
+        protected || self.params.exit.carries(&self.weights(), &yes)
+    }
+
+    async fn on_start(
+        &mut self,
+        positions: &HashMap<String, f64>,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) {
+        for member in &mut self.members {
+            member.on_start(positions, shared_state.clone()).await;
+        }
+    }
+
+    async fn on_fill(
+        &mut self,
+        ctx: &ExecContext,
+        fill: &OrderDetail,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) {
+        for member in &mut self.members {
+            member.on_fill(ctx, fill, shared_state.clone()).await;
+        }
+    }
+
+    async fn on_reject(
+        &mut self,
+        ctx: &ExecContext,
+        order: &Order,
+        reason: &str,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) {
+        for member in &mut self.members {
+            member
+                .on_reject(ctx, order, reason, shared_state.clone())
+                .await;
+        }
+    }
+
+    fn timers(&self) -> Vec<u64> {
+        let mut timers: Vec<u64> = self.members.iter().flat_map(|m| m.timers()).collect();
+        timers.sort_unstable();
+        timers.dedup();
+        timers
+    }
+
+    async fn on_timer(
+        &mut self,
+        interval_secs: u64,
+        now: u64,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) {
+        for member in &mut self.members {
+            if member.timers().contains(&interval_secs) {
+                member
+                    .on_timer(interval_secs, now, shared_state.clone())
+                    .await;
+            }
+        }
+    }
+
+    async fn on_stop(&mut self, shared_state: Arc<Mutex<SharedState>>) {
+        for member in &mut self.members {
+            member.on_stop(shared_state.clone()).await;
+        }
+    }
+
+    fn reload(&mut self, config: &ConfigFile) -> Result<(), ConfigError> {
+        let params: EnsembleParams = config.settings(Self::SECTION)?.unwrap_or_default();
+        let names = |params: &EnsembleParams| -> Vec<String> {
+            params.members.iter().map(|m| m.strategy.clone()).collect()
+        };
+        if names(&params) != names(&self.params) {
+            return Err(ConfigError::ReloadError(
+                "ensemble members changed, restart to apply".to_string(),
+            ));
+        }
+        // building fresh members checks every section before any is swapped
+        for name in names(&params) {
+            registry::build(&name, config)?;
+        }
+        for member in &mut self.members {
+            member.reload(config)?;
+        }
+        self.params = params;
+        Ok(())
+    }
+
+    // each member's state, in member order
+    fn snapshot(&self) -> Option<serde_json::Value> {
+        let states: Vec<Option<serde_json::Value>> = self
+            .members
+            .iter()
+            .map(|member| member.snapshot())
+            .collect();
+        serde_json::to_value(states).ok()
+    }
+
+    fn restore(&mut self, state: serde_json::Value) -> Result<(), serde_json::Error> {
+        let states: Vec<Option<serde_json::Value>> = serde_json::from_value(state)?;
+        if states.len() != self.members.len() {
+            return Err(serde::de::Error::custom(format!(
+                "snapshot has {} ensemble members, config has {}",
+                states.len(),
+                self.members.len()
+            )));
+        }
+        for (member, state) in self.members.iter_mut().zip(states) {
+            if let Some(state) = state {
+                member.restore(state)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fourier::Position;
+    use crate::roostoo::{OrderSide, OrderType};
+
+    // votes `long` and sizes `quantity`, whatever the market does
+    struct Fixed {
+        long: bool,
+        quantity: f64,
+    }
+
+    #[async_trait]
+    impl Strategy for Fixed {
+        async fn should_long(&self, _: &mut ExecContext, _: Arc<Mutex<SharedState>>) -> bool {
+            self.long
+        }
+
+        async fn go_long(&self, ctx: &ExecContext, _: Arc<Mutex<SharedState>>) -> Option<Order> {
+            Some(Order {
+                pair: ctx.pair(),
+                side: OrderSide::Buy,
+                order_type: OrderType::Market,
+                quantity: self.quantity,
+                price: None,
+            })
+        }
+
+        async fn update_position(&self, _: &mut ExecContext, _: Arc<Mutex<SharedState>>) -> bool {
+            !self.long
+        }
+    }
+
+    fn ensemble(entry: Vote, size: SizeRule, members: &[(bool, f64, f64)]) -> Ensemble {
+        let params = EnsembleParams {
+            entry,
+            exit: Vote::Majority,
+            size,
+            members: members
+                .iter()
+                .map(|(_, _, weight)| MemberParams {
+                    strategy: "fixed".to_string(),
+                    weight: *weight,
+                })
+                .collect(),
+        };
+        let strategies = members
+            .iter()
+            .map(|&(long, quantity, _)| Box::new(Fixed { long, quantity }) as Box<dyn Strategy>)
+            .collect();
+        Ensemble::new(params, strategies)
+    }
+
+    async fn entry(ensemble: &Ensemble) -> Option<f64> {
+        let shared = Arc::new(Mutex::new(SharedState::new(10_000.0)));
+        let mut ctx = ExecContext {
+            last_close: 100.0,
+            ..ExecContext::for_test("BTC", Vec::new())
+        };
+        if !ensemble.should_long(&mut ctx, shared.clone()).await {
+            return None;
+        }
+        ensemble.go_long(&ctx, shared).await.map(|o| o.quantity)
+    }
+
+    #[tokio::test]
+    async fn votes_and_sizes_combine_across_members() {
+        // two of three want in, with sizes 2 and 4; the third would size 30
+        let members = [(true, 2.0, 1.0), (true, 4.0, 3.0), (false, 30.0, 1.0)];
+
+        assert_eq!(
+            entry(&ensemble(Vote::Unanimous, SizeRule::Average, &members)).await,
+            None
+        );
+        assert_eq!(
+            entry(&ensemble(Vote::Majority, SizeRule::Average, &members)).await,
+            Some(3.0)
+        );
+        assert_eq!(
+            entry(&ensemble(Vote::Majority, SizeRule::Min, &members)).await,
+            Some(2.0)
+        );
+        // voters hold 4 of 5 weight: (1 * 2 + 3 * 4) / 5
+        let weighted = Vote::Weighted { threshold: 0.8 };
+        assert_eq!(
+            entry(&ensemble(weighted, SizeRule::WeightedSum, &members)).await,
+            Some(2.8)
+        );
+        let strict = Vote::Weighted { threshold: 0.9 };
+        assert_eq!(
+            entry(&ensemble(strict, SizeRule::WeightedSum, &members)).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn one_members_stop_loss_closes_without_a_vote() {
+        // Fourier stops out 2% below entry; the others never vote to exit
+        let members: Vec<Box<dyn Strategy>> = vec![
+            Box::new(Fourier::from_config(&ConfigFile::default()).unwrap()),
+            Box::new(Fixed {
+                long: true,
+                quantity: 1.0,
+            }),
+            Box::new(Fixed {
+                long: true,
+                quantity: 1.0,
+            }),
+        ];
+        let params = EnsembleParams {
+            exit: Vote::Unanimous,
+            ..Default::default()
+        };
+        let ensemble = Ensemble::new(params, members);
+        let shared = Arc::new(Mutex::new(SharedState::new(10_000.0)));
+        let mut position = Position::empty("BTC");
+        position.add_fill(1.0, 100.0, 0.0, Some(0)).unwrap();
+        let mut ctx = ExecContext {
+            position,
+            last_close: 99.0,
+            ..ExecContext::for_test("BTC", Vec::new())
+        };
+        assert!(!ensemble.update_position(&mut ctx, shared.clone()).await);
+        ctx.last_close = 97.0;
+        assert!(ensemble.update_position(&mut ctx, shared).await);
+
+        let config = ConfigFile::parse("ensemble:\n  members:\n    - strategy: grid\n").unwrap();
+        assert!(matches!(
+            Ensemble::from_config(&config),
+            Err(ConfigError::UnknownStrategy(reason)) if reason.starts_with("grid")
+        ));
+    }
+}
@@ -0,0 +1,295 @@
+use crate::config::{ConfigError, Validate, check_range};
+use crate::indicators::Indicators;
+use crate::strategy::ExecContext;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Per-position bookkeeping used by exit policies.
+/// Lives on `Position` so it is saved and restored together with the position.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExitState {
+    pub high_water_mark: f64,   // highest mark price seen since entry_time
+    pub entry_atr: Option<f64>, // ATR captured on the first evaluation after entry
+    pub break_even_armed: bool, // set once the break-even trigger was reached
+    pub last_mark_time: u64,    // unix seconds of the last mark
+}
+
+/// A single exit rule. Several can be combined with `ExitPolicies`.
+/// Percentages are in percent, e.g. `2.0` means 2%.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExitPolicy {
+    /// Exit below `-stop_loss_pct` or above `take_profit_pct` from entry.
+    FixedPercent {
+        stop_loss_pct: f64,
+        take_profit_pct: f64,
+    },
+    /// Exit when price falls `multiple` ATRs below the entry price.
+    AtrStop { period: usize, multiple: f64 },
+    /// Exit when price falls `trail_pct` below the high-water mark.
+    TrailingStop { trail_pct: f64 },
+    /// Once the position is `trigger_pct` in profit, exit if price drops
+    /// back to entry plus `offset_pct`.
+    BreakEven { trigger_pct: f64, offset_pct: f64 },
+    /// Exit after holding for `seconds`.
+    MaxHoldingTime { seconds: u64 },
+    /// Exit when the fast EMA crosses below the slow EMA.
+    IndicatorReversal { fast: usize, slow: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitReason {
+    StopLoss,
+    TakeProfit,
+    AtrStop,
+    TrailingStop,
+    BreakEven,
+    TimeStop,
+    IndicatorReversal,
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitReason::StopLoss => write!(f, "STOP_LOSS"),
+            ExitReason::TakeProfit => write!(f, "TAKE_PROFIT"),
+            ExitReason::AtrStop => write!(f, "ATR_STOP"),
+            ExitReason::TrailingStop => write!(f, "TRAILING_STOP"),
+            ExitReason::BreakEven => write!(f, "BREAK_EVEN"),
+            ExitReason::TimeStop => write!(f, "TIME_STOP"),
+            ExitReason::IndicatorReversal => write!(f, "INDICATOR_REVERSAL"),
+        }
+    }
+}
+
+impl ExitPolicy {
+    fn check(&self, ctx: &ExecContext) -> Option<ExitReason> {
+        let position = &ctx.position;
+        let state = &position.exit_state;
+        let price = ctx.last_close;
+        let entry = position.entry_price;
+
+        match *self {
+            ExitPolicy::FixedPercent {
+                stop_loss_pct,
+                take_profit_pct,
+            } => {
+                let pct = position.unrealized_pct(price)?;
+                if pct <= -stop_loss_pct {
+                    Some(ExitReason::StopLoss)
+                } else if pct >= take_profit_pct {
+                    Some(ExitReason::TakeProfit)
+                } else {
+                    None
+                }
+            }
+            ExitPolicy::AtrStop { multiple, .. } => {
+                let atr = state.entry_atr?;
+                (price <= entry - multiple * atr).then_some(ExitReason::AtrStop)
+            }
+            ExitPolicy::TrailingStop { trail_pct } => {
+                let stop = state.high_water_mark * (1.0 - trail_pct / 100.0);
+                (price <= stop).then_some(ExitReason::TrailingStop)
+            }
+            ExitPolicy::BreakEven { offset_pct, .. } => {
+                let stop = entry * (1.0 + offset_pct / 100.0);
+                (state.break_even_armed && price <= stop).then_some(ExitReason::BreakEven)
+            }
+            ExitPolicy::MaxHoldingTime { seconds } => {
+                let opened = position.entry_time?;
+                (state.last_mark_time >= opened + seconds).then_some(ExitReason::TimeStop)
+            }
+            ExitPolicy::IndicatorReversal { fast, slow } => {
+                let indicators = Indicators::new(&ctx.candles);
+                match (indicators.ema(fast), indicators.ema(slow)) {
+                    (Some(fast), Some(slow)) if fast < slow => Some(ExitReason::IndicatorReversal),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    // updates state that depends on the policy's parameters
+    fn observe(&self, ctx: &ExecContext, state: &mut ExitState) {
+        match *self {
+            ExitPolicy::AtrStop { period, .. } if state.entry_atr.is_none() => {
+                state.entry_atr = Indicators::new(&ctx.candles).atr(period);
+            }
+            ExitPolicy::BreakEven { trigger_pct, .. } => {
+                let trigger = ctx.position.entry_price * (1.0 + trigger_pct / 100.0);
+                if state.high_water_mark >= trigger {
+                    state.break_even_armed = true;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// An ordered set of exit policies; the first one that triggers wins.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(transparent)]
+pub struct ExitPolicies(pub Vec<ExitPolicy>);
+
+impl ExitPolicies {
+    pub fn new(policies: Vec<ExitPolicy>) -> Self {
+        Self(policies)
+    }
+
+    /// Update the exit state on `ctx.position` and return the reason to
+    /// liquidate, if any.
+    pub fn evaluate(&self, ctx: &mut ExecContext) -> Option<ExitReason> {
+        if !ctx.position.is_open() {
+            return None;
+        }
+
+        let mut state = ctx.position.exit_state.clone();
+        for policy in &self.0 {
+            policy.observe(ctx, &mut state);
+        }
+        ctx.position.exit_state = state;
+
+        self.0.iter().find_map(|policy| policy.check(ctx))
+    }
+
+    /// The reason to liquidate as of the last `evaluate`, without updating
+    /// the exit state again
+    pub fn triggered(&self, ctx: &ExecContext) -> Option<ExitReason> {
+        if !ctx.position.is_open() {
+            return None;
+        }
+        self.0.iter().find_map(|policy| policy.check(ctx))
+    }
+}
+
+impl Validate for ExitPolicies {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        for policy in &self.0 {
+            match *policy {
+                ExitPolicy::FixedPercent {
+                    stop_loss_pct,
+                    take_profit_pct,
+                } => {
+                    check_range(scope, "stop_loss_pct", stop_loss_pct, 0.0, 100.0)?;
+                    check_range(scope, "take_profit_pct", take_profit_pct, 0.0, 1000.0)?;
+                }
+                ExitPolicy::AtrStop { period, multiple } => {
+                    check_range(scope, "period", period as f64, 1.0, 1024.0)?;
+                    check_range(scope, "multiple", multiple, 0.0, 100.0)?;
+                }
+                ExitPolicy::TrailingStop { trail_pct } => {
+                    check_range(scope, "trail_pct", trail_pct, 0.0, 100.0)?;
+                }
+                ExitPolicy::BreakEven {
+                    trigger_pct,
+                    offset_pct,
+                } => {
+                    check_range(scope, "trigger_pct", trigger_pct, 0.0, 1000.0)?;
+                    check_range(scope, "offset_pct", offset_pct, -100.0, trigger_pct)?;
+                }
+                ExitPolicy::MaxHoldingTime { seconds } => {
+                    check_range(scope, "seconds", seconds as f64, 1.0, f64::MAX)?;
+                }
+                ExitPolicy::IndicatorReversal { fast, slow } => {
+                    check_range(scope, "fast", fast as f64, 1.0, 1024.0)?;
+                    check_range(scope, "slow", slow as f64, fast as f64 + 1.0, 2048.0)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fourier::{Candle, Position};
+
+    fn context(entry: f64, entry_time: u64) -> ExecContext {
+        let mut position = Position::empty("BTC");
+        position
+            .add_fill(1.0, entry, 0.0, Some(entry_time))
+            .unwrap();
+        ExecContext {
+            position,
+            last_close: entry,
+            precision: 5,
+            ..ExecContext::for_test("BTC", Vec::new())
+        }
+    }
+
+    fn mark(ctx: &mut ExecContext, price: f64, time: u64) {
+        ctx.last_close = price;
+        ctx.candles.push(Candle {
+            close_time: time * 1000,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            ..Default::default()
+        });
+        ctx.position.mark(price, time);
+    }
+
+    #[test]
+    fn fixed_percent_matches_legacy_thresholds() {
+        let policies = ExitPolicies::new(vec![ExitPolicy::FixedPercent {
+            stop_loss_pct: 2.0,
+            take_profit_pct: 4.0,
+        }]);
+        let mut ctx = context(100.0, 0);
+        mark(&mut ctx, 99.0, 1);
+        assert_eq!(policies.evaluate(&mut ctx), None);
+        mark(&mut ctx, 98.0, 2);
+        assert_eq!(policies.evaluate(&mut ctx), Some(ExitReason::StopLoss));
+        mark(&mut ctx, 104.0, 3);
+        assert_eq!(policies.evaluate(&mut ctx), Some(ExitReason::TakeProfit));
+    }
+
+    #[test]
+    fn trailing_stop_follows_high_water_mark() {
+        let policies = ExitPolicies::new(vec![ExitPolicy::TrailingStop { trail_pct: 1.0 }]);
+        let mut ctx = context(100.0, 0);
+        mark(&mut ctx, 110.0, 1);
+        assert_eq!(policies.evaluate(&mut ctx), None);
+        mark(&mut ctx, 109.0, 2);
+        assert_eq!(policies.evaluate(&mut ctx), None);
+        mark(&mut ctx, 108.8, 3);
+        assert_eq!(policies.evaluate(&mut ctx), Some(ExitReason::TrailingStop));
+    }
+
+    #[test]
+    fn break_even_arms_after_trigger() {
+        let policies = ExitPolicies::new(vec![ExitPolicy::BreakEven {
+            trigger_pct: 1.0,
+            offset_pct: 0.1,
+        }]);
+        let mut ctx = context(100.0, 0);
+        mark(&mut ctx, 100.05, 1);
+        assert_eq!(policies.evaluate(&mut ctx), None);
+        mark(&mut ctx, 101.5, 2);
+        assert_eq!(policies.evaluate(&mut ctx), None);
+        assert!(ctx.position.exit_state.break_even_armed);
+        mark(&mut ctx, 100.05, 3);
+        assert_eq!(policies.evaluate(&mut ctx), Some(ExitReason::BreakEven));
+    }
+
+    #[test]
+    fn max_holding_time_uses_candle_time() {
+        let policies = ExitPolicies::new(vec![ExitPolicy::MaxHoldingTime { seconds: 60 }]);
+        let mut ctx = context(100.0, 1_000);
+        mark(&mut ctx, 100.0, 1_059);
+        assert_eq!(policies.evaluate(&mut ctx), None);
+        mark(&mut ctx, 100.0, 1_060);
+        assert_eq!(policies.evaluate(&mut ctx), Some(ExitReason::TimeStop));
+    }
+
+    #[test]
+    fn exit_state_survives_yaml_round_trip() {
+        let mut ctx = context(100.0, 0);
+        mark(&mut ctx, 105.0, 10);
+        let yaml = serde_yaml::to_string(&ctx.position).unwrap();
+        let restored: Position = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(restored.exit_state, ctx.position.exit_state);
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{
+    config::{ConfigError, ConfigFile, SymbolParams, Validate, check_ordered, check_range},
+    exits::{ExitPolicies, ExitPolicy, ExitState},
     indicators::Indicators,
     roostoo::{OrderSide, OrderType},
+    sizing::{PositionSizer, Sizing, SizingInput},
     strategy::{ExecContext, Order, SharedState, Strategy},
 };
 use anyhow::{Context, Result};
@@ -15,7 +18,7 @@ use std::{
 };
 use tokio::sync::Mutex;
 
-#[derive(Debug, Deserialize, Copy, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, Default)]
 pub struct Candle {
     #[serde(rename = "datetime")]
     pub open_time: u64,
@@ -28,8 +31,20 @@ pub struct Candle {
     pub trade_count: i64,
 }
 
+impl Candle {
+    /// Close time in unix seconds. Binance klines carry milliseconds, newer
+    /// spot dumps carry microseconds.
+    pub fn close_secs(&self) -> u64 {
+        match self.close_time {
+            t if t >= 100_000_000_000_000 => t / 1_000_000,
+            t if t >= 100_000_000_000 => t / 1_000,
+            t => t,
+        }
+    }
+}
+
 /// Helper to get current unix epoch seconds
-fn now_unix_secs() -> u64 {
+pub(crate) fn now_unix_secs() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_secs())
@@ -47,6 +62,8 @@ pub struct Position {
     pub realized_pnl: f64,       // cumulative realized PnL in quote currency
     pub unrealized_pnl: f64,     // last computed unrealized PnL in quote currency
     pub avg_fee_per_unit: f64,   // average fee in quote currency per base unit
+    #[serde(default)]
+    pub exit_state: ExitState, // bookkeeping for exit policies, reset on close
 }
 
 impl Position {
@@ -60,6 +77,7 @@ impl Position {
             realized_pnl: 0.0,
             unrealized_pnl: 0.0,
             avg_fee_per_unit: 0.0,
+            exit_state: ExitState::default(),
         }
     }
     pub fn save_to_yaml<P: AsRef<Path>>(&self, path: P) -> Result<()> {
@@ -68,7 +86,7 @@ impl Position {
 
         let s = serde_yaml::to_string(self).context("serialize position to YAML")?;
         fs::write(&tmp, s).with_context(|| format!("write temp file {:?}", tmp))?;
-        fs::rename(&tmp, &path).with_context(|| format!("rename {:?} -> {:?}", tmp, path))?;
+        fs::rename(&tmp, path).with_context(|| format!("rename {:?} -> {:?}", tmp, path))?;
         Ok(())
     }
 
@@ -109,6 +127,16 @@ impl Position {
         Some(pct)
     }
 
+    /// Record a mark price at `time_unix_secs` for exit bookkeeping.
+    pub fn mark(&mut self, mark_price: f64, time_unix_secs: u64) {
+        if !self.is_open() {
+            return;
+        }
+        let state = &mut self.exit_state;
+        state.high_water_mark = state.high_water_mark.max(mark_price);
+        state.last_mark_time = time_unix_secs;
+    }
+
     /// Open a new long position when currently closed.
     /// `fee` is total fee paid in quote currency for this fill.
     pub fn open_new(
@@ -130,6 +158,11 @@ impl Position {
         self.entry_time = Some(time_unix_secs.unwrap_or_else(now_unix_secs));
         self.avg_fee_per_unit = if qty != 0.0 { fee / qty } else { 0.0 };
         self.unrealized_pnl = 0.0;
+        self.exit_state = ExitState {
+            high_water_mark: price,
+            last_mark_time: self.entry_time.unwrap_or(0),
+            ..Default::default()
+        };
         Ok(())
     }
 
@@ -190,6 +223,7 @@ impl Position {
             self.entry_time = None;
             self.avg_fee_per_unit = 0.0;
             self.unrealized_pnl = 0.0;
+            self.exit_state = ExitState::default();
         }
 
         Ok(realized)
@@ -213,12 +247,90 @@ impl Position {
         self.entry_time = None;
         self.avg_fee_per_unit = 0.0;
         self.unrealized_pnl = 0.0;
+        self.exit_state = ExitState::default();
 
         Ok(realized)
     }
 }
 
-pub struct Fourier {}
+/// Tuning for `Fourier`, loaded from the `fourier` section of the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FourierParams {
+    pub ema_fast: usize,
+    pub ema_slow: usize,
+    pub rsi_period: usize,
+    pub rsi_max: f64, // only enter while RSI is below this
+    pub sizing: Sizing,
+    pub max_notional: f64,     // hard cap on entry size in USD
+    pub warmup_candles: usize, // candles needed before the first entry
+    pub exits: ExitPolicies,
+}
+
+impl Default for FourierParams {
+    fn default() -> Self {
+        FourierParams {
+            ema_fast: 12,
+            ema_slow: 26,
+            rsi_period: 14,
+            rsi_max: 60.0,
+            sizing: Sizing::default(),
+            max_notional: 10_000.0,
+            warmup_candles: 32,
+            exits: ExitPolicies::new(vec![ExitPolicy::FixedPercent {
+                stop_loss_pct: 2.0,
+                take_profit_pct: 4.0,
+            }]),
+        }
+    }
+}
+
+impl Validate for FourierParams {
+    fn validate(&self, scope: &str) -> std::result::Result<(), ConfigError> {
+        check_range(scope, "ema_fast", self.ema_fast as f64, 1.0, 1024.0)?;
+        check_range(scope, "ema_slow", self.ema_slow as f64, 2.0, 2048.0)?;
+        check_ordered(
+            scope,
+            "ema_fast",
+            self.ema_fast as f64,
+            self.ema_slow as f64,
+            "ema_slow",
+        )?;
+        check_range(scope, "rsi_period", self.rsi_period as f64, 1.0, 1024.0)?;
+        check_range(scope, "rsi_max", self.rsi_max, 0.0, 100.0)?;
+        self.sizing.validate(scope)?;
+        check_range(scope, "max_notional", self.max_notional, 0.0, f64::MAX)?;
+        check_range(
+            scope,
+            "warmup_candles",
+            self.warmup_candles as f64,
+            self.ema_slow.max(self.rsi_period + 1) as f64,
+            2048.0,
+        )?;
+        self.exits.validate(scope)
+    }
+}
+
+#[derive(Default)]
+pub struct Fourier {
+    pub params: SymbolParams<FourierParams>,
+}
+
+impl Fourier {
+    pub const SECTION: &'static str = "fourier";
+
+    pub fn new(params: SymbolParams<FourierParams>) -> Self {
+        Fourier { params }
+    }
+
+    pub fn from_config(config: &ConfigFile) -> std::result::Result<Self, ConfigError> {
+        Ok(Self::new(config.section_or_default(Self::SECTION)?))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> std::result::Result<Self, ConfigError> {
+        Self::from_config(&ConfigFile::load(path)?)
+    }
+}
 
 #[async_trait]
 impl Strategy for Fourier {
@@ -231,24 +343,25 @@ impl Strategy for Fourier {
             return false;
         }
 
-        if ctx.candles.len() < 32 {
+        let params = self.params.get(&ctx.symbol);
+        if ctx.candles.len() < params.warmup_candles {
             return false;
         }
 
         let indicators = Indicators::new(&ctx.candles);
-        let short = indicators.ema(12);
-        let long = indicators.ema(26);
-        let rsi = indicators.rsi(14);
+        let short = indicators.ema(params.ema_fast);
+        let long = indicators.ema(params.ema_slow);
+        let rsi = indicators.rsi(params.rsi_period);
         let has_capital = {
             let guard = shared_state.lock().await;
-            guard.capital > 0.0
+            guard.available_capital() > 0.0
         };
         if !has_capital {
             return false;
         }
 
         match (short, long, rsi) {
-            (Some(short), Some(long), Some(rsi)) => short > long && rsi < 60.0,
+            (Some(short), Some(long), Some(rsi)) => short > long && rsi < params.rsi_max,
             _ => false,
         }
     }
@@ -258,19 +371,19 @@ impl Strategy for Fourier {
         ctx: &ExecContext,
         shared_state: Arc<Mutex<SharedState>>,
     ) -> Option<Order> {
-        let indicators = Indicators::new(&ctx.candles);
-        let atr = indicators.atr(14).unwrap_or(ctx.last_close * 0.01);
-
-        let risk_capital = {
+        let params = self.params.get(&ctx.symbol);
+        let size = {
             let guard = shared_state.lock().await;
-            (guard.capital * 0.02).max(0.0)
+            let trade_returns: Vec<f64> = guard.trade_returns.iter().copied().collect();
+            params.sizing.size(&SizingInput {
+                capital: guard.available_capital(),
+                price: ctx.last_close,
+                candles: &ctx.candles,
+                trade_returns: &trade_returns,
+                open_positions: guard.open_positions,
+            })
         };
-        if risk_capital == 0.0 || ctx.last_close == 0.0 {
-            return None;
-        }
-
-        let per_unit_risk = atr.max(1e-6);
-        let position_size = (risk_capital / per_unit_risk).min(guarded_max_size(ctx.last_close));
+        let position_size = size.min(guarded_max_size(ctx.last_close, params.max_notional));
         if position_size <= 0.0 {
             return None;
         }
@@ -289,33 +402,34 @@ impl Strategy for Fourier {
     // #TODO more options other than just liquidate all
     async fn update_position(
         &self,
-        ctx: &ExecContext,
+        ctx: &mut ExecContext,
         _shared_state: Arc<Mutex<SharedState>>,
     ) -> bool {
-        if !ctx.position.is_open() {
-            return false;
+        let exits = &self.params.get(&ctx.symbol).exits;
+        match exits.evaluate(ctx) {
+            Some(reason) => {
+                if ctx.verbose {
+                    println!("[INFO][EXIT] {} {} @{}", ctx.symbol, reason, ctx.last_close);
+                }
+                true
+            }
+            None => false,
         }
+    }
 
-        let pct = ctx.position.unrealized_pct(ctx.last_close);
-        let target = match pct {
-            Some(p) => p,
-            None => return false,
-        };
-
-        let stop_loss = -2.0;
-        let take_profit = 4.0;
-
-        if target <= stop_loss || target >= take_profit {
-            return true;
-        }
+    fn protective_exits(&self, symbol: &str) -> Option<&ExitPolicies> {
+        Some(&self.params.get(symbol).exits)
+    }
 
-        false
+    fn reload(&mut self, config: &ConfigFile) -> std::result::Result<(), ConfigError> {
+        self.params = config.section_or_default(Self::SECTION)?;
+        Ok(())
     }
 }
 
-fn guarded_max_size(price: f64) -> f64 {
+fn guarded_max_size(price: f64, max_notional: f64) -> f64 {
     if price <= 0.0 {
         return 0.0;
     }
-    10_000.0 / price
+    max_notional / price
 }
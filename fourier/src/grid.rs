@@ -0,0 +1,396 @@
+use crate::config::{ConfigError, ConfigFile, SymbolParams, Validate, check_range};
+use crate::order_engine::{RestingOrder, round_significant};
+use crate::roostoo::{OrderSide, OrderType};
+use crate::strategy::{ExecContext, Order, OrderIntent, SharedState, Strategy};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Tuning for `Grid`, loaded from the `grid` section of the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct GridParams {
+    pub levels: usize,               // limit orders on each side of the centre
+    pub spacing_pct: f64,            // distance between levels, in percent of the centre
+    pub level_notional: f64,         // USD per level, fixed in base units at each recentre
+    pub max_inventory_notional: f64, // held plus resting buys, in USD
+    pub significant_digits: i32,     // limit prices are rounded to this many
+}
+
+impl Default for GridParams {
+    fn default() -> Self {
+        GridParams {
+            levels: 5,
+            spacing_pct: 0.5,
+            level_notional: 100.0,
+            max_inventory_notional: 1_000.0,
+            significant_digits: 5,
+        }
+    }
+}
+
+impl Validate for GridParams {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        check_range(scope, "levels", self.levels as f64, 1.0, 50.0)?;
+        check_range(scope, "spacing_pct", self.spacing_pct, 0.01, 50.0)?;
+        check_range(scope, "level_notional", self.level_notional, 0.0, f64::MAX)?;
+        check_range(
+            scope,
+            "max_inventory_notional",
+            self.max_inventory_notional,
+            self.level_notional,
+            f64::MAX,
+        )?;
+        check_range(
+            scope,
+            "significant_digits",
+            self.significant_digits as f64,
+            1.0,
+            12.0,
+        )
+    }
+}
+
+// grid of one symbol, fixed until the next recentre. Levels are indexed
+// from the centre: level `i` sits at centre * (1 + i * spacing).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Ladder {
+    centre: f64,
+    unit: f64,       // base units per level
+    sells: Vec<i64>, // level each held unit is offered at, one above its buy
+    armed: Vec<i64>, // buy levels wanted on the last call
+}
+
+impl Ladder {
+    fn price(&self, params: &GridParams, level: i64) -> f64 {
+        round_significant(
+            self.centre * (1.0 + params.spacing_pct / 100.0 * level as f64),
+            params.significant_digits,
+        )
+    }
+
+    // first level priced above `price` that no held unit is offered at
+    fn free_level_above(&self, params: &GridParams, mut level: i64, price: f64) -> i64 {
+        while self.price(params, level) <= price || self.sells.contains(&level) {
+            level += 1;
+        }
+        level
+    }
+
+    /// Book the fills since the last call from the held quantity: new units
+    /// were bought at the highest armed levels no longer resting, and sold
+    /// units leave from the lowest offer.
+    fn reconcile(&mut self, params: &GridParams, ctx: &ExecContext, resting: &[RestingOrder]) {
+        let held = (ctx.position.quantity / self.unit).round().max(0.0) as usize;
+        let mut filled: Vec<i64> = self
+            .armed
+            .iter()
+            .copied()
+            .filter(|&level| {
+                !resting
+                    .iter()
+                    .any(|o| o.side == OrderSide::Buy && self.matches(params, o, level))
+            })
+            .collect();
+        filled.sort_unstable();
+        while self.sells.len() < held {
+            let bought = filled.pop().unwrap_or(-1);
+            let offer = self.free_level_above(params, bought + 1, ctx.last_close);
+            self.sells.push(offer);
+        }
+        self.sells.sort_unstable();
+        while self.sells.len() > held {
+            self.sells.remove(0);
+        }
+    }
+
+    fn matches(&self, params: &GridParams, order: &RestingOrder, level: i64) -> bool {
+        let tolerance = self.centre * params.spacing_pct / 100.0 / 4.0;
+        (order.price - self.price(params, level)).abs() < tolerance
+    }
+
+    /// Orders the grid should have resting, nearest levels first: an offer
+    /// one level above each held unit's buy, and every buy level below the
+    /// last close whose unit is not held, while inventory allows.
+    fn desired(&mut self, params: &GridParams, ctx: &ExecContext) -> Vec<(OrderSide, i64)> {
+        let price = ctx.last_close;
+        let mut orders: Vec<(OrderSide, i64)> = self
+            .sells
+            .iter()
+            .map(|&level| (OrderSide::Sell, level))
+            .collect();
+
+        self.armed.clear();
+        let mut inventory = ctx.position.quantity * price;
+        let top = params.levels as i64;
+        for level in (-top..top).rev() {
+            let level_price = self.price(params, level);
+            if level_price >= price || self.sells.contains(&(level + 1)) {
+                continue;
+            }
+            inventory += self.unit * level_price;
+            if inventory > params.max_inventory_notional + 1e-9 {
+                break;
+            }
+            self.armed.push(level);
+            orders.push((OrderSide::Buy, level));
+        }
+        orders
+    }
+}
+
+/// Passive grid: a ladder of limit buys below and limit sells above a centre
+/// price. Every unit bought is offered one level higher and its buy level is
+/// re-armed once it sells, so the grid earns a level's spacing per round
+/// trip, paid maker commission on both legs. Buys stop once held plus
+/// resting inventory reaches `max_inventory_notional`, and the grid is
+/// rebuilt around the last close when price leaves the ladder.
+#[derive(Default)]
+pub struct Grid {
+    pub params: SymbolParams<GridParams>,
+    ladders: HashMap<String, Ladder>,
+}
+
+impl Grid {
+    pub const SECTION: &'static str = "grid";
+
+    pub fn new(params: SymbolParams<GridParams>) -> Self {
+        Grid {
+            params,
+            ladders: HashMap::new(),
+        }
+    }
+
+    pub fn from_config(config: &ConfigFile) -> Result<Self, ConfigError> {
+        Ok(Self::new(config.section_or_default(Self::SECTION)?))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::from_config(&ConfigFile::load(path)?)
+    }
+
+    /// Centre of the grid for `symbol`, once one has been laid out
+    pub fn centre(&self, symbol: &str) -> Option<f64> {
+        self.ladders.get(symbol).map(|ladder| ladder.centre)
+    }
+
+    fn order(ctx: &ExecContext, side: OrderSide, price: f64, quantity: f64) -> Order {
+        Order {
+            pair: ctx.pair(),
+            side,
+            order_type: OrderType::Limit,
+            quantity,
+            price: Some(price),
+        }
+    }
+}
+
+#[async_trait]
+impl Strategy for Grid {
+    async fn should_long(
+        &self,
+        _ctx: &mut ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        false
+    }
+
+    async fn go_long(
+        &self,
+        _ctx: &ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> Option<Order> {
+        None
+    }
+
+    async fn update_position(
+        &self,
+        _ctx: &mut ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        false
+    }
+
+    async fn manage_orders(
+        &mut self,
+        ctx: &ExecContext,
+        resting: &[RestingOrder],
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> Vec<OrderIntent> {
+        let params = self.params.get(&ctx.symbol);
+        let price = ctx.last_close;
+        if price <= 0.0 {
+            return Vec::new();
+        }
+
+        let reach = params.spacing_pct / 100.0 * params.levels as f64;
+        let inside = |ladder: &Ladder| {
+            price >= ladder.centre * (1.0 - reach) && price <= ladder.centre * (1.0 + reach)
+        };
+        let mut intents = Vec::new();
+        let mut resting = resting;
+        let ladder = match self.ladders.get_mut(&ctx.symbol) {
+            Some(ladder) if inside(ladder) => {
+                ladder.reconcile(params, ctx, resting);
+                ladder
+            }
+            previous => {
+                if let Some(previous) = previous {
+                    println!(
+                        "[INFO][GRID] {} left the grid around {}, recentring at {}",
+                        ctx.symbol, previous.centre, price
+                    );
+                }
+                // everything resting belongs to the old ladder; units already
+                // held are offered from the first level up
+                intents.extend(resting.iter().map(|order| OrderIntent::Cancel(order.id)));
+                resting = &[];
+                let mut ladder = Ladder {
+                    centre: price,
+                    unit: params.level_notional / price,
+                    ..Default::default()
+                };
+                let held = (ctx.position.quantity / ladder.unit).round().max(0.0) as i64;
+                ladder.sells = (1..=held).collect();
+                self.ladders.insert(ctx.symbol.clone(), ladder);
+                self.ladders.get_mut(&ctx.symbol).expect("just inserted")
+            }
+        };
+
+        let desired = ladder.desired(params, ctx);
+        let wanted = |order: &RestingOrder| {
+            desired
+                .iter()
+                .any(|(side, level)| order.side == *side && ladder.matches(params, order, *level))
+        };
+        // levels no longer wanted, e.g. buys past the inventory bound
+        for order in resting {
+            if !wanted(order) {
+                intents.push(OrderIntent::Cancel(order.id));
+            }
+        }
+        // arm empty levels
+        for (side, level) in &desired {
+            let armed = resting
+                .iter()
+                .any(|order| order.side == *side && ladder.matches(params, order, *level));
+            if !armed {
+                intents.push(OrderIntent::Place(Self::order(
+                    ctx,
+                    side.clone(),
+                    ladder.price(params, *level),
+                    ladder.unit,
+                )));
+            }
+        }
+        intents
+    }
+
+    fn reload(&mut self, config: &ConfigFile) -> Result<(), ConfigError> {
+        self.params = config.section_or_default(Self::SECTION)?;
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(&self.ladders).ok()
+    }
+
+    fn restore(&mut self, state: serde_json::Value) -> Result<(), serde_json::Error> {
+        self.ladders = serde_json::from_value(state)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fourier::Position;
+
+    fn context(price: f64, held: f64) -> ExecContext {
+        let mut position = Position::empty("BTC");
+        if held > 0.0 {
+            position.open_new(held, price, 0.0, Some(0)).unwrap();
+        }
+        ExecContext {
+            position,
+            last_close: price,
+            ..ExecContext::for_test("BTC", Vec::new())
+        }
+    }
+
+    fn resting(intents: &[OrderIntent]) -> Vec<RestingOrder> {
+        intents
+            .iter()
+            .enumerate()
+            .filter_map(|(i, intent)| match intent {
+                OrderIntent::Place(order) => Some(RestingOrder {
+                    id: i as u64,
+                    symbol: "BTC".to_string(),
+                    side: order.side.clone(),
+                    price: order.price.unwrap(),
+                    quantity: order.quantity,
+                    filled: 0.0,
+                    fee_paid: 0.0,
+                    reservation: None,
+                }),
+                OrderIntent::Cancel(_) => None,
+            })
+            .collect()
+    }
+
+    fn placed(intents: &[OrderIntent]) -> Vec<(OrderSide, f64)> {
+        resting(intents)
+            .into_iter()
+            .map(|order| (order.side, order.price))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn ladders_buys_within_the_inventory_bound_and_recentres() {
+        let mut grid = Grid::new(SymbolParams::new(GridParams {
+            levels: 3,
+            spacing_pct: 1.0,
+            level_notional: 100.0,
+            max_inventory_notional: 250.0,
+            ..Default::default()
+        }));
+        let shared = Arc::new(Mutex::new(SharedState::new(10_000.0)));
+
+        // flat: two buys fit under the bound, nothing to sell yet
+        let ctx = context(100.0, 0.0);
+        let intents = grid.manage_orders(&ctx, &[], shared.clone()).await;
+        let book = resting(&intents);
+        let prices: Vec<f64> = book.iter().map(|o| o.price).collect();
+        assert_eq!(prices, [99.0, 98.0]);
+        assert!(book.iter().all(|o| o.side == OrderSide::Buy));
+
+        // the 99 buy filled: its unit is offered a level up, and 99 stays
+        // empty until it sells
+        let remaining: Vec<RestingOrder> = book.into_iter().skip(1).collect();
+        let intents = grid
+            .manage_orders(&context(99.5, 1.0), &remaining, shared.clone())
+            .await;
+        assert_eq!(placed(&intents), [(OrderSide::Sell, 100.0)]);
+
+        // sold at 100: the buys follow price up, and the 98 buy no longer
+        // fits under the bound
+        let intents = grid
+            .manage_orders(&context(100.2, 0.0), &remaining, shared.clone())
+            .await;
+        assert!(matches!(intents[0], OrderIntent::Cancel(1)));
+        assert_eq!(
+            placed(&intents),
+            [(OrderSide::Buy, 100.0), (OrderSide::Buy, 99.0)]
+        );
+
+        // price runs away: everything is cancelled and the grid moves
+        let intents = grid
+            .manage_orders(&context(110.0, 0.0), &remaining, shared)
+            .await;
+        assert!(matches!(intents[0], OrderIntent::Cancel(1)));
+        assert_eq!(grid.centre("BTC"), Some(110.0));
+    }
+}
@@ -1,5 +1,7 @@
 use crate::fourier::Candle;
 
+const SECS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0;
+
 pub struct Indicators<'a> {
     candles: &'a [Candle],
 }
@@ -107,6 +109,51 @@ impl<'a> Indicators<'a> {
         Some(100.0 - (100.0 / (1.0 + rs)))
     }
 
+    /// Log returns of the last `period` closes, oldest first
+    pub fn log_returns(&self, period: usize) -> Option<Vec<f64>> {
+        if period == 0 || self.candles.len() <= period {
+            return None;
+        }
+        let window = &self.candles[self.candles.len() - (period + 1)..];
+        window
+            .windows(2)
+            .map(|w| (w[0].close > 0.0 && w[1].close > 0.0).then(|| (w[1].close / w[0].close).ln()))
+            .collect()
+    }
+
+    /// Volatility of the last `period` log returns scaled to a year, using
+    /// the average spacing of the candles (one second for 1s klines)
+    pub fn annualised_volatility(&self, period: usize) -> Option<f64> {
+        let returns = self.log_returns(period)?;
+        let sigma = self.stddev_series(returns, period)?;
+        let window = &self.candles[self.candles.len() - (period + 1)..];
+        let elapsed = window[period]
+            .close_secs()
+            .saturating_sub(window[0].close_secs());
+        let spacing = (elapsed as f64 / period as f64).max(1.0);
+        Some(sigma * (SECS_PER_YEAR / spacing).sqrt())
+    }
+
+    /// Highest high of `period` candles ending `skip` candles before the last
+    pub fn highest_high(&self, period: usize, skip: usize) -> Option<f64> {
+        let window = self.window(period, skip)?;
+        Some(window.iter().map(|c| c.high).fold(f64::MIN, f64::max))
+    }
+
+    /// Lowest low of `period` candles ending `skip` candles before the last
+    pub fn lowest_low(&self, period: usize, skip: usize) -> Option<f64> {
+        let window = self.window(period, skip)?;
+        Some(window.iter().map(|c| c.low).fold(f64::MAX, f64::min))
+    }
+
+    fn window(&self, period: usize, skip: usize) -> Option<&'a [Candle]> {
+        if period == 0 || self.candles.len() < period + skip {
+            return None;
+        }
+        let end = self.candles.len() - skip;
+        Some(&self.candles[end - period..end])
+    }
+
     // Add other indicators as needed...
     pub fn atr(&self, period: usize) -> Option<f64> {
         if self.candles.len() < period + 1 {
@@ -130,3 +177,29 @@ impl<'a> Indicators<'a> {
         Some(sum / period as f64)
     }
 }
+
+/// Merge candles into bars of `timeframe_secs` by close time, oldest first.
+/// The last bar is still forming unless a later candle has started the next.
+pub fn aggregate(candles: &[Candle], timeframe_secs: u64) -> Vec<Candle> {
+    let timeframe = timeframe_secs.max(1);
+    let mut bars: Vec<Candle> = Vec::new();
+    let mut bucket = None;
+    for candle in candles {
+        let current = candle.close_secs() / timeframe;
+        match bars.last_mut() {
+            Some(bar) if bucket == Some(current) => {
+                bar.close_time = candle.close_time;
+                bar.high = bar.high.max(candle.high);
+                bar.low = bar.low.min(candle.low);
+                bar.close = candle.close;
+                bar.volume += candle.volume;
+                bar.trade_count += candle.trade_count;
+            }
+            _ => {
+                bars.push(*candle);
+                bucket = Some(current);
+            }
+        }
+    }
+    bars
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+// one equity point per minute of candle time keeps month-long 1s backtests small
+const EQUITY_SAMPLE_SECS: u64 = 60;
+
+/// A completed (or partially completed) round trip on one symbol.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TradeRecord {
+    pub symbol: String,
+    pub entry_time: u64, // unix seconds
+    pub exit_time: u64,  // unix seconds
+    pub entry_price: f64,
+    pub exit_price: f64,
+    pub quantity: f64,
+    pub pnl: f64,        // realized pnl in quote currency, fees included
+    pub return_pct: f64, // pnl relative to the entry notional, in percent
+}
+
+/// Closed trades and a sampled equity curve recorded by the `Executioner`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Journal {
+    pub trades: Vec<TradeRecord>,
+    pub equity_curve: Vec<(u64, f64)>, // (unix seconds, equity)
+}
+
+impl Journal {
+    pub fn record_trade(&mut self, trade: TradeRecord) {
+        self.trades.push(trade);
+    }
+
+    /// Whether an equity point is due at `time`.
+    pub fn wants_sample(&self, time: u64) -> bool {
+        match self.equity_curve.last() {
+            None => true,
+            Some((last, _)) => time >= last + EQUITY_SAMPLE_SECS,
+        }
+    }
+
+    pub fn record_equity(&mut self, time: u64, equity: f64) {
+        match self.equity_curve.last_mut() {
+            // same timestamp: keep the latest value
+            Some((last, value)) if *last == time => *value = equity,
+            _ => self.equity_curve.push((time, equity)),
+        }
+    }
+}
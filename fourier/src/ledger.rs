@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// fees assumed when reserving for a buy; matches the exchange taker rate
+pub const FEE_RESERVE_RATE: f64 = 0.001;
+
+/// Handle for funds held back while an order is in flight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Reservation(u64);
+
+/// USD balance plus the amounts promised to orders that have not come back.
+///
+/// Buys reserve their estimated notional and fees before they are sent, and
+/// the reservation is settled against the real fill or released on reject.
+/// Sizing reads `available`, so two symbols can never size against the same
+/// dollars while their orders are outstanding.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CapitalLedger {
+    balance: f64,
+    reserved: HashMap<Reservation, (String, f64)>, // pair, amount
+    next_id: u64,
+}
+
+impl CapitalLedger {
+    pub fn new(balance: f64) -> Self {
+        Self {
+            balance,
+            ..Default::default()
+        }
+    }
+
+    /// Cash held, including reserved amounts
+    pub fn balance(&self) -> f64 {
+        self.balance
+    }
+
+    pub fn reserved(&self) -> f64 {
+        self.reserved.values().map(|(_, amount)| amount).sum()
+    }
+
+    /// Cash free to size new orders against
+    pub fn available(&self) -> f64 {
+        (self.balance - self.reserved()).max(0.0)
+    }
+
+    /// Estimated cash a buy of `quantity` at `price` will take, fees included
+    pub fn estimate(quantity: f64, price: f64) -> f64 {
+        quantity * price * (1.0 + FEE_RESERVE_RATE)
+    }
+
+    /// Hold `amount` for an order on `pair`; None if it is not available.
+    pub fn reserve(&mut self, pair: &str, amount: f64) -> Option<Reservation> {
+        if amount.is_nan() || amount < 0.0 || amount > self.available() {
+            return None;
+        }
+        let id = Reservation(self.next_id);
+        self.next_id += 1;
+        self.reserved.insert(id, (pair.to_string(), amount));
+        Some(id)
+    }
+
+    /// Drop a reservation whose order was rejected or never filled
+    pub fn release(&mut self, id: Reservation) {
+        self.reserved.remove(&id);
+    }
+
+    /// Change the amount held by a reservation, e.g. after a partial fill
+    pub fn resize(&mut self, id: Reservation, amount: f64) {
+        if let Some((_, reserved)) = self.reserved.get_mut(&id) {
+            *reserved = amount.max(0.0);
+        }
+    }
+
+    /// Drop a reservation and book the fill's actual cash movement
+    pub fn settle(&mut self, id: Reservation, cash_delta: f64) {
+        self.reserved.remove(&id);
+        self.apply(cash_delta);
+    }
+
+    /// Book cash that needed no reservation, e.g. sale proceeds
+    pub fn apply(&mut self, cash_delta: f64) {
+        self.balance += cash_delta;
+    }
+
+    /// Overwrite the balance with the exchange's wallet. Cash the exchange
+    /// has locked for resting buys is still held, and stays set aside by
+    /// those orders' reservations, so it counts towards the balance rather
+    /// than being taken off twice.
+    pub fn sync_wallet(&mut self, free: f64, locked: f64) {
+        self.balance = free + locked;
+    }
+
+    /// Pairs with funds currently reserved and how much
+    pub fn reservations(&self) -> Vec<(String, f64)> {
+        self.reserved.values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reservations_hold_funds_until_settled_or_released() {
+        let mut ledger = CapitalLedger::new(1_000.0);
+        let btc = ledger.reserve("BTC/USD", 600.0).unwrap();
+        assert_eq!(ledger.available(), 400.0);
+        assert!(ledger.reserve("ETH/USD", 500.0).is_none());
+
+        let eth = ledger.reserve("ETH/USD", 300.0).unwrap();
+        ledger.release(eth);
+        assert_eq!(ledger.available(), 400.0);
+
+        ledger.settle(btc, -590.0);
+        assert_eq!(ledger.balance(), 410.0);
+        assert_eq!(ledger.available(), 410.0);
+    }
+
+    #[test]
+    fn wallet_sync_does_not_count_a_resting_buy_twice() {
+        let mut ledger = CapitalLedger::new(1_000.0);
+        ledger.reserve("BTC/USD", 300.0).unwrap();
+        // the exchange moved the resting buy's cash from free to locked
+        ledger.sync_wallet(700.0, 300.0);
+        assert_eq!(ledger.balance(), 1_000.0);
+        assert_eq!(ledger.available(), 700.0);
+    }
+}
@@ -1,8 +1,30 @@
 pub mod roostoo;
 
 pub mod backtest;
+pub mod bollinger;
+pub mod breakout;
+pub mod config;
+pub mod correlation;
+pub mod ensemble;
+pub mod exits;
 pub mod fourier;
-
+pub mod grid;
 pub mod indicators;
+pub mod journal;
+pub mod ledger;
+pub mod market_maker;
+pub mod momentum;
+pub mod optimiser;
 pub mod order_engine;
+pub mod pairs;
+pub mod portfolio;
+pub mod registry;
+pub mod risk;
+pub mod robustness;
+pub mod scripted;
+pub mod shadow;
+pub mod sizing;
+pub mod snapshot;
 pub mod strategy;
+pub mod walkforward;
+pub mod warmup;
@@ -2,21 +2,83 @@ use binance::api::Binance;
 use binance::market::Market;
 use binance::model::KlineSummaries;
 use dotenv::dotenv;
+use fourier::config::{ConfigError, ConfigFile, ConfigWatch};
 use fourier::fourier::{Candle, Fourier};
+use fourier::market_maker::MarketMaker;
 use fourier::order_engine::OrderEngine;
+use fourier::portfolio::PortfolioConfig;
+use fourier::registry;
+use fourier::risk::RiskLimits;
 use fourier::roostoo::RoostooClient;
-use fourier::strategy::{CandleData, Executioner, Strategy, TraderConfig};
+use fourier::shadow::ShadowConfig;
+use fourier::snapshot::{ExecutionerSnapshot, SnapshotConfig};
+use fourier::strategy::{CandleData, Executioner, Quote, Strategy, TraderConfig};
+use fourier::warmup::WarmupConfig;
 use std::collections::HashMap;
 use std::env;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
 use tokio::time::{Duration, interval};
 
+// the live feed polls one second klines
+const KLINE_INTERVAL: &str = "1s";
+const KLINE_INTERVAL_MS: u64 = 1_000;
+
 const CRYPTOS: [&str; 15] = [
     "BTC", "ETH", "SOL", "BNB", "DOGE", "ICP", "XRP", "AAVE", "UNI", "XLM", "SUI", "BONK", "FIL",
     "TRX", "WIF",
 ];
 
-async fn binance_task(tx: mpsc::Sender<CandleData>) {
+// Roostoo's book for every pair, by symbol
+async fn roostoo_quotes(client: &RoostooClient) -> HashMap<String, Quote> {
+    match client.get_ticker(None).await {
+        Ok(ticker) => ticker
+            .data
+            .iter()
+            .filter_map(|(pair, data)| {
+                let symbol = pair.strip_suffix("/USD")?;
+                Some((
+                    symbol.to_string(),
+                    Quote::from_ticker(ticker.server_time / 1_000, data),
+                ))
+            })
+            .collect(),
+        Err(e) => {
+            println!("[ERROR][ROOSTOO] Failed to fetch ticker: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+// appends `quote` to <dir>/<symbol>.csv, the format the backtester replays
+fn record_quote(dir: &str, symbol: &str, quote: &Quote) {
+    let path = std::path::Path::new(dir).join(format!("{symbol}.csv"));
+    let fresh = !path.exists();
+    let written = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(csv::Error::from)
+        .and_then(|file| {
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(fresh)
+                .from_writer(file);
+            writer.serialize(quote)?;
+            writer.flush().map_err(csv::Error::from)
+        });
+    if let Err(e) = written {
+        println!("[ERROR][ROOSTOO] Failed to record {} ticker: {}", symbol, e);
+    }
+}
+
+// `quotes` attaches Roostoo's ticker to every candle; `record_dir` also
+// saves it for replay
+async fn binance_task(
+    tx: mpsc::Sender<CandleData>,
+    quotes: Option<RoostooClient>,
+    record_dir: Option<String>,
+) {
     let cryptos: HashMap<&str, String> = CRYPTOS
         .iter()
         .map(|symbol| (*symbol, format!("{symbol}USDT")))
@@ -25,6 +87,15 @@ async fn binance_task(tx: mpsc::Sender<CandleData>) {
 
     loop {
         throttle.tick().await;
+        let book = match &quotes {
+            Some(client) => roostoo_quotes(client).await,
+            None => HashMap::new(),
+        };
+        if let Some(dir) = &record_dir {
+            for (symbol, quote) in &book {
+                record_quote(dir, symbol, quote);
+            }
+        }
         let mut handles = Vec::with_capacity(cryptos.len());
 
         for (real_name, symbol) in &cryptos {
@@ -32,7 +103,7 @@ async fn binance_task(tx: mpsc::Sender<CandleData>) {
             let symbol = symbol.clone();
             handles.push(tokio::task::spawn_blocking(move || {
                 let market: Market = Binance::new(None, None);
-                let candle = market.get_klines(&symbol, "1s", 1, None, None);
+                let candle = market.get_klines(&symbol, KLINE_INTERVAL, 1, None, None);
                 (real_name, candle)
             }));
         }
@@ -46,7 +117,8 @@ async fn binance_task(tx: mpsc::Sender<CandleData>) {
                     }
                     let kline = candles.remove(0);
                     match parse_kline(&real_name, kline) {
-                        Some(candle_data) => {
+                        Some(mut candle_data) => {
+                            candle_data.quote = book.get(&real_name).copied();
                             if tx.send(candle_data).await.is_err() {
                                 println!("[INFO][BINANCE] Candle consumer dropped, stopping feed");
                                 return;
@@ -85,43 +157,157 @@ fn parse_kline(symbol: &str, kline: binance::model::KlineSummary) -> Option<Cand
             volume: parse_number(&kline.volume)?,
             trade_count: kline.number_of_trades,
         },
+        quote: None,
     })
 }
 
+// the last `warmup.candles` closed klines of every symbol, from Binance
+async fn fetch_history(warmup: &WarmupConfig) -> Vec<(String, Vec<Candle>)> {
+    let end_ms = now_unix_millis();
+    let mut handles = Vec::with_capacity(CRYPTOS.len());
+    for symbol in CRYPTOS {
+        let warmup = warmup.clone();
+        handles.push(tokio::task::spawn_blocking(move || {
+            let market: Market = Binance::new(None, None);
+            let pair = format!("{symbol}USDT");
+            let candles =
+                warmup.backfill(symbol, end_ms, KLINE_INTERVAL_MS, |start, end| match market
+                    .get_klines(&pair, KLINE_INTERVAL, warmup.page as u16, start, end)
+                {
+                    Ok(KlineSummaries::AllKlineSummaries(klines)) => Ok(klines
+                        .into_iter()
+                        .filter_map(|kline| parse_kline(symbol, kline))
+                        .map(|candle_data| candle_data.candle)
+                        .collect()),
+                    Err(e) => Err(e.to_string()),
+                });
+            (symbol.to_string(), candles)
+        }));
+    }
+
+    let mut history = Vec::with_capacity(handles.len());
+    for handle in handles {
+        match handle.await {
+            Ok((symbol, candles)) => {
+                println!(
+                    "[INFO][WARMUP] Loaded {} candles for {}",
+                    candles.len(),
+                    symbol
+                );
+                history.push((symbol, candles));
+            }
+            Err(e) => println!("[ERROR][WARMUP] Task error: {}", e),
+        }
+    }
+    history
+}
+
+fn now_unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 // trader task to trade one symbol.receiver for time/prices as they are generated
-async fn trader<T: Strategy + Send>(config: TraderConfig<T>) {
+async fn trader<T: Strategy + Send>(config: TraderConfig<T>, warmup: WarmupConfig) {
     println!("IN TRADER");
+    let snapshot = config.snapshot.clone();
     let mut executioner = Executioner::new(config);
     for symbol in CRYPTOS {
         executioner.add_symbol(symbol.to_string(), default_precision(symbol));
     }
+    if let Some(snapshot) = &snapshot
+        && !resume(&mut executioner, &snapshot.path)
+    {
+        return;
+    }
+    if warmup.candles > 0 {
+        executioner.warm_up(&fetch_history(&warmup).await);
+    }
 
     executioner.run(false).await;
 }
 
-async fn trading_task<T: Strategy + Send + 'static + std::marker::Sync>(
+// restores the executioner from its last snapshot, if there is one; false
+// when a snapshot exists but cannot be used, so nothing trades on top of it
+fn resume<T: Strategy + Send>(executioner: &mut Executioner<T>, path: &str) -> bool {
+    if !Path::new(path).exists() {
+        println!("[INFO][SNAPSHOT] No snapshot at {}, starting fresh", path);
+        return true;
+    }
+    let restored = ExecutionerSnapshot::load(path).and_then(|snapshot| {
+        let age = (now_unix_millis() / 1_000).saturating_sub(snapshot.saved_at);
+        executioner.restore(snapshot).map(|()| age)
+    });
+    match restored {
+        Ok(age) => {
+            println!("[INFO][SNAPSHOT] Resumed from {} saved {}s ago", path, age);
+            true
+        }
+        Err(e) => {
+            println!(
+                "[ERROR][SNAPSHOT] Refusing to trade from {}: {}. Move it away to start fresh",
+                path, e
+            );
+            false
+        }
+    }
+}
+
+async fn trading_task(
     mut bt_rx: mpsc::Receiver<CandleData>,
-    initial_capital: f64,
     api_key: String,
     api_secret: String,
-    strategy: T,
+    strategy_name: String,
+    config: ConfigFile,
+    config_path: Option<String>,
 ) -> () {
+    let strategy = match registry::build(&strategy_name, &config) {
+        Err(ConfigError::UnknownStrategy(name)) => {
+            println!("[WARN][MAIN] Unknown strategy {}, trading fourier", name);
+            registry::build(Fourier::SECTION, &config)
+        }
+        built => built,
+    }
+    .expect("invalid strategy config");
+    let symbol_strategies =
+        registry::symbol_strategies(&config).expect("invalid symbol strategies");
+    let risk_limits = RiskLimits::from_config(&config).expect("invalid risk limits");
+    let portfolio = PortfolioConfig::from_config(&config).expect("invalid portfolio config");
+    let shadows = ShadowConfig::from_config(&config)
+        .and_then(|shadows| {
+            shadows
+                .map(|shadows| shadows.build(&config, &risk_limits))
+                .transpose()
+        })
+        .expect("invalid shadow strategies");
+    let warmup = WarmupConfig::from_config(&config).expect("invalid warmup config");
+    let snapshot = SnapshotConfig::from_config(&config).expect("invalid snapshot config");
+    let config_watch = config_path.map(|path| ConfigWatch::new(&path, config.clone()));
+
     let (candle_tx, candle_rx) = mpsc::channel(32);
     let (oe_tx, oe_rx) = mpsc::channel(32);
     let (starting_capital, initial_positions) =
-        fetch_account_state(&api_key, &api_secret, initial_capital).await;
+        fetch_account_state(&api_key, &api_secret, INIT_CAPITAL).await;
     let config = TraderConfig {
         initial_capital: starting_capital,
-        strategy: strategy,
+        strategy,
         candle_data_rx: candle_rx,
         order_engine_tx: oe_tx,
         api_key: api_key.clone(),
         api_secret: api_secret.clone(),
         initial_positions,
+        risk_limits,
+        portfolio,
+        symbol_strategies,
+        shadows,
+        config_watch,
+        snapshot,
     };
 
     let trader_handle = tokio::spawn(async move {
-        trader(config).await;
+        trader(config, warmup).await;
     });
 
     let order_engine_handle = tokio::spawn(async move {
@@ -140,6 +326,21 @@ async fn trading_task<T: Strategy + Send + 'static + std::marker::Sync>(
 }
 //
 
+// whether the market maker trades anywhere, live or in a shadow, and so
+// needs Roostoo's ticker
+fn quotes_needed(config: &ConfigFile, strategy_name: &str) -> bool {
+    let assigned = registry::symbol_strategy_names(config).unwrap_or_default();
+    let shadowed = ShadowConfig::from_config(config)
+        .ok()
+        .flatten()
+        .map(|shadows| shadows.strategies)
+        .unwrap_or_default();
+    std::iter::once(strategy_name)
+        .chain(assigned.values().map(|name| name.as_str()))
+        .chain(shadowed.iter().map(|name| name.as_str()))
+        .any(|name| name == MarketMaker::SECTION)
+}
+
 const INIT_CAPITAL: f64 = 50_005.91;
 
 #[tokio::main]
@@ -149,14 +350,36 @@ async fn main() {
     let rs_api_key = env::var("ROOSTOO_API_KEY").unwrap();
     let rs_api_secret = env::var("ROOSTOO_API_SECRET").unwrap();
 
+    let config_path = env::var("FOURIER_CONFIG").ok();
+    let config = match &config_path {
+        Some(path) => ConfigFile::load(path).expect("unreadable config file"),
+        None => ConfigFile::default(),
+    };
+    let strategy_name = config
+        .raw("strategy")
+        .and_then(|name| name.as_str())
+        .unwrap_or(Fourier::SECTION)
+        .to_string();
+    println!("[INFO][MAIN] Trading with strategy {}", strategy_name);
+
+    let record_dir = env::var("TICKER_RECORD_DIR").ok();
+    let quotes = (quotes_needed(&config, &strategy_name) || record_dir.is_some())
+        .then(|| RoostooClient::new(rs_api_key.clone(), rs_api_secret.clone()));
     let (bt_tx, bt_rx) = mpsc::channel(32);
     let binance_task = tokio::spawn(async move {
-        binance_task(bt_tx).await;
+        binance_task(bt_tx, quotes, record_dir).await;
     });
 
-    let god_strategy = Fourier {};
     let trader_task = tokio::spawn(async move {
-        trading_task(bt_rx, INIT_CAPITAL, rs_api_key, rs_api_secret, god_strategy).await;
+        trading_task(
+            bt_rx,
+            rs_api_key,
+            rs_api_secret,
+            strategy_name,
+            config,
+            config_path,
+        )
+        .await
     });
 
     let (binance_res, trader_res) = tokio::join!(binance_task, trader_task);
@@ -204,10 +427,10 @@ async fn fetch_account_state(
                 .unwrap_or(fallback_capital);
             let mut positions = HashMap::new();
             for symbol in CRYPTOS {
-                if let Some(info) = balance.spot_wallet.get(symbol) {
-                    if info.free > 0.0 {
-                        positions.insert(symbol.to_string(), info.free);
-                    }
+                if let Some(info) = balance.spot_wallet.get(symbol)
+                    && info.free > 0.0
+                {
+                    positions.insert(symbol.to_string(), info.free);
                 }
             }
             (capital, positions)
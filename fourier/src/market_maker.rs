@@ -0,0 +1,381 @@
+use crate::config::{ConfigError, ConfigFile, SymbolParams, Validate, check_range};
+use crate::order_engine::{RestingOrder, round_significant};
+use crate::roostoo::{OrderSide, OrderType};
+use crate::strategy::{ExecContext, Order, OrderIntent, Quote, SharedState, Strategy};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Tuning for `MarketMaker`, loaded from the `market_maker` section of the
+/// config file. Basis points are of Roostoo's mid.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarketMakerParams {
+    pub quote_notional: f64,     // USD shown on each side
+    pub min_spread_bps: f64,     // quote only while Roostoo's spread is at least this wide
+    pub improve_bps: f64,        // how far inside the best bid and ask to quote
+    pub inventory_skew_bps: f64, // shift of both quotes at full inventory
+    pub lead_weight: f64,        // share of the Binance premium to lean quotes by
+    pub requote_bps: f64,        // replace a quote once it is this far from its target
+    pub max_inventory_notional: f64,
+    pub max_loss: f64, // USD realised plus unrealised before the symbol stops quoting
+    pub max_quote_age_secs: u64, // pull quotes when the ticker is older than this
+    pub significant_digits: i32, // quote prices are rounded to this many
+}
+
+impl Default for MarketMakerParams {
+    fn default() -> Self {
+        MarketMakerParams {
+            quote_notional: 100.0,
+            min_spread_bps: 10.0,
+            improve_bps: 1.0,
+            inventory_skew_bps: 20.0,
+            lead_weight: 0.5,
+            requote_bps: 2.0,
+            max_inventory_notional: 1_000.0,
+            max_loss: 50.0,
+            max_quote_age_secs: 10,
+            significant_digits: 5,
+        }
+    }
+}
+
+impl Validate for MarketMakerParams {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        check_range(scope, "quote_notional", self.quote_notional, 0.0, f64::MAX)?;
+        check_range(scope, "min_spread_bps", self.min_spread_bps, 0.0, 10_000.0)?;
+        // improving by half the spread or more would meet the other side
+        check_range(
+            scope,
+            "improve_bps",
+            self.improve_bps,
+            0.0,
+            self.min_spread_bps / 2.0,
+        )?;
+        check_range(
+            scope,
+            "inventory_skew_bps",
+            self.inventory_skew_bps,
+            0.0,
+            10_000.0,
+        )?;
+        check_range(scope, "lead_weight", self.lead_weight, 0.0, 1.0)?;
+        check_range(scope, "requote_bps", self.requote_bps, 0.0, 10_000.0)?;
+        check_range(
+            scope,
+            "max_inventory_notional",
+            self.max_inventory_notional,
+            self.quote_notional,
+            f64::MAX,
+        )?;
+        check_range(scope, "max_loss", self.max_loss, 0.0, f64::MAX)?;
+        check_range(
+            scope,
+            "max_quote_age_secs",
+            self.max_quote_age_secs as f64,
+            1.0,
+            3_600.0,
+        )?;
+        check_range(
+            scope,
+            "significant_digits",
+            self.significant_digits as f64,
+            1.0,
+            12.0,
+        )
+    }
+}
+
+/// Quotes a bid and an ask just inside Roostoo's spread from its ticker.
+/// Both quotes lean towards Binance, which leads Roostoo, and away from
+/// held inventory, so a long book is sold down rather than added to. Quotes
+/// are cancelled and replaced once the target moves `requote_bps`, pulled
+/// while the ticker is stale or the book too tight, and a symbol that loses
+/// `max_loss` is flattened and stops quoting for the session.
+#[derive(Default)]
+pub struct MarketMaker {
+    pub params: SymbolParams<MarketMakerParams>,
+    halted: HashSet<String>,
+}
+
+impl MarketMaker {
+    pub const SECTION: &'static str = "market_maker";
+
+    pub fn new(params: SymbolParams<MarketMakerParams>) -> Self {
+        MarketMaker {
+            params,
+            halted: HashSet::new(),
+        }
+    }
+
+    pub fn from_config(config: &ConfigFile) -> Result<Self, ConfigError> {
+        Ok(Self::new(config.section_or_default(Self::SECTION)?))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::from_config(&ConfigFile::load(path)?)
+    }
+
+    /// Bid and ask to show against `quote`, or None while the book is
+    /// one-sided or tighter than `min_spread_bps`. Neither side crosses
+    /// Roostoo's best price on the other.
+    pub fn targets(
+        params: &MarketMakerParams,
+        ctx: &ExecContext,
+        quote: &Quote,
+    ) -> Option<(f64, f64)> {
+        if quote.spread_bps()? < params.min_spread_bps {
+            return None;
+        }
+        let mid = quote.mid();
+        let inventory =
+            (ctx.position.notional(mid) / params.max_inventory_notional).clamp(0.0, 1.0);
+        let lead = if ctx.last_close > 0.0 {
+            (ctx.last_close - mid) / mid
+        } else {
+            0.0
+        };
+        let shift = params.lead_weight * lead - params.inventory_skew_bps * inventory / 10_000.0;
+        let improve = params.improve_bps / 10_000.0;
+
+        let bid = (quote.bid * (1.0 + improve + shift)).min(quote.ask * (1.0 - improve));
+        let ask = (quote.ask * (1.0 - improve + shift)).max(quote.bid * (1.0 + improve));
+        let bid = round_significant(bid, params.significant_digits);
+        let ask = round_significant(ask, params.significant_digits);
+        (bid > 0.0 && bid < ask).then_some((bid, ask))
+    }
+
+    /// USD lost on the symbol this session, realised and marked
+    pub fn loss(ctx: &ExecContext) -> f64 {
+        -(ctx.position.realized_pnl + ctx.position.unrealized_pnl)
+    }
+
+    // keeps at most one order per side near `target`, cancelling the rest,
+    // and asks for a new one when none is close enough
+    fn requote(
+        params: &MarketMakerParams,
+        ctx: &ExecContext,
+        resting: &[RestingOrder],
+        side: OrderSide,
+        target: Option<(f64, f64)>, // price, quantity
+        intents: &mut Vec<OrderIntent>,
+        places: &mut Vec<OrderIntent>,
+    ) {
+        let mut kept = false;
+        for order in resting.iter().filter(|order| order.side == side) {
+            let close = target.is_some_and(|(price, _)| {
+                (order.price - price).abs() / price * 10_000.0 < params.requote_bps
+            });
+            if close && !kept {
+                kept = true;
+            } else {
+                intents.push(OrderIntent::Cancel(order.id));
+            }
+        }
+        if let (false, Some((price, quantity))) = (kept, target) {
+            places.push(OrderIntent::Place(Order {
+                pair: ctx.pair(),
+                side,
+                order_type: OrderType::Limit,
+                quantity,
+                price: Some(price),
+            }));
+        }
+    }
+}
+
+#[async_trait]
+impl Strategy for MarketMaker {
+    async fn should_long(
+        &self,
+        _ctx: &mut ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        false
+    }
+
+    async fn go_long(
+        &self,
+        _ctx: &ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> Option<Order> {
+        None
+    }
+
+    async fn update_position(
+        &self,
+        ctx: &mut ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        Self::loss(ctx) >= self.params.get(&ctx.symbol).max_loss
+    }
+
+    async fn manage_orders(
+        &mut self,
+        ctx: &ExecContext,
+        resting: &[RestingOrder],
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> Vec<OrderIntent> {
+        let params = self.params.get(&ctx.symbol);
+        let pull_all = || {
+            resting
+                .iter()
+                .map(|order| OrderIntent::Cancel(order.id))
+                .collect()
+        };
+
+        if self.halted.contains(&ctx.symbol) {
+            return pull_all();
+        }
+        if Self::loss(ctx) >= params.max_loss {
+            println!(
+                "[WARN][MM] {} lost {:.2} USD, no more quotes this session",
+                ctx.symbol,
+                Self::loss(ctx)
+            );
+            self.halted.insert(ctx.symbol.clone());
+            return pull_all();
+        }
+        let Some(quote) = ctx
+            .quote
+            .filter(|quote| ctx.now_secs().saturating_sub(quote.time) <= params.max_quote_age_secs)
+        else {
+            return pull_all();
+        };
+        let Some((bid, ask)) = Self::targets(params, ctx, &quote) else {
+            return pull_all();
+        };
+
+        let held = ctx.position.notional(quote.mid());
+        let bid_target = (held + params.quote_notional <= params.max_inventory_notional)
+            .then(|| (bid, params.quote_notional / bid));
+        let ask_quantity = ctx.position.quantity.min(params.quote_notional / ask);
+        let ask_target = (ask_quantity > 0.0).then_some((ask, ask_quantity));
+
+        // cancels go first so a replaced ask frees its inventory
+        let mut intents = Vec::new();
+        let mut places = Vec::new();
+        for (side, target) in [(OrderSide::Buy, bid_target), (OrderSide::Sell, ask_target)] {
+            Self::requote(
+                params,
+                ctx,
+                resting,
+                side,
+                target,
+                &mut intents,
+                &mut places,
+            );
+        }
+        intents.extend(places);
+        intents
+    }
+
+    fn reload(&mut self, config: &ConfigFile) -> Result<(), ConfigError> {
+        self.params = config.section_or_default(Self::SECTION)?;
+        Ok(())
+    }
+
+    // a symbol past `max_loss` stays off for the session, restarts included
+    fn snapshot(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(&self.halted).ok()
+    }
+
+    fn restore(&mut self, state: serde_json::Value) -> Result<(), serde_json::Error> {
+        self.halted = serde_json::from_value(state)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fourier::Position;
+
+    fn context(binance: f64, bid: f64, ask: f64, held: f64) -> ExecContext {
+        let mut position = Position::empty("BTC");
+        if held > 0.0 {
+            position.open_new(held, 100.0, 0.0, Some(0)).unwrap();
+        }
+        ExecContext {
+            position,
+            last_close: binance,
+            quote: Some(Quote {
+                time: 0,
+                bid,
+                ask,
+                last: bid,
+            }),
+            ..ExecContext::for_test("BTC", Vec::new())
+        }
+    }
+
+    fn placed(intents: &[OrderIntent]) -> Vec<(OrderSide, f64)> {
+        intents
+            .iter()
+            .filter_map(|intent| match intent {
+                OrderIntent::Place(order) => Some((order.side.clone(), order.price.unwrap())),
+                OrderIntent::Cancel(_) => None,
+            })
+            .collect()
+    }
+
+    fn resting(id: u64, side: OrderSide, price: f64) -> RestingOrder {
+        RestingOrder {
+            id,
+            symbol: "BTC".to_string(),
+            side,
+            price,
+            quantity: 1.0,
+            filled: 0.0,
+            fee_paid: 0.0,
+            reservation: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn quotes_inside_the_spread_skewed_by_inventory_and_lead() {
+        let params = MarketMakerParams {
+            improve_bps: 20.0,
+            min_spread_bps: 50.0,
+            inventory_skew_bps: 50.0,
+            ..Default::default()
+        };
+        let mut mm = MarketMaker::new(SymbolParams::new(params.clone()));
+        let shared = Arc::new(Mutex::new(SharedState::new(10_000.0)));
+
+        // flat with Binance at Roostoo's mid: a bid just inside, nothing to offer
+        let flat = context(100.0, 99.0, 101.0, 0.0);
+        let intents = mm.manage_orders(&flat, &[], shared.clone()).await;
+        assert_eq!(placed(&intents), [(OrderSide::Buy, 99.198)]);
+        let (bid, ask) = MarketMaker::targets(&params, &flat, &flat.quote.unwrap()).unwrap();
+        assert_eq!(ask, 100.8);
+
+        // holding half the inventory bound lowers both quotes
+        let long = context(100.0, 99.0, 101.0, 5.0);
+        let skewed = MarketMaker::targets(&params, &long, &long.quote.unwrap()).unwrap();
+        assert!(skewed.0 < bid && skewed.1 < ask);
+        // Binance trading above Roostoo lifts them, without crossing the book
+        let lead = context(100.4, 99.0, 101.0, 0.0);
+        let leaning = MarketMaker::targets(&params, &lead, &lead.quote.unwrap()).unwrap();
+        assert!(leaning.0 > bid && leaning.1 > ask && leaning.0 < 101.0);
+
+        // the book moves: the stale bid is cancelled before its replacement
+        let moved = context(101.0, 100.5, 101.5, 0.0);
+        let book = [resting(7, OrderSide::Buy, 99.198)];
+        let intents = mm.manage_orders(&moved, &book, shared.clone()).await;
+        assert!(matches!(intents[0], OrderIntent::Cancel(7)));
+        assert_eq!(placed(&intents), [(OrderSide::Buy, 100.7)]);
+
+        // past the loss limit everything is pulled for good
+        let mut losing = context(101.0, 100.5, 101.5, 0.0);
+        losing.position.realized_pnl = -60.0;
+        assert!(mm.update_position(&mut losing, shared.clone()).await);
+        let intents = mm.manage_orders(&losing, &book, shared.clone()).await;
+        assert!(matches!(intents[..], [OrderIntent::Cancel(7)]));
+        let intents = mm.manage_orders(&moved, &[], shared).await;
+        assert!(intents.is_empty());
+    }
+}
@@ -0,0 +1,301 @@
+use crate::config::{ConfigError, ConfigFile, Validate, check_range};
+use crate::indicators::Indicators;
+use crate::portfolio::Allocation;
+use crate::strategy::{ExecContext, Order, SharedState, Strategy};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// How the selected symbols share the invested capital.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Weighting {
+    Equal,
+    /// Inversely proportional to each symbol's volatility over the lookback.
+    VolatilityScaled,
+}
+
+/// Tuning for `Momentum`, loaded from the `momentum` section of the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MomentumParams {
+    pub lookback: usize, // candles the score is measured over
+    pub top_k: usize,
+    pub weighting: Weighting,
+    pub rotation_secs: u64,
+    pub gross_weight: f64, // fraction of equity invested across the top K
+    pub min_score: f64,    // only hold symbols scoring above this
+    pub min_order_notional: f64,
+}
+
+impl Default for MomentumParams {
+    fn default() -> Self {
+        Self {
+            lookback: 1_800,
+            top_k: 3,
+            weighting: Weighting::Equal,
+            rotation_secs: 3_600,
+            gross_weight: 0.8,
+            min_score: 0.0,
+            min_order_notional: 10.0,
+        }
+    }
+}
+
+impl Validate for MomentumParams {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        check_range(scope, "lookback", self.lookback as f64, 2.0, 2047.0)?;
+        check_range(scope, "top_k", self.top_k as f64, 1.0, 64.0)?;
+        check_range(
+            scope,
+            "rotation_secs",
+            self.rotation_secs as f64,
+            1.0,
+            f64::MAX,
+        )?;
+        check_range(scope, "gross_weight", self.gross_weight, 0.0, 1.0)?;
+        check_range(
+            scope,
+            "min_order_notional",
+            self.min_order_notional,
+            0.0,
+            f64::MAX,
+        )
+    }
+}
+
+/// Cross-sectional momentum rotation: every `rotation_secs` rank all symbols
+/// by return over the lookback divided by the volatility over the same
+/// window, and hold the top K. Symbols without enough history yet are not
+/// ranked. Positions are only changed on rotation.
+#[derive(Debug, Default)]
+pub struct Momentum {
+    pub params: MomentumParams,
+    holding: Vec<String>,
+}
+
+impl Momentum {
+    pub const SECTION: &'static str = "momentum";
+
+    pub fn new(params: MomentumParams) -> Self {
+        Self {
+            params,
+            holding: Vec::new(),
+        }
+    }
+
+    pub fn from_config(config: &ConfigFile) -> Result<Self, ConfigError> {
+        Ok(Self::new(
+            config.settings(Self::SECTION)?.unwrap_or_default(),
+        ))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::from_config(&ConfigFile::load(path)?)
+    }
+
+    /// Risk-adjusted return over `lookback` candles and the volatility used,
+    /// None without enough history
+    pub fn score(&self, ctx: &ExecContext) -> Option<(f64, f64)> {
+        let lookback = self.params.lookback;
+        let returns = Indicators::new(&ctx.candles).log_returns(lookback)?;
+        let total: f64 = returns.iter().sum();
+        let mean = total / lookback as f64;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / lookback as f64;
+        let window_vol = (variance * lookback as f64).sqrt();
+        if window_vol <= 0.0 {
+            return None;
+        }
+        Some((total / window_vol, window_vol))
+    }
+
+    /// Top K symbols by score with their weights of equity
+    pub fn rank(&self, contexts: &HashMap<String, ExecContext>) -> Vec<(String, f64, f64)> {
+        let mut scored: Vec<(String, f64, f64)> = contexts
+            .values()
+            .filter_map(|ctx| {
+                let (score, vol) = self.score(ctx)?;
+                (score > self.params.min_score).then(|| (ctx.symbol.clone(), score, vol))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scored.truncate(self.params.top_k);
+        scored
+    }
+}
+
+#[async_trait]
+impl Strategy for Momentum {
+    async fn should_long(
+        &self,
+        _ctx: &mut ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        false
+    }
+
+    async fn go_long(
+        &self,
+        _ctx: &ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> Option<Order> {
+        None
+    }
+
+    async fn update_position(
+        &self,
+        _ctx: &mut ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        false
+    }
+
+    fn allocation_secs(&self) -> u64 {
+        self.params.rotation_secs
+    }
+
+    async fn target_weights(
+        &mut self,
+        contexts: &HashMap<String, ExecContext>,
+        now: u64,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> Option<Option<Allocation>> {
+        // symbols without `lookback` candles yet are left out of the ranking;
+        // wait until at least one has them
+        let ready = contexts
+            .values()
+            .any(|ctx| ctx.candles.len() > self.params.lookback);
+        if !ready {
+            return None;
+        }
+
+        let top = self.rank(contexts);
+        let inverse_vol: f64 = top.iter().map(|(_, _, vol)| 1.0 / vol).sum();
+        let weights: HashMap<String, f64> = top
+            .iter()
+            .map(|(symbol, _, vol)| {
+                let share = match self.params.weighting {
+                    Weighting::Equal => 1.0 / top.len() as f64,
+                    Weighting::VolatilityScaled => (1.0 / vol) / inverse_vol,
+                };
+                (symbol.clone(), share * self.params.gross_weight)
+            })
+            .collect();
+
+        let selected: Vec<String> = top.into_iter().map(|(symbol, _, _)| symbol).collect();
+        if selected != self.holding {
+            println!(
+                "[INFO][MOMENTUM] {} rotating {:?} -> {:?}",
+                now, self.holding, selected
+            );
+            self.holding = selected;
+        }
+
+        Some(Some(Allocation {
+            weights,
+            min_order_notional: self.params.min_order_notional,
+            // rotations may swap the whole book
+            turnover_budget: 2.0,
+        }))
+    }
+
+    fn reload(&mut self, config: &ConfigFile) -> Result<(), ConfigError> {
+        self.params = config.settings(Self::SECTION)?.unwrap_or_default();
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(&self.holding).ok()
+    }
+
+    fn restore(&mut self, state: serde_json::Value) -> Result<(), serde_json::Error> {
+        self.holding = serde_json::from_value(state)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fourier::Candle;
+
+    fn context(symbol: &str, drift: f64) -> ExecContext {
+        let mut price = 100.0;
+        let candles = (0..50u64)
+            .map(|i| {
+                // same noise for every symbol, different drift
+                price *= 1.0 + drift + if i % 2 == 0 { 0.001 } else { -0.001 };
+                Candle {
+                    close_time: (1_700_000_000 + i) * 1_000,
+                    close: price,
+                    ..Default::default()
+                }
+            })
+            .collect();
+        ExecContext::for_test(symbol, candles)
+    }
+
+    #[tokio::test]
+    async fn holds_the_strongest_symbols() {
+        let mut momentum = Momentum::new(MomentumParams {
+            lookback: 20,
+            top_k: 2,
+            ..Default::default()
+        });
+        let contexts: HashMap<String, ExecContext> = [
+            ("BTC", 0.0005),
+            ("ETH", 0.0010),
+            ("SOL", 0.0002),
+            ("DOGE", -0.0010),
+        ]
+        .into_iter()
+        .map(|(s, drift)| (s.to_string(), context(s, drift)))
+        .collect();
+
+        let shared = Arc::new(Mutex::new(SharedState::new(1_000.0)));
+        let allocation = momentum
+            .target_weights(&contexts, 0, shared)
+            .await
+            .flatten()
+            .unwrap();
+        let mut held: Vec<&String> = allocation.weights.keys().collect();
+        held.sort();
+        assert_eq!(held, ["BTC", "ETH"]);
+        assert!((allocation.weights["ETH"] - 0.4).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn ranks_the_symbols_with_enough_history() {
+        let mut momentum = Momentum::new(MomentumParams {
+            lookback: 20,
+            top_k: 2,
+            ..Default::default()
+        });
+        let mut listed = context("NEW", 0.0020);
+        listed.candles.truncate(10);
+        let shared = Arc::new(Mutex::new(SharedState::new(1_000.0)));
+
+        let only_new = HashMap::from([("NEW".to_string(), listed.clone())]);
+        assert!(
+            momentum
+                .target_weights(&only_new, 0, shared.clone())
+                .await
+                .is_none()
+        );
+
+        let contexts = HashMap::from([
+            ("NEW".to_string(), listed),
+            ("BTC".to_string(), context("BTC", 0.0005)),
+        ]);
+        let allocation = momentum
+            .target_weights(&contexts, 0, shared)
+            .await
+            .flatten()
+            .unwrap();
+        let held: Vec<&String> = allocation.weights.keys().collect();
+        assert_eq!(held, ["BTC"]);
+    }
+}
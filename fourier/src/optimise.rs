@@ -0,0 +1,164 @@
+use anyhow::{Result, bail};
+use fourier::backtest::{load_candles, period_returns};
+use fourier::bollinger::{Bollinger, BollingerParams};
+use fourier::breakout::{Breakout, BreakoutParams};
+use fourier::fourier::{Fourier, FourierParams};
+use fourier::optimiser::{SweepSpec, Trial, grid, rank, run_grid, write_csv, write_heatmap};
+use fourier::robustness::analyse;
+use fourier::walkforward::{self, WalkForwardReport, WalkForwardSpec, walk_forward};
+use std::env;
+use std::sync::Arc;
+
+const USAGE: &str = "usage: optimise <grid|walkforward> <spec.yaml>";
+
+async fn sweep(spec: &SweepSpec) -> Result<Vec<Trial>> {
+    let candles = Arc::new(load_candles(&spec.csv)?);
+    let assignments = grid(&spec.parsed_ranges()?);
+    println!(
+        "[INFO][OPTIMISER] {} candles, {} combinations",
+        candles.len(),
+        assignments.len()
+    );
+
+    let trials = match spec.strategy.as_str() {
+        "fourier" => {
+            run_grid::<FourierParams, _, _>(
+                candles,
+                spec,
+                Fourier::SECTION,
+                assignments,
+                Fourier::new,
+            )
+            .await
+        }
+        "bollinger" => {
+            run_grid::<BollingerParams, _, _>(
+                candles,
+                spec,
+                Bollinger::SECTION,
+                assignments,
+                Bollinger::new,
+            )
+            .await
+        }
+        "breakout" => {
+            run_grid::<BreakoutParams, _, _>(
+                candles,
+                spec,
+                Breakout::SECTION,
+                assignments,
+                Breakout::new,
+            )
+            .await
+        }
+        other => bail!("unknown strategy: {}", other),
+    };
+    Ok(trials)
+}
+
+async fn walk(spec: &WalkForwardSpec) -> Result<WalkForwardReport> {
+    let candles = load_candles(&spec.sweep.csv)?;
+    match spec.sweep.strategy.as_str() {
+        "fourier" => {
+            walk_forward::<FourierParams, _, _>(&candles, spec, Fourier::SECTION, Fourier::new)
+                .await
+        }
+        "bollinger" => {
+            walk_forward::<BollingerParams, _, _>(
+                &candles,
+                spec,
+                Bollinger::SECTION,
+                Bollinger::new,
+            )
+            .await
+        }
+        "breakout" => {
+            walk_forward::<BreakoutParams, _, _>(&candles, spec, Breakout::SECTION, Breakout::new)
+                .await
+        }
+        other => bail!("unknown strategy: {}", other),
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let (Some(command), Some(spec_path)) = (args.get(1), args.get(2)) else {
+        bail!(USAGE);
+    };
+
+    match command.as_str() {
+        "grid" => {
+            let spec = SweepSpec::load(spec_path)?;
+            let mut trials = sweep(&spec).await?;
+            rank(&mut trials, spec.rank_by);
+
+            let csv_path = format!("{}.csv", spec.output);
+            let html_path = format!("{}.html", spec.output);
+            write_csv(&csv_path, &trials, spec.rank_by)?;
+            write_heatmap(&html_path, &trials, spec.rank_by);
+
+            if let Some(best) = trials.first() {
+                println!(
+                    "[SUCCESS][OPTIMISER] Best {:?} {:.4}: {:?}",
+                    spec.rank_by,
+                    spec.rank_by.score(&best.report),
+                    best.assignment
+                );
+
+                let equity = |trial: &Trial| -> Vec<f64> {
+                    trial.report.equity_curve.iter().map(|(_, e)| *e).collect()
+                };
+                let trial_returns: Vec<Vec<f64>> =
+                    trials.iter().map(|t| period_returns(&equity(t))).collect();
+                let robustness = analyse(
+                    &best.report.trades,
+                    &trial_returns[0],
+                    &trial_returns,
+                    spec.initial_capital,
+                    &spec.robustness,
+                );
+                println!(
+                    "[INFO][ROBUSTNESS] Bootstrap final equity p05 {:.2} p50 {:.2} p95 {:.2}, max drawdown p95 {:.2}%",
+                    robustness.bootstrap.final_equity.p05,
+                    robustness.bootstrap.final_equity.p50,
+                    robustness.bootstrap.final_equity.p95,
+                    robustness.bootstrap.max_drawdown_pct.p95
+                );
+                println!(
+                    "[INFO][ROBUSTNESS] Deflated sharpe {:.3}, probability of overfitting {}",
+                    robustness.deflated_sharpe,
+                    robustness
+                        .probability_of_overfitting
+                        .map(|p| format!("{:.3}", p))
+                        .unwrap_or_else(|| "n/a".to_string())
+                );
+                let yaml_path = format!("{}_robustness.yaml", spec.output);
+                std::fs::write(&yaml_path, serde_yaml::to_string(&robustness)?)?;
+                println!("[INFO][OPTIMISER] Wrote {}", yaml_path);
+            }
+            println!("[INFO][OPTIMISER] Wrote {} and {}", csv_path, html_path);
+        }
+        "walkforward" => {
+            let spec = WalkForwardSpec::load(spec_path)?;
+            let report = walk(&spec).await?;
+
+            let csv_path = format!("{}_walkforward.csv", spec.sweep.output);
+            let html_path = format!("{}_walkforward.html", spec.sweep.output);
+            walkforward::write_csv(&csv_path, &report)?;
+            walkforward::write_equity_plot(&html_path, &report);
+
+            println!(
+                "[SUCCESS][WALKFORWARD] {} windows, return {:.2}%, max drawdown {:.2}%, sharpe {:.2}, efficiency {:.2}",
+                report.windows.len(),
+                report.total_return_pct(),
+                report.max_drawdown_pct(),
+                report.sharpe(),
+                report.efficiency()
+            );
+            println!("[INFO][WALKFORWARD] Wrote {} and {}", csv_path, html_path);
+        }
+        _ => bail!(USAGE),
+    }
+    Ok(())
+}
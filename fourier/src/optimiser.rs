@@ -0,0 +1,493 @@
+use crate::backtest::{BackTester, BacktestReport};
+use crate::config::{ConfigFile, SymbolParams, Validate, merge};
+use crate::fourier::Candle;
+use crate::risk::RiskLimits;
+use crate::robustness::RobustnessConfig;
+use crate::strategy::Strategy;
+use anyhow::{Context, Result, bail};
+use plotly::{HeatMap, Plot};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// One point of a parameter grid: dotted parameter path -> value,
+/// e.g. `ema_fast` or `exits.0.stop_loss_pct`.
+pub type Assignment = Vec<(String, Value)>;
+
+/// Values a single parameter takes in a sweep. In YAML either a list
+/// (`[8, 12, 16]`) or a range (`{start: 40, end: 70, step: 10}`, end inclusive).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ParamRange {
+    Values(Vec<Value>),
+    Linear { start: f64, end: f64, step: f64 },
+}
+
+impl ParamRange {
+    pub fn values(&self) -> Vec<Value> {
+        match self {
+            ParamRange::Values(values) => values.clone(),
+            ParamRange::Linear { start, end, step } => {
+                if *step <= 0.0 || end < start {
+                    return vec![Value::from(*start)];
+                }
+                let count = ((end - start) / step + 1e-9).floor() as usize + 1;
+                // keep integers integral so usize fields still deserialize
+                let integral = start.fract() == 0.0 && step.fract() == 0.0;
+                (0..count)
+                    .map(|i| start + step * i as f64)
+                    .map(|v| {
+                        if integral {
+                            Value::from(v as i64)
+                        } else {
+                            Value::from(v)
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// What the sweep ranks runs by. Higher scores are better.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    #[default]
+    Sharpe,
+    TotalReturn,
+    Calmar,
+    MaxDrawdown,
+}
+
+impl Metric {
+    pub fn score(&self, report: &BacktestReport) -> f64 {
+        match self {
+            Metric::Sharpe => report.sharpe,
+            Metric::TotalReturn => report.total_return_pct,
+            Metric::Calmar => report.calmar(),
+            Metric::MaxDrawdown => -report.max_drawdown_pct,
+        }
+    }
+}
+
+/// Grid search description, read from YAML by the `optimise` binary.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SweepSpec {
+    #[serde(default = "default_strategy")]
+    pub strategy: String,
+    pub csv: String,
+    pub symbol: String,
+    #[serde(default = "default_capital")]
+    pub initial_capital: f64,
+    #[serde(default)]
+    pub rank_by: Metric,
+    #[serde(default = "default_output")]
+    pub output: String, // writes <output>.csv and <output>.html
+    #[serde(default)]
+    pub base: Mapping, // fixed parameter overrides applied to every run
+    pub ranges: Mapping, // parameter path -> ParamRange, in sweep order
+    #[serde(default)]
+    pub robustness: RobustnessConfig,
+    // trading config whose `risk` section every run is held to, the
+    // defaults without one
+    #[serde(default)]
+    pub config: Option<String>,
+    #[serde(skip)]
+    pub risk_limits: RiskLimits, // loaded from `config`
+}
+
+fn default_strategy() -> String {
+    "fourier".to_string()
+}
+
+fn default_capital() -> f64 {
+    50_000.0
+}
+
+fn default_output() -> String {
+    "sweep".to_string()
+}
+
+impl SweepSpec {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data =
+            std::fs::read_to_string(path).with_context(|| format!("read sweep {:?}", path))?;
+        let mut spec: Self = serde_yaml::from_str(&data).context("parse sweep spec")?;
+        spec.load_risk_limits()?;
+        Ok(spec)
+    }
+
+    /// Read `risk_limits` from the `risk` section of `config`
+    pub fn load_risk_limits(&mut self) -> Result<()> {
+        if let Some(path) = &self.config {
+            let config = ConfigFile::load(path)?;
+            self.risk_limits = RiskLimits::from_config(&config)?;
+        }
+        Ok(())
+    }
+
+    pub fn parsed_ranges(&self) -> Result<Vec<(String, ParamRange)>> {
+        self.ranges
+            .iter()
+            .map(|(key, value)| {
+                let key = key.as_str().context("range keys must be strings")?;
+                let range: ParamRange = serde_yaml::from_value(value.clone())
+                    .with_context(|| format!("invalid range for {}", key))?;
+                Ok((key.to_string(), range))
+            })
+            .collect()
+    }
+}
+
+/// Result of one backtest in a sweep.
+#[derive(Debug, Clone)]
+pub struct Trial {
+    pub assignment: Assignment,
+    pub report: BacktestReport,
+}
+
+/// Cartesian product of all ranges, first range varying slowest.
+pub fn grid(ranges: &[(String, ParamRange)]) -> Vec<Assignment> {
+    let mut assignments: Vec<Assignment> = vec![Vec::new()];
+    for (name, range) in ranges {
+        let values = range.values();
+        let mut next = Vec::with_capacity(assignments.len() * values.len());
+        for assignment in &assignments {
+            for value in &values {
+                let mut extended = assignment.clone();
+                extended.push((name.clone(), value.clone()));
+                next.push(extended);
+            }
+        }
+        assignments = next;
+    }
+    assignments
+}
+
+/// `base` with every assignment written at its dotted path
+pub fn apply(base: &Value, assignment: &Assignment) -> Result<Value> {
+    let mut value = base.clone();
+    for (path, v) in assignment {
+        set_path(&mut value, path, v.clone())?;
+    }
+    Ok(value)
+}
+
+fn set_path(root: &mut Value, path: &str, value: Value) -> Result<()> {
+    let mut current = root;
+    let mut segments = path.split('.').peekable();
+    while let Some(segment) = segments.next() {
+        let last = segments.peek().is_none();
+        current = match current {
+            Value::Sequence(items) => {
+                let index: usize = segment
+                    .parse()
+                    .with_context(|| format!("{}: expected list index, got {}", path, segment))?;
+                let Some(item) = items.get_mut(index) else {
+                    bail!("{}: index {} out of bounds", path, index);
+                };
+                item
+            }
+            Value::Mapping(map) => {
+                let key = Value::from(segment);
+                if !map.contains_key(&key) {
+                    map.insert(key.clone(), Value::Mapping(Mapping::new()));
+                }
+                map.get_mut(&key).expect("inserted above")
+            }
+            _ => bail!("{}: cannot descend into {}", path, segment),
+        };
+        if last {
+            *current = value;
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// Resolve a sweep point into validated strategy parameters: built-in
+/// defaults, then `base`, then the assignment.
+pub fn params_for<P>(
+    section: &str,
+    base: &Mapping,
+    assignment: &Assignment,
+) -> Result<SymbolParams<P>>
+where
+    P: DeserializeOwned + Serialize + Default + Validate,
+{
+    // paths like exits.0.stop_loss_pct need the defaults to descend into
+    let mut start = serde_yaml::to_value(P::default())?;
+    merge(&mut start, &Value::Mapping(base.clone()));
+    let params = apply(&start, assignment)?;
+
+    let mut wrapper = Mapping::new();
+    wrapper.insert(Value::from("default"), params);
+    Ok(SymbolParams::from_value(section, &Value::Mapping(wrapper))?)
+}
+
+/// Backtest every assignment on `candles` as `sweep` describes, at most one
+/// run per CPU core at a time. Assignments whose parameters fail validation
+/// are skipped.
+pub async fn run_grid<P, S, F>(
+    candles: Arc<Vec<Candle>>,
+    sweep: &SweepSpec,
+    section: &str,
+    assignments: Vec<Assignment>,
+    make: F,
+) -> Vec<Trial>
+where
+    P: DeserializeOwned + Serialize + Default + Validate,
+    S: Strategy + Send + 'static,
+    F: Fn(SymbolParams<P>) -> S,
+{
+    let cores = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let permits = Arc::new(Semaphore::new(cores));
+    let total = assignments.len();
+    let mut handles = Vec::with_capacity(total);
+
+    for assignment in assignments {
+        let params = match params_for::<P>(section, &sweep.base, &assignment) {
+            Ok(params) => params,
+            Err(e) => {
+                println!(
+                    "[WARN][OPTIMISER] Skipping {}: {}",
+                    describe(&assignment),
+                    e
+                );
+                continue;
+            }
+        };
+        let strategy = make(params);
+        let candles = candles.clone();
+        let symbol = sweep.symbol.clone();
+        let initial_capital = sweep.initial_capital;
+        let risk_limits = sweep.risk_limits.clone();
+        let permits = permits.clone();
+        handles.push(tokio::spawn(async move {
+            let _permit = permits.acquire_owned().await.ok();
+            let report = BackTester::create(strategy)
+                .quiet()
+                .with_risk_limits(risk_limits)
+                .run_candles(candles, &symbol, initial_capital)
+                .await;
+            Trial { assignment, report }
+        }));
+    }
+
+    let mut trials = Vec::with_capacity(handles.len());
+    for (i, handle) in handles.into_iter().enumerate() {
+        match handle.await {
+            Ok(trial) => {
+                println!(
+                    "[INFO][OPTIMISER] {}/{} {} -> return {:.2}% sharpe {:.2}",
+                    i + 1,
+                    total,
+                    describe(&trial.assignment),
+                    trial.report.total_return_pct,
+                    trial.report.sharpe
+                );
+                trials.push(trial);
+            }
+            Err(e) => println!("[ERROR][OPTIMISER] Backtest task failed: {}", e),
+        }
+    }
+    trials
+}
+
+/// Best first
+pub fn rank(trials: &mut [Trial], metric: Metric) {
+    trials.sort_by(|a, b| {
+        metric
+            .score(&b.report)
+            .partial_cmp(&metric.score(&a.report))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+pub fn value_label(value: &Value) -> String {
+    match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+pub fn describe(assignment: &Assignment) -> String {
+    assignment
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, value_label(v)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Ranked table: one row per trial, parameters first, then the metrics.
+pub fn write_csv<P: AsRef<Path>>(path: P, trials: &[Trial], metric: Metric) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path.as_ref())?;
+    let Some(first) = trials.first() else {
+        return Ok(());
+    };
+
+    let mut header = vec!["rank".to_string()];
+    header.extend(first.assignment.iter().map(|(k, _)| k.clone()));
+    header.extend(
+        [
+            "score",
+            "final_equity",
+            "total_return_pct",
+            "max_drawdown_pct",
+            "sharpe",
+            "trade_count",
+            "win_rate",
+        ]
+        .map(String::from),
+    );
+    writer.write_record(&header)?;
+
+    for (i, trial) in trials.iter().enumerate() {
+        let report = &trial.report;
+        let mut row = vec![(i + 1).to_string()];
+        row.extend(trial.assignment.iter().map(|(_, v)| value_label(v)));
+        row.extend([
+            metric.score(report).to_string(),
+            report.final_equity.to_string(),
+            report.total_return_pct.to_string(),
+            report.max_drawdown_pct.to_string(),
+            report.sharpe.to_string(),
+            report.trade_count.to_string(),
+            report.win_rate.to_string(),
+        ]);
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Heatmap of `metric` over the first two swept parameters. When more
+/// parameters were swept each cell shows the best score over the rest.
+pub fn write_heatmap<P: AsRef<Path>>(path: P, trials: &[Trial], metric: Metric) {
+    let Some(first) = trials.first() else {
+        return;
+    };
+    let x_name = first.assignment.first().map(|(k, _)| k.clone());
+    let y_name = first.assignment.get(1).map(|(k, _)| k.clone());
+
+    let label = |trial: &Trial, index: usize| {
+        trial
+            .assignment
+            .get(index)
+            .map(|(_, v)| value_label(v))
+            .unwrap_or_default()
+    };
+    let mut xs: Vec<String> = Vec::new();
+    let mut ys: Vec<String> = Vec::new();
+    for trial in trials {
+        let (x, y) = (label(trial, 0), label(trial, 1));
+        if !xs.contains(&x) {
+            xs.push(x);
+        }
+        if !ys.contains(&y) {
+            ys.push(y);
+        }
+    }
+
+    let mut z = vec![vec![f64::NAN; xs.len()]; ys.len()];
+    for trial in trials {
+        let xi = xs.iter().position(|x| *x == label(trial, 0)).unwrap_or(0);
+        let yi = ys.iter().position(|y| *y == label(trial, 1)).unwrap_or(0);
+        let score = metric.score(&trial.report);
+        if z[yi][xi].is_nan() || score > z[yi][xi] {
+            z[yi][xi] = score;
+        }
+    }
+
+    let mut plot = Plot::new();
+    plot.add_trace(HeatMap::new(xs, ys, z).name(format!("{:?}", metric)));
+    plot.set_layout(
+        plotly::Layout::new()
+            .title(format!("{:?} by parameters", metric))
+            .x_axis(plotly::layout::Axis::new().title(x_name.unwrap_or_default()))
+            .y_axis(plotly::layout::Axis::new().title(y_name.unwrap_or_default())),
+    );
+    plot.write_html(path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_range_is_inclusive_and_keeps_integers() {
+        let range = ParamRange::Linear {
+            start: 8.0,
+            end: 16.0,
+            step: 4.0,
+        };
+        let values: Vec<i64> = range.values().iter().filter_map(|v| v.as_i64()).collect();
+        assert_eq!(values, vec![8, 12, 16]);
+    }
+
+    #[test]
+    fn grid_covers_every_combination() {
+        let ranges = vec![
+            (
+                "ema_fast".to_string(),
+                ParamRange::Values(vec![Value::from(8), Value::from(12)]),
+            ),
+            (
+                "rsi_max".to_string(),
+                ParamRange::Values(vec![
+                    Value::from(50.0),
+                    Value::from(60.0),
+                    Value::from(70.0),
+                ]),
+            ),
+        ];
+        assert_eq!(grid(&ranges).len(), 6);
+    }
+
+    #[test]
+    fn apply_reaches_into_lists() {
+        let base: Mapping = serde_yaml::from_str(
+            "exits:\n  - kind: fixed_percent\n    stop_loss_pct: 2.0\n    take_profit_pct: 4.0\n",
+        )
+        .unwrap();
+        let assignment = vec![("exits.0.stop_loss_pct".to_string(), Value::from(1.5))];
+        let value = apply(&Value::Mapping(base), &assignment).unwrap();
+        assert_eq!(value["exits"][0]["stop_loss_pct"], Value::from(1.5));
+    }
+
+    #[test]
+    fn runs_are_held_to_the_configs_risk_limits() {
+        let dir = std::env::temp_dir();
+        let config = dir.join(format!("fourier-sweep-config-{}.yaml", std::process::id()));
+        let spec = dir.join(format!("fourier-sweep-{}.yaml", std::process::id()));
+        std::fs::write(&config, "risk:\n  max_trade_fraction: 0.05\n").unwrap();
+        std::fs::write(
+            &spec,
+            format!(
+                "csv: BTC.csv\nsymbol: BTC\nconfig: {}\nranges:\n  ema_fast: [8]\n",
+                config.display()
+            ),
+        )
+        .unwrap();
+        let loaded = SweepSpec::load(&spec).unwrap();
+        assert_eq!(loaded.risk_limits.max_trade_fraction, 0.05);
+        assert_eq!(
+            loaded.risk_limits.max_symbol_notional,
+            RiskLimits::default().max_symbol_notional
+        );
+        std::fs::remove_file(&config).unwrap();
+        std::fs::remove_file(&spec).unwrap();
+    }
+}
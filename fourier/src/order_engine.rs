@@ -1,65 +1,243 @@
-use crate::roostoo::{OrderDetail, RoostooClient};
+use crate::ledger::Reservation;
+use crate::roostoo::{OrderDetail, OrderSide, RoostooClient};
 use crate::strategy::Order;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use tokio::sync::{mpsc, oneshot};
 
 pub struct OrderWithResponse {
     pub order: Order,
     pub precision: u64,
-    pub response: oneshot::Sender<OrderDetail>,
+    // Err carries the exchange's reason when the order was not filled
+    pub response: oneshot::Sender<Result<OrderDetail, String>>,
 }
+
+/// Work for the order engine. Limit orders come back from `Place` still
+/// pending; `Query` and `Cancel` follow them up while they rest on the book.
+pub enum EngineRequest {
+    Place(OrderWithResponse),
+    Query {
+        order_id: Option<u64>,
+        pair: Option<String>,
+        pending_only: bool,
+        response: oneshot::Sender<Result<Vec<OrderDetail>, String>>,
+    },
+    Cancel {
+        order_id: u64,
+        response: oneshot::Sender<Result<(), String>>,
+    },
+}
+
+/// A limit order waiting on the exchange (or the backtest's simulated book).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestingOrder {
+    pub id: u64,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub price: f64,
+    pub quantity: f64,
+    pub filled: f64,   // base units filled so far
+    pub fee_paid: f64, // commission booked for `filled`
+    pub reservation: Option<Reservation>,
+}
+
+impl RestingOrder {
+    pub fn remaining(&self) -> f64 {
+        (self.quantity - self.filled).max(0.0)
+    }
+}
+
+/// Every resting limit order, grouped by symbol. Orders are keyed by the
+/// exchange's order id; simulated orders get ids from `next_simulated_id`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RestingOrders {
+    orders: HashMap<String, Vec<RestingOrder>>,
+    next_id: u64,
+}
+
+impl RestingOrders {
+    pub fn symbol(&self, symbol: &str) -> &[RestingOrder] {
+        self.orders.get(symbol).map(|o| o.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn insert(&mut self, order: RestingOrder) {
+        self.orders
+            .entry(order.symbol.clone())
+            .or_default()
+            .push(order);
+    }
+
+    pub fn get_mut(&mut self, symbol: &str, id: u64) -> Option<&mut RestingOrder> {
+        self.orders.get_mut(symbol)?.iter_mut().find(|o| o.id == id)
+    }
+
+    pub fn remove(&mut self, symbol: &str, id: u64) -> Option<RestingOrder> {
+        let orders = self.orders.get_mut(symbol)?;
+        let index = orders.iter().position(|o| o.id == id)?;
+        Some(orders.remove(index))
+    }
+
+    /// Unfilled base units resting on `side` for `symbol`
+    pub fn remaining(&self, symbol: &str, side: &OrderSide) -> f64 {
+        self.symbol(symbol)
+            .iter()
+            .filter(|o| o.side == *side)
+            .map(|o| o.remaining())
+            .sum()
+    }
+
+    /// Unfilled notional resting on `side` for `symbol`
+    pub fn notional(&self, symbol: &str, side: &OrderSide) -> f64 {
+        self.symbol(symbol)
+            .iter()
+            .filter(|o| o.side == *side)
+            .map(|o| o.remaining() * o.price)
+            .sum()
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.values().map(|o| o.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn next_simulated_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Orders a candle trading between `low` and `high` would have filled:
+    /// buys at or above the low, sells at or below the high.
+    pub fn crossed(&self, symbol: &str, low: f64, high: f64) -> Vec<u64> {
+        self.symbol(symbol)
+            .iter()
+            .filter(|o| match o.side {
+                OrderSide::Buy => low <= o.price,
+                OrderSide::Sell => high >= o.price,
+            })
+            .map(|o| o.id)
+            .collect()
+    }
+}
+
+/// `price` rounded to `digits` significant figures, e.g. for limit prices on
+/// pairs whose tick size is not known
+pub fn round_significant(price: f64, digits: i32) -> f64 {
+    if price <= 0.0 || !price.is_finite() {
+        return 0.0;
+    }
+    let magnitude = price.log10().floor() as i32 + 1;
+    let factor = 10f64.powi(digits - magnitude);
+    (price * factor).round() / factor
+}
+
 pub struct OrderEngine {
     client: RoostooClient,
 }
 
 impl OrderEngine {
     pub fn build(api_key: String, api_secret: String) -> Self {
-        return Self {
+        Self {
             client: RoostooClient::new(api_key, api_secret),
-        };
+        }
     }
-    pub async fn run(&mut self, mut rx: mpsc::Receiver<OrderWithResponse>) {
-        while let Some(order) = rx.recv().await {
-            let factor = 10f64.powi(order.precision as i32);
-            let rounded = (order.order.quantity * factor).round() / factor;
-            // println!(
-            //     "[INFO][ORDERENGINE] executioner sent {} {}",
-            //     order.order.pair.clone(),
-            //     rounded,
-            // );
-
-            let _result = self
-                .client
-                .place_order(
-                    &order.order.pair,
-                    order.order.side,
-                    order.order.order_type.clone(),
-                    rounded,
-                    order.order.price,
-                )
-                .await;
-
-            match _result {
-                Ok(result) => {
-                    if result.success {
-                        if let Some(order_detail) = result.order_detail {
-                            // println!("Order filled: {}", order_detail.order_id);
+    pub async fn run(&mut self, mut rx: mpsc::Receiver<EngineRequest>) {
+        while let Some(request) = rx.recv().await {
+            match request {
+                EngineRequest::Place(order) => self.place(order).await,
+                EngineRequest::Query {
+                    order_id,
+                    pair,
+                    pending_only,
+                    response,
+                } => {
+                    let result = self
+                        .client
+                        .query_order(order_id, pair.as_deref(), Some(pending_only))
+                        .await;
+                    let _ = response.send(match result {
+                        Ok(query) => Ok(query.order_matched),
+                        // the exchange reports an empty result as a failure
+                        Err(e) if e.to_string().to_lowercase().contains("no order") => {
+                            Ok(Vec::new())
+                        }
+                        Err(e) => {
+                            println!("[ERROR][ORDERENGINE] Failed to query orders: {}", e);
+                            Err(e.to_string())
+                        }
+                    });
+                }
+                EngineRequest::Cancel { order_id, response } => {
+                    let result = self.client.cancel_order(Some(order_id), None).await;
+                    let _ = response.send(match result {
+                        Ok(_) => {
+                            println!("[SUCCESS][ORDERENGINE] Cancelled order {}", order_id);
+                            Ok(())
+                        }
+                        Err(e) => {
                             println!(
-                                "[SUCCESS][ORDERENGINE] {} {} {} @{} @fee {}",
-                                order_detail.pair.clone(),
-                                order_detail.side.to_uppercase(),
-                                order_detail.filled_quantity,
-                                order_detail.filled_aver_price,
-                                order_detail.commission_charge_value,
+                                "[ERROR][ORDERENGINE] Failed to cancel order {}: {}",
+                                order_id, e
                             );
-                            let _ = order.response.send(order_detail);
-                        } else {
-                            println!("Order succeeded but no details returned");
+                            Err(e.to_string())
                         }
+                    });
+                }
+            }
+        }
+    }
+
+    async fn place(&mut self, order: OrderWithResponse) {
+        let factor = 10f64.powi(order.precision as i32);
+        let rounded = (order.order.quantity * factor).round() / factor;
+        // println!(
+        //     "[INFO][ORDERENGINE] executioner sent {} {}",
+        //     order.order.pair.clone(),
+        //     rounded,
+        // );
+
+        let _result = self
+            .client
+            .place_order(
+                &order.order.pair,
+                order.order.side,
+                order.order.order_type.clone(),
+                rounded,
+                order.order.price,
+            )
+            .await;
+
+        match _result {
+            Ok(result) => {
+                if result.success {
+                    if let Some(order_detail) = result.order_detail {
+                        // println!("Order filled: {}", order_detail.order_id);
+                        println!(
+                            "[SUCCESS][ORDERENGINE] {} {} {} {} @{} @fee {}",
+                            order_detail.status,
+                            order_detail.pair.clone(),
+                            order_detail.side.to_uppercase(),
+                            order_detail.filled_quantity,
+                            order_detail.filled_aver_price,
+                            order_detail.commission_charge_value,
+                        );
+                        let _ = order.response.send(Ok(order_detail));
                     } else {
-                        println!("[ERROR][ORDERENGINE] Order failed: {}", result.err_msg);
+                        println!("Order succeeded but no details returned");
+                        let _ = order
+                            .response
+                            .send(Err("order succeeded but no details returned".to_string()));
                     }
+                } else {
+                    println!("[ERROR][ORDERENGINE] Order failed: {}", result.err_msg);
+                    let _ = order.response.send(Err(result.err_msg));
                 }
-                Err(e) => println!("[ERROR][ORDERENGINE] Failed to place order: {}", e),
+            }
+            Err(e) => {
+                println!("[ERROR][ORDERENGINE] Failed to place order: {}", e);
+                let _ = order.response.send(Err(e.to_string()));
             }
         }
     }
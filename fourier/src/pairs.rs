@@ -0,0 +1,728 @@
+use crate::config::{ConfigError, ConfigFile, Validate, check_ordered, check_range};
+use crate::portfolio::Allocation;
+use crate::strategy::{ExecContext, Order, SharedState, Strategy};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// How the hedge ratio of a selected pair is tracked between retests.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HedgeMethod {
+    /// OLS over the last `lookback` samples, refit on every sample.
+    Rolling,
+    /// Kalman filter on intercept and slope, seeded from the cointegration fit.
+    Kalman,
+}
+
+/// Tuning for `PairsTrading`, loaded from the `pairs` section of the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PairsParams {
+    pub sample_secs: u64, // prices are sampled on this clock
+    pub lookback: usize,  // samples used by the cointegration test
+    pub retest_secs: u64,
+    pub adf_critical: f64, // Engle-Granger critical value, -3.34 is 5% for two series
+    pub hedge: HedgeMethod,
+    pub zscore_window: usize, // samples of spread for the rolling z-score
+    pub kalman_delta: f64,    // state drift of the Kalman hedge
+    pub kalman_noise: f64,    // observation variance of the Kalman hedge
+    pub entry_z: f64,
+    pub exit_z: f64,
+    pub max_pairs: usize,
+    pub leg_weight: f64, // fraction of equity in the cheap leg of each pair
+    pub min_order_notional: f64,
+}
+
+impl Default for PairsParams {
+    fn default() -> Self {
+        Self {
+            sample_secs: 60,
+            lookback: 240,
+            retest_secs: 3_600,
+            adf_critical: -3.34,
+            hedge: HedgeMethod::Rolling,
+            zscore_window: 60,
+            kalman_delta: 1e-4,
+            kalman_noise: 1e-4,
+            entry_z: 2.0,
+            exit_z: 0.5,
+            max_pairs: 3,
+            leg_weight: 0.25,
+            min_order_notional: 10.0,
+        }
+    }
+}
+
+impl Validate for PairsParams {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        check_range(scope, "sample_secs", self.sample_secs as f64, 1.0, f64::MAX)?;
+        check_range(scope, "lookback", self.lookback as f64, 20.0, 100_000.0)?;
+        check_range(scope, "retest_secs", self.retest_secs as f64, 1.0, f64::MAX)?;
+        check_range(scope, "adf_critical", self.adf_critical, -10.0, 0.0)?;
+        check_range(
+            scope,
+            "zscore_window",
+            self.zscore_window as f64,
+            2.0,
+            self.lookback as f64,
+        )?;
+        check_range(scope, "kalman_delta", self.kalman_delta, 0.0, 0.5)?;
+        check_range(scope, "kalman_noise", self.kalman_noise, 0.0, 1.0)?;
+        check_range(scope, "exit_z", self.exit_z, 0.0, 10.0)?;
+        check_ordered(scope, "exit_z", self.exit_z, self.entry_z, "entry_z")?;
+        check_range(scope, "max_pairs", self.max_pairs as f64, 1.0, 64.0)?;
+        check_range(scope, "leg_weight", self.leg_weight, 0.0, 1.0)?;
+        check_range(
+            scope,
+            "min_order_notional",
+            self.min_order_notional,
+            0.0,
+            f64::MAX,
+        )
+    }
+}
+
+/// Engle-Granger fit of `log y = alpha + beta * log x + residual`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Cointegration {
+    pub alpha: f64,
+    pub beta: f64,
+    pub adf: f64, // Dickey-Fuller t-statistic of the residuals
+}
+
+/// Intercept and slope of the least squares fit of `y` on `x`
+pub fn ols(x: &[f64], y: &[f64]) -> Option<(f64, f64)> {
+    if x.len() != y.len() || x.len() < 2 {
+        return None;
+    }
+    let n = x.len() as f64;
+    let mean_x = x.iter().sum::<f64>() / n;
+    let mean_y = y.iter().sum::<f64>() / n;
+    let (mut cov, mut var) = (0.0, 0.0);
+    for (a, b) in x.iter().zip(y) {
+        cov += (a - mean_x) * (b - mean_y);
+        var += (a - mean_x).powi(2);
+    }
+    if var <= 0.0 {
+        return None;
+    }
+    let beta = cov / var;
+    Some((mean_y - beta * mean_x, beta))
+}
+
+/// Dickey-Fuller t-statistic of `series` (no constant, no lags): strongly
+/// negative values reject a unit root, i.e. the series mean reverts.
+pub fn adf_statistic(series: &[f64]) -> Option<f64> {
+    if series.len() < 3 {
+        return None;
+    }
+    let (mut lagged_sq, mut cross) = (0.0, 0.0);
+    for w in series.windows(2) {
+        lagged_sq += w[0] * w[0];
+        cross += w[0] * (w[1] - w[0]);
+    }
+    if lagged_sq <= 0.0 {
+        return None;
+    }
+    let gamma = cross / lagged_sq;
+    let residual_sq: f64 = series
+        .windows(2)
+        .map(|w| (w[1] - w[0] - gamma * w[0]).powi(2))
+        .sum();
+    let variance = residual_sq / (series.len() - 2) as f64;
+    let se = (variance / lagged_sq).sqrt();
+    if se <= 0.0 {
+        return None;
+    }
+    Some(gamma / se)
+}
+
+/// Two-step Engle-Granger test on log prices `y` and `x`
+pub fn engle_granger(y: &[f64], x: &[f64]) -> Option<Cointegration> {
+    let (alpha, beta) = ols(x, y)?;
+    let residuals: Vec<f64> = x.iter().zip(y).map(|(x, y)| y - alpha - beta * x).collect();
+    Some(Cointegration {
+        alpha,
+        beta,
+        adf: adf_statistic(&residuals)?,
+    })
+}
+
+/// Kalman filter on `y = alpha + beta * x` with a random-walk state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KalmanHedge {
+    state: [f64; 2], // alpha, beta
+    covariance: [[f64; 2]; 2],
+    drift: f64,
+    noise: f64,
+}
+
+impl KalmanHedge {
+    pub fn new(alpha: f64, beta: f64, delta: f64, noise: f64) -> Self {
+        Self {
+            state: [alpha, beta],
+            covariance: [[0.0; 2]; 2],
+            drift: delta / (1.0 - delta),
+            noise,
+        }
+    }
+
+    /// Current intercept and hedge ratio
+    pub fn hedge(&self) -> (f64, f64) {
+        (self.state[0], self.state[1])
+    }
+
+    /// Feed one observation; returns the forecast error and its variance
+    pub fn update(&mut self, x: f64, y: f64) -> (f64, f64) {
+        let obs = [1.0, x];
+        let mut r = self.covariance;
+        r[0][0] += self.drift;
+        r[1][1] += self.drift;
+
+        let rx = [
+            r[0][0] * obs[0] + r[0][1] * obs[1],
+            r[1][0] * obs[0] + r[1][1] * obs[1],
+        ];
+        let variance = obs[0] * rx[0] + obs[1] * rx[1] + self.noise;
+        let error = y - (self.state[0] * obs[0] + self.state[1] * obs[1]);
+        let gain = [rx[0] / variance, rx[1] / variance];
+
+        self.state[0] += gain[0] * error;
+        self.state[1] += gain[1] * error;
+        for i in 0..2 {
+            for j in 0..2 {
+                self.covariance[i][j] = r[i][j] - gain[i] * rx[j];
+            }
+        }
+        (error, variance)
+    }
+}
+
+// a selected pair; `y` is regressed on `x`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Pair {
+    y: String,
+    x: String,
+    fit: Cointegration,
+    kalman: Option<KalmanHedge>,
+    zscore: f64,
+    long: Option<String>, // leg currently held
+}
+
+/// Long-only statistical arbitrage. Every `retest_secs` the symbols are
+/// paired up by Engle-Granger on sampled log prices; between retests the
+/// spread of each pair is tracked, and when it diverges past `entry_z`
+/// capital is rotated into the cheap leg until the spread is back within
+/// `exit_z` of its mean.
+#[derive(Debug, Default)]
+pub struct PairsTrading {
+    pub params: PairsParams,
+    history: VecDeque<HashMap<String, f64>>, // log prices per sample, oldest first
+    pairs: Vec<Pair>,
+    next_retest: u64,
+}
+
+impl PairsTrading {
+    pub const SECTION: &'static str = "pairs";
+
+    pub fn new(params: PairsParams) -> Self {
+        Self {
+            params,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_config(config: &ConfigFile) -> Result<Self, ConfigError> {
+        Ok(Self::new(
+            config.settings(Self::SECTION)?.unwrap_or_default(),
+        ))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::from_config(&ConfigFile::load(path)?)
+    }
+
+    /// Selected pairs as (y, x, hedge ratio, z-score)
+    pub fn pairs(&self) -> Vec<(String, String, f64, f64)> {
+        self.pairs
+            .iter()
+            .map(|pair| {
+                let beta = match &pair.kalman {
+                    Some(kalman) => kalman.hedge().1,
+                    None => pair.fit.beta,
+                };
+                (pair.y.clone(), pair.x.clone(), beta, pair.zscore)
+            })
+            .collect()
+    }
+
+    // aligned log prices of `y` and `x` over the last `window` samples
+    fn series(&self, y: &str, x: &str, window: usize) -> (Vec<f64>, Vec<f64>) {
+        let skip = self.history.len().saturating_sub(window);
+        self.history
+            .iter()
+            .skip(skip)
+            .filter_map(|row| Some((*row.get(y)?, *row.get(x)?)))
+            .unzip()
+    }
+
+    // appends the latest log prices to the history; false when no symbol
+    // has a price yet
+    fn sample(&mut self, contexts: &HashMap<String, ExecContext>) -> bool {
+        let row: HashMap<String, f64> = contexts
+            .values()
+            .filter(|ctx| ctx.last_close > 0.0)
+            .map(|ctx| (ctx.symbol.clone(), ctx.last_close.ln()))
+            .collect();
+        if row.is_empty() {
+            return false;
+        }
+        self.history.push_back(row);
+        if self.history.len() > self.params.lookback {
+            self.history.pop_front();
+        }
+        true
+    }
+
+    /// Pair symbols greedily by strongest cointegration; each symbol joins at
+    /// most one pair and only positively related pairs are kept
+    fn retest(&mut self, symbols: &[String]) {
+        let lookback = self.params.lookback;
+        let mut candidates = Vec::new();
+        for (i, a) in symbols.iter().enumerate() {
+            for b in &symbols[i + 1..] {
+                let (y, x) = self.series(a, b, lookback);
+                if y.len() < lookback {
+                    continue;
+                }
+                if let Some(fit) = engle_granger(&y, &x)
+                    && fit.beta > 0.0
+                    && fit.adf < self.params.adf_critical
+                {
+                    candidates.push((a.clone(), b.clone(), fit));
+                }
+            }
+        }
+        candidates.sort_by(|p, q| p.2.adf.total_cmp(&q.2.adf));
+
+        let mut previous = std::mem::take(&mut self.pairs);
+        for (y, x, fit) in candidates {
+            if self.pairs.len() >= self.params.max_pairs
+                || self
+                    .pairs
+                    .iter()
+                    .any(|p| [&p.y, &p.x].iter().any(|s| **s == y || **s == x))
+            {
+                continue;
+            }
+            // pairs that survive the retest keep their filter and held leg
+            let kept = previous
+                .iter()
+                .position(|p| p.y == y && p.x == x)
+                .map(|i| previous.swap_remove(i));
+            let pair = match kept {
+                Some(mut pair) => {
+                    pair.fit = fit;
+                    pair
+                }
+                None => {
+                    println!(
+                        "[INFO][PAIRS] {}/{} cointegrated: beta {:.3}, adf {:.2}",
+                        y, x, fit.beta, fit.adf
+                    );
+                    Pair {
+                        kalman: (self.params.hedge == HedgeMethod::Kalman).then(|| {
+                            KalmanHedge::new(
+                                fit.alpha,
+                                fit.beta,
+                                self.params.kalman_delta,
+                                self.params.kalman_noise,
+                            )
+                        }),
+                        y,
+                        x,
+                        fit,
+                        zscore: 0.0,
+                        long: None,
+                    }
+                }
+            };
+            self.pairs.push(pair);
+        }
+        for dropped in previous {
+            println!(
+                "[INFO][PAIRS] {}/{} no longer cointegrated",
+                dropped.y, dropped.x
+            );
+        }
+    }
+
+    // z-score of the latest spread of `pair`
+    fn zscore(&self, pair: &mut Pair) -> Option<f64> {
+        let (y, x) = self.series(&pair.y, &pair.x, self.params.lookback);
+        let (&last_y, &last_x) = (y.last()?, x.last()?);
+        if let Some(kalman) = &mut pair.kalman {
+            let (error, variance) = kalman.update(last_x, last_y);
+            return (variance > 0.0).then(|| error / variance.sqrt());
+        }
+
+        let (alpha, beta) = ols(&x, &y)?;
+        let window = self.params.zscore_window.min(y.len());
+        let spreads: Vec<f64> = x[x.len() - window..]
+            .iter()
+            .zip(&y[y.len() - window..])
+            .map(|(x, y)| y - alpha - beta * x)
+            .collect();
+        let n = spreads.len() as f64;
+        let mean = spreads.iter().sum::<f64>() / n;
+        let std = (spreads.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n).sqrt();
+        pair.fit.alpha = alpha;
+        pair.fit.beta = beta;
+        (std > 0.0).then(|| (spreads[window - 1] - mean) / std)
+    }
+
+    /// Weights implied by the current spreads; true when any held leg changed
+    fn update_legs(&mut self) -> bool {
+        let mut pairs = std::mem::take(&mut self.pairs);
+        let mut changed = false;
+        for pair in &mut pairs {
+            let Some(z) = self.zscore(pair) else {
+                continue;
+            };
+            pair.zscore = z;
+            let long = if z > self.params.entry_z {
+                Some(pair.x.clone()) // y rich, x cheap
+            } else if z < -self.params.entry_z {
+                Some(pair.y.clone())
+            } else if z.abs() < self.params.exit_z {
+                None
+            } else {
+                pair.long.clone()
+            };
+            if long != pair.long {
+                println!(
+                    "[INFO][PAIRS] {}/{} z {:.2}: holding {:?}",
+                    pair.y, pair.x, z, long
+                );
+                pair.long = long;
+                changed = true;
+            }
+        }
+        self.pairs = pairs;
+        changed
+    }
+
+    fn allocation(&self) -> Allocation {
+        let weights = self
+            .pairs
+            .iter()
+            .filter_map(|pair| pair.long.clone())
+            .map(|symbol| (symbol, self.params.leg_weight))
+            .collect();
+        Allocation {
+            weights,
+            min_order_notional: self.params.min_order_notional,
+            // a rotation sells one leg and buys the other
+            turnover_budget: 2.0,
+        }
+    }
+}
+
+#[async_trait]
+impl Strategy for PairsTrading {
+    async fn should_long(
+        &self,
+        _ctx: &mut ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        false
+    }
+
+    async fn go_long(
+        &self,
+        _ctx: &ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> Option<Order> {
+        None
+    }
+
+    async fn update_position(
+        &self,
+        _ctx: &mut ExecContext,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        false
+    }
+
+    fn allocation_secs(&self) -> u64 {
+        self.params.sample_secs
+    }
+
+    async fn target_weights(
+        &mut self,
+        contexts: &HashMap<String, ExecContext>,
+        now: u64,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> Option<Option<Allocation>> {
+        if !self.sample(contexts) {
+            return None;
+        }
+        // sampled, so the next sample is due in `sample_secs` either way
+        if self.history.len() < self.params.lookback {
+            return Some(None);
+        }
+
+        let mut changed = false;
+        if now >= self.next_retest {
+            let held: Vec<String> = self.pairs.iter().filter_map(|p| p.long.clone()).collect();
+            let mut symbols: Vec<String> = contexts.keys().cloned().collect();
+            symbols.sort();
+            self.retest(&symbols);
+            self.next_retest = now + self.params.retest_secs;
+            // legs of dropped pairs have to be sold
+            changed = held
+                .iter()
+                .any(|s| !self.pairs.iter().any(|p| p.long.as_ref() == Some(s)));
+        }
+        changed |= self.update_legs();
+
+        // positions stay as they are between rotations
+        Some(changed.then(|| self.allocation()))
+    }
+
+    fn warm_up(&mut self, contexts: &HashMap<String, ExecContext>, _now: u64) {
+        self.sample(contexts);
+    }
+
+    fn reload(&mut self, config: &ConfigFile) -> Result<(), ConfigError> {
+        self.params = config.settings(Self::SECTION)?.unwrap_or_default();
+        Ok(())
+    }
+
+    // sampled prices, the selected pairs with their filters, and the retest clock
+    fn snapshot(&self) -> Option<serde_json::Value> {
+        serde_json::to_value((&self.history, &self.pairs, self.next_retest)).ok()
+    }
+
+    fn restore(&mut self, state: serde_json::Value) -> Result<(), serde_json::Error> {
+        (self.history, self.pairs, self.next_retest) = serde_json::from_value(state)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::BackTester;
+    use crate::fourier::Candle;
+
+    // deterministic noise in [-0.5, 0.5)
+    fn noise(seed: &mut u64) -> f64 {
+        *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (*seed >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+    }
+
+    fn context(symbol: &str, price: f64) -> ExecContext {
+        ExecContext::for_test(
+            symbol,
+            vec![Candle {
+                close: price,
+                ..Default::default()
+            }],
+        )
+    }
+
+    #[test]
+    fn engle_granger_separates_cointegrated_from_independent_walks() {
+        let mut seed = 7;
+        let (mut walk, mut other) = (0.0, 0.0);
+        let (mut x, mut y, mut z) = (Vec::new(), Vec::new(), Vec::new());
+        for _ in 0..300 {
+            walk += 0.01 * noise(&mut seed);
+            other += 0.01 * noise(&mut seed);
+            x.push(4.0 + walk);
+            y.push(1.0 + 1.5 * (4.0 + walk) + 0.005 * noise(&mut seed));
+            z.push(2.0 + other);
+        }
+        let fit = engle_granger(&y, &x).unwrap();
+        assert!((fit.beta - 1.5).abs() < 0.05, "beta {}", fit.beta);
+        assert!(fit.adf < -3.34, "adf {}", fit.adf);
+        assert!(engle_granger(&z, &x).unwrap().adf > -3.34);
+    }
+
+    #[tokio::test]
+    async fn rotates_into_the_cheap_leg_and_out_on_reversion() {
+        let mut pairs = PairsTrading::new(PairsParams {
+            lookback: 100,
+            zscore_window: 50,
+            ..Default::default()
+        });
+        let shared = Arc::new(Mutex::new(SharedState::new(1_000.0)));
+        let mut seed = 11;
+        let mut walk = 0.0;
+        let mut sample = async |pairs: &mut PairsTrading, t: u64, gap: f64| {
+            walk += 0.01 * noise(&mut seed);
+            let a = (4.0 + walk + 0.002 * noise(&mut seed) + gap).exp();
+            let b = (4.0 + walk).exp();
+            let contexts = HashMap::from([
+                ("AAA".to_string(), context("AAA", a)),
+                ("BBB".to_string(), context("BBB", b)),
+            ]);
+            pairs
+                .target_weights(&contexts, t * 60, shared.clone())
+                .await
+        };
+
+        for t in 0..100 {
+            assert_eq!(sample(&mut pairs, t, 0.0).await, Some(None));
+        }
+        assert_eq!(pairs.pairs().len(), 1);
+
+        // AAA falls well below BBB: hold AAA only
+        let allocation = sample(&mut pairs, 100, -0.05).await.flatten().unwrap();
+        assert_eq!(allocation.weights.len(), 1);
+        assert_eq!(allocation.weights["AAA"], 0.25);
+
+        // spread closes again: sell everything
+        let allocation = sample(&mut pairs, 101, 0.0).await.flatten().unwrap();
+        assert!(allocation.weights.is_empty());
+    }
+
+    fn candle(secs: u64, close: f64) -> Candle {
+        Candle {
+            open_time: (secs - 60) * 1_000,
+            close_time: secs * 1_000,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1.0,
+            trade_count: 1,
+        }
+    }
+
+    // AAA and BBB share a random walk; AAA drops `gap` below it at minute
+    // `dip` and is back the minute after
+    fn cointegrated(minutes: u64, dip: u64, gap: f64) -> Vec<(String, Arc<Vec<Candle>>)> {
+        let mut seed = 11;
+        let mut walk = 0.0;
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        for t in 1..=minutes {
+            walk += 0.01 * noise(&mut seed);
+            let shift = if t == dip { gap } else { 0.0 };
+            let secs = 1_700_000_000 + t * 60;
+            a.push(candle(
+                secs,
+                (4.0 + walk + 0.002 * noise(&mut seed) + shift).exp(),
+            ));
+            b.push(candle(secs, (4.0 + walk).exp()));
+        }
+        vec![
+            ("AAA".to_string(), Arc::new(a)),
+            ("BBB".to_string(), Arc::new(b)),
+        ]
+    }
+
+    #[tokio::test]
+    async fn backtest_buys_the_cheap_leg_and_sells_it_on_reversion() {
+        // BBB's first candle comes after the first sample, so the pair is
+        // only complete for the second retest
+        let pairs = PairsTrading::new(PairsParams {
+            lookback: 100,
+            retest_secs: 300,
+            zscore_window: 50,
+            ..Default::default()
+        });
+        let report = BackTester::create(pairs)
+            .quiet()
+            .run_universe(cointegrated(115, 108, -0.05), 10_000.0)
+            .await;
+        assert_eq!(report.trade_count, 1);
+        let trade = &report.trades[0];
+        assert_eq!(trade.symbol, "AAA");
+        assert!(trade.pnl > 0.0, "pnl {}", trade.pnl);
+    }
+
+    #[tokio::test]
+    async fn history_from_warm_up_fills_the_samples() {
+        let pairs = PairsTrading::new(PairsParams {
+            lookback: 100,
+            retest_secs: 300,
+            zscore_window: 50,
+            ..Default::default()
+        });
+        // the first 100 minutes are history; live, there is only enough left
+        // for 15 samples
+        let series = cointegrated(115, 108, -0.05);
+        let mut tester = BackTester::create(pairs).quiet();
+        let mut live = Vec::new();
+        for (symbol, candles) in series {
+            let (history, rest) = candles.split_at(100);
+            tester = tester.with_history(&symbol, history.to_vec());
+            live.push((symbol, Arc::new(rest.to_vec())));
+        }
+        let report = tester.run_universe(live, 10_000.0).await;
+        assert_eq!(report.trade_count, 1);
+        assert_eq!(report.trades[0].symbol, "AAA");
+    }
+
+    // pairs with every `target_weights` call time noted
+    struct Clocked {
+        pairs: PairsTrading,
+        calls: Arc<std::sync::Mutex<Vec<u64>>>,
+    }
+
+    #[async_trait]
+    impl Strategy for Clocked {
+        async fn should_long(&self, _: &mut ExecContext, _: Arc<Mutex<SharedState>>) -> bool {
+            false
+        }
+
+        async fn go_long(&self, _: &ExecContext, _: Arc<Mutex<SharedState>>) -> Option<Order> {
+            None
+        }
+
+        async fn update_position(&self, _: &mut ExecContext, _: Arc<Mutex<SharedState>>) -> bool {
+            false
+        }
+
+        fn allocation_secs(&self) -> u64 {
+            self.pairs.allocation_secs()
+        }
+
+        async fn target_weights(
+            &mut self,
+            contexts: &HashMap<String, ExecContext>,
+            now: u64,
+            shared_state: Arc<Mutex<SharedState>>,
+        ) -> Option<Option<Allocation>> {
+            self.calls.lock().unwrap().push(now);
+            self.pairs.target_weights(contexts, now, shared_state).await
+        }
+    }
+
+    #[tokio::test]
+    async fn samples_on_the_sample_clock_while_history_fills() {
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let clocked = Clocked {
+            pairs: PairsTrading::new(PairsParams {
+                sample_secs: 300,
+                lookback: 20,
+                zscore_window: 10,
+                ..Default::default()
+            }),
+            calls: calls.clone(),
+        };
+        BackTester::create(clocked)
+            .quiet()
+            .run_universe(cointegrated(150, 0, 0.0), 10_000.0)
+            .await;
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 30);
+        assert!(calls.windows(2).all(|w| w[1] - w[0] == 300), "{:?}", calls);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::bollinger::Bollinger;
+use crate::breakout::Breakout;
+use crate::config::{ConfigError, ConfigFile};
+use crate::ensemble::Ensemble;
+use crate::fourier::Fourier;
+use crate::grid::Grid;
+use crate::market_maker::MarketMaker;
+use crate::momentum::Momentum;
+use crate::pairs::PairsTrading;
+use crate::scripted::ScriptedStrategy;
+use crate::strategy::Strategy;
+use std::collections::HashMap;
+
+/// Section of the config file mapping symbols to the strategy they trade
+pub const SYMBOL_STRATEGIES: &str = "symbol_strategies";
+
+/// The strategy whose section is `name`, built from that section of `config`
+pub fn build(name: &str, config: &ConfigFile) -> Result<Box<dyn Strategy>, ConfigError> {
+    Ok(match name {
+        Fourier::SECTION => Box::new(Fourier::from_config(config)?),
+        Bollinger::SECTION => Box::new(Bollinger::from_config(config)?),
+        Breakout::SECTION => Box::new(Breakout::from_config(config)?),
+        Ensemble::SECTION => Box::new(Ensemble::from_config(config)?),
+        Grid::SECTION => Box::new(Grid::from_config(config)?),
+        MarketMaker::SECTION => Box::new(MarketMaker::from_config(config)?),
+        Momentum::SECTION => Box::new(Momentum::from_config(config)?),
+        PairsTrading::SECTION => Box::new(PairsTrading::from_config(config)?),
+        ScriptedStrategy::SECTION => Box::new(ScriptedStrategy::from_config(config)?),
+        other => return Err(ConfigError::UnknownStrategy(other.to_string())),
+    })
+}
+
+/// Like `build`, for a symbol trading its own strategy. Strategies that only
+/// allocate across symbols are refused: the executioner asks just its own
+/// strategy for target weights, so one assigned to a symbol would never trade.
+pub fn build_for_symbol(name: &str, config: &ConfigFile) -> Result<Box<dyn Strategy>, ConfigError> {
+    let strategy = build(name, config)?;
+    if strategy.allocation_secs() > 0 {
+        return Err(ConfigError::UnknownStrategy(format!(
+            "{} under {}",
+            name, SYMBOL_STRATEGIES
+        )));
+    }
+    Ok(strategy)
+}
+
+/// Strategies for the symbols listed under `symbol_strategies`, e.g.
+/// ```yaml
+/// symbol_strategies:
+///   BONK: bollinger
+///   WIF: bollinger
+/// ```
+/// Symbols left out trade the top-level `strategy`.
+pub fn symbol_strategies(
+    config: &ConfigFile,
+) -> Result<HashMap<String, Box<dyn Strategy>>, ConfigError> {
+    symbol_strategy_names(config)?
+        .into_iter()
+        .map(|(symbol, name)| Ok((symbol, build_for_symbol(&name, config)?)))
+        .collect()
+}
+
+/// The `symbol_strategies` section as symbol -> strategy name
+pub fn symbol_strategy_names(config: &ConfigFile) -> Result<HashMap<String, String>, ConfigError> {
+    match config.raw(SYMBOL_STRATEGIES) {
+        Some(section) => Ok(serde_yaml::from_value(section.clone())?),
+        None => Ok(HashMap::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::{BackTester, BacktestReport};
+    use crate::fourier::Candle;
+    use crate::portfolio::Allocation;
+    use crate::strategy::{Churn, ExecContext, Order, SharedState};
+    use async_trait::async_trait;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    // puts half of equity into BTC every minute and notes the symbols it
+    // was asked to allocate
+    struct HalfInBtc {
+        seen: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Strategy for HalfInBtc {
+        async fn should_long(&self, _: &mut ExecContext, _: Arc<Mutex<SharedState>>) -> bool {
+            false
+        }
+
+        async fn go_long(&self, _: &ExecContext, _: Arc<Mutex<SharedState>>) -> Option<Order> {
+            None
+        }
+
+        async fn update_position(&self, _: &mut ExecContext, _: Arc<Mutex<SharedState>>) -> bool {
+            false
+        }
+
+        fn allocation_secs(&self) -> u64 {
+            60
+        }
+
+        async fn target_weights(
+            &mut self,
+            contexts: &HashMap<String, ExecContext>,
+            _: u64,
+            _: Arc<Mutex<SharedState>>,
+        ) -> Option<Option<Allocation>> {
+            self.seen.lock().unwrap().extend(contexts.keys().cloned());
+            Some(Some(Allocation {
+                weights: HashMap::from([("BTC".to_string(), 0.5)]),
+                min_order_notional: 10.0,
+                turnover_budget: 2.0,
+            }))
+        }
+    }
+
+    fn candles() -> Arc<Vec<Candle>> {
+        let candles = (1..=6)
+            .map(|i| Candle {
+                open_time: (i - 1) * 60_000,
+                close_time: i * 60_000,
+                open: 100.0,
+                high: 100.0,
+                low: 100.0,
+                close: 100.0,
+                volume: 1.0,
+                trade_count: 1,
+            })
+            .collect();
+        Arc::new(candles)
+    }
+
+    #[tokio::test]
+    async fn assigned_symbols_trade_their_own_strategy() {
+        let candles = candles();
+        let report = BackTester::create(Churn::new(false))
+            .quiet()
+            .with_symbol_strategy("ETH", Box::new(Churn::new(true)))
+            .run_universe(
+                vec![
+                    ("BTC".to_string(), candles.clone()),
+                    ("ETH".to_string(), candles),
+                ],
+                10_000.0,
+            )
+            .await;
+        assert!(report.trade_count > 0);
+        assert!(report.trades.iter().all(|t| t.symbol == "ETH"));
+
+        let config = ConfigFile::parse("symbol_strategies:\n  BTC: martingale\n").unwrap();
+        assert!(matches!(
+            symbol_strategies(&config),
+            Err(ConfigError::UnknownStrategy(name)) if name == "martingale"
+        ));
+    }
+
+    #[tokio::test]
+    async fn allocation_leaves_symbols_with_their_own_strategy_alone() {
+        let universe = || {
+            vec![
+                ("BTC".to_string(), candles()),
+                ("ETH".to_string(), candles()),
+            ]
+        };
+        let alone = BackTester::create(Churn::new(false))
+            .quiet()
+            .with_symbol_strategy("ETH", Box::new(Churn::new(true)))
+            .run_universe(universe(), 10_000.0)
+            .await;
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let beside = BackTester::create(HalfInBtc { seen: seen.clone() })
+            .quiet()
+            .with_symbol_strategy("ETH", Box::new(Churn::new(true)))
+            .run_universe(universe(), 10_000.0)
+            .await;
+
+        let seen = seen.lock().unwrap();
+        assert!(!seen.is_empty());
+        assert!(seen.iter().all(|symbol| symbol == "BTC"));
+        // ETH trades exactly as it does without an allocator beside it
+        let times = |report: &BacktestReport| -> Vec<(u64, u64)> {
+            report
+                .trades
+                .iter()
+                .map(|t| (t.entry_time, t.exit_time))
+                .collect()
+        };
+        assert!(alone.trade_count > 0);
+        assert!(beside.trades.iter().all(|t| t.symbol == "ETH"));
+        assert_eq!(times(&beside), times(&alone));
+
+        for name in [Momentum::SECTION, PairsTrading::SECTION] {
+            let config =
+                ConfigFile::parse(&format!("symbol_strategies:\n  BTC: {}\n", name)).unwrap();
+            assert!(matches!(
+                symbol_strategies(&config),
+                Err(ConfigError::UnknownStrategy(reason)) if reason.starts_with(name)
+            ));
+        }
+    }
+}
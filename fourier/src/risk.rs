@@ -0,0 +1,832 @@
+use crate::config::{ConfigError, ConfigFile, Validate, check_range};
+use crate::correlation::CorrelationEstimator;
+use crate::roostoo::OrderSide;
+use crate::strategy::Order;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Portfolio limits applied to every order between `Strategy::go_long` and
+/// the order engine. Read from the `risk` section of the config file:
+/// ```yaml
+/// risk:
+///   max_symbol_notional: 10000.0
+///   symbol_notional:
+///     BONK: 2500.0
+///   max_gross_exposure: 40000.0
+///   max_open_positions: 6
+///   max_trade_fraction: 0.2
+///   daily_loss_limit_pct: 5.0
+///   halt_file: HALTED
+///   drawdown:
+///     steps: [{drawdown_pct: 5.0, scale: 0.5}]
+///     streak_steps: [{losses: 3, scale: 0.5}]
+///     recovery_pct: 2.0
+///   correlation:
+///     benchmark: BTC
+///     max_beta_exposure: 0.8
+///     max_correlated_exposure: 0.6
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskLimits {
+    pub max_symbol_notional: f64, // USD per symbol unless overridden below
+    pub symbol_notional: HashMap<String, f64>,
+    pub max_gross_exposure: f64, // USD summed over all open positions
+    pub max_open_positions: usize,
+    pub max_trade_fraction: f64,   // of equity, per order
+    pub daily_loss_limit_pct: f64, // of start-of-day equity, 0 disables
+    // written when the kill switch trips; delete it to resume trading
+    pub halt_file: Option<String>,
+    pub drawdown: DrawdownLimits,
+    pub correlation: CorrelationLimits,
+}
+
+impl Default for RiskLimits {
+    fn default() -> Self {
+        Self {
+            max_symbol_notional: 10_000.0,
+            symbol_notional: HashMap::new(),
+            max_gross_exposure: 40_000.0,
+            max_open_positions: 15,
+            max_trade_fraction: 0.25,
+            daily_loss_limit_pct: 5.0,
+            halt_file: None,
+            drawdown: DrawdownLimits::default(),
+            correlation: CorrelationLimits::default(),
+        }
+    }
+}
+
+/// Limits on how much of the book is one bet in disguise. Returns are sampled
+/// every `sample_secs` over the last `window` samples; until a symbol has
+/// `min_samples` its beta and correlations are taken as 1.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorrelationLimits {
+    pub benchmark: String,
+    pub sample_secs: u64,
+    pub window: usize,
+    pub min_samples: usize,
+    pub max_beta_exposure: f64, // sum of notional * beta, as a fraction of equity
+    // sqrt(w' C w) over equity with w the USD holdings and C their correlations
+    pub max_correlated_exposure: f64,
+}
+
+impl Default for CorrelationLimits {
+    fn default() -> Self {
+        Self {
+            benchmark: "BTC".to_string(),
+            sample_secs: 60,
+            window: 240,
+            min_samples: 30,
+            max_beta_exposure: 0.8,
+            max_correlated_exposure: 0.6,
+        }
+    }
+}
+
+impl CorrelationLimits {
+    pub fn estimator(&self) -> CorrelationEstimator {
+        CorrelationEstimator::new(self.sample_secs, self.window)
+    }
+}
+
+impl Validate for CorrelationLimits {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        check_range(scope, "sample_secs", self.sample_secs as f64, 1.0, 86_400.0)?;
+        check_range(scope, "window", self.window as f64, 2.0, 100_000.0)?;
+        check_range(
+            scope,
+            "min_samples",
+            self.min_samples as f64,
+            2.0,
+            self.window as f64,
+        )?;
+        check_range(
+            scope,
+            "max_beta_exposure",
+            self.max_beta_exposure,
+            0.0,
+            100.0,
+        )?;
+        check_range(
+            scope,
+            "max_correlated_exposure",
+            self.max_correlated_exposure,
+            0.0,
+            100.0,
+        )
+    }
+}
+
+/// Shrink new entries to `scale` once equity is `drawdown_pct` below its high.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DrawdownStep {
+    pub drawdown_pct: f64,
+    pub scale: f64,
+}
+
+/// Shrink new entries to `scale` after `losses` losing trades in a row.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct StreakStep {
+    pub losses: u64,
+    pub scale: f64,
+}
+
+/// Stepwise de-risking from the equity curve. Steps are listed from mildest
+/// to harshest; the smaller of the drawdown and streak scales applies.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DrawdownLimits {
+    pub steps: Vec<DrawdownStep>,
+    pub streak_steps: Vec<StreakStep>,
+    // a drawdown step is only left once drawdown is this far under its trigger
+    pub recovery_pct: f64,
+}
+
+impl Default for DrawdownLimits {
+    fn default() -> Self {
+        Self {
+            steps: vec![
+                DrawdownStep {
+                    drawdown_pct: 5.0,
+                    scale: 0.5,
+                },
+                DrawdownStep {
+                    drawdown_pct: 10.0,
+                    scale: 0.25,
+                },
+            ],
+            streak_steps: vec![
+                StreakStep {
+                    losses: 3,
+                    scale: 0.5,
+                },
+                StreakStep {
+                    losses: 5,
+                    scale: 0.25,
+                },
+            ],
+            recovery_pct: 2.0,
+        }
+    }
+}
+
+impl Validate for RiskLimits {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        check_range(
+            scope,
+            "max_symbol_notional",
+            self.max_symbol_notional,
+            0.0,
+            f64::MAX,
+        )?;
+        for (symbol, cap) in &self.symbol_notional {
+            check_range(
+                &format!("{}.{}", scope, symbol),
+                "symbol_notional",
+                *cap,
+                0.0,
+                f64::MAX,
+            )?;
+        }
+        check_range(
+            scope,
+            "max_gross_exposure",
+            self.max_gross_exposure,
+            0.0,
+            f64::MAX,
+        )?;
+        check_range(
+            scope,
+            "max_open_positions",
+            self.max_open_positions as f64,
+            1.0,
+            1024.0,
+        )?;
+        check_range(
+            scope,
+            "max_trade_fraction",
+            self.max_trade_fraction,
+            0.0,
+            1.0,
+        )?;
+        check_range(
+            scope,
+            "daily_loss_limit_pct",
+            self.daily_loss_limit_pct,
+            0.0,
+            100.0,
+        )?;
+        self.drawdown.validate(&format!("{}.drawdown", scope))?;
+        self.correlation.validate(&format!("{}.correlation", scope))
+    }
+}
+
+impl Validate for DrawdownLimits {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        let mut previous = 0.0;
+        for step in &self.steps {
+            check_range(scope, "drawdown_pct", step.drawdown_pct, previous, 100.0)?;
+            check_range(scope, "scale", step.scale, 0.0, 1.0)?;
+            previous = step.drawdown_pct;
+        }
+        let mut previous = 1.0;
+        for step in &self.streak_steps {
+            check_range(scope, "losses", step.losses as f64, previous, 1e6)?;
+            check_range(scope, "scale", step.scale, 0.0, 1.0)?;
+            previous = step.losses as f64;
+        }
+        check_range(scope, "recovery_pct", self.recovery_pct, 0.0, 100.0)
+    }
+}
+
+impl RiskLimits {
+    pub const SECTION: &'static str = "risk";
+
+    /// Limits from the config file; a missing section yields the defaults.
+    pub fn from_config(config: &ConfigFile) -> Result<Self, ConfigError> {
+        Ok(config.settings(Self::SECTION)?.unwrap_or_default())
+    }
+
+    pub fn symbol_cap(&self, symbol: &str) -> f64 {
+        self.symbol_notional
+            .get(symbol)
+            .copied()
+            .unwrap_or(self.max_symbol_notional)
+    }
+}
+
+/// What the portfolio looks like when an order is checked, marked at last closes.
+#[derive(Debug, Clone, Default)]
+pub struct Exposure {
+    pub equity: f64,
+    pub gross: f64,
+    pub open_positions: usize,
+    pub symbol_notional: f64,         // already held in the order's symbol
+    pub holdings: Vec<(String, f64)>, // USD notional of every open position
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum RiskRejection {
+    #[error("kill switch engaged: {0}")]
+    Halted(String),
+
+    #[error("{open} positions open, limit is {limit}")]
+    MaxOpenPositions { open: usize, limit: usize },
+
+    #[error("{limit} leaves no room (cap {cap:.2} USD)")]
+    NoHeadroom { limit: &'static str, cap: f64 },
+
+    #[error("no price to value the order at")]
+    NoPrice,
+
+    #[error("correlated exposure would reach {exposure:.2} of equity, limit is {limit:.2}")]
+    Concentrated { exposure: f64, limit: f64 },
+
+    #[error("size scaled to nothing at {scale:.2}x while de-risking")]
+    ScaledToZero { scale: f64 },
+}
+
+/// Tracks the equity high-water mark and the losing streak and turns them
+/// into a size multiplier. Drawdown steps apply as soon as they are crossed
+/// but are only stepped back one at a time, after drawdown has recovered
+/// `recovery_pct` past the step's trigger. Streak steps clear with the
+/// streak, i.e. on the next winning trade.
+#[derive(Debug)]
+pub struct DrawdownGovernor {
+    limits: DrawdownLimits,
+    high_water_mark: f64,
+    level: usize, // drawdown steps in force
+    scale: f64,
+}
+
+impl DrawdownGovernor {
+    pub fn new(limits: DrawdownLimits) -> Self {
+        Self {
+            limits,
+            high_water_mark: 0.0,
+            level: 0,
+            scale: 1.0,
+        }
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    // high-water mark, steps in force and scale, for snapshots
+    fn state(&self) -> (f64, usize, f64) {
+        (self.high_water_mark, self.level, self.scale)
+    }
+
+    fn restore(&mut self, (high_water_mark, level, scale): (f64, usize, f64)) {
+        self.high_water_mark = high_water_mark;
+        self.level = level.min(self.limits.steps.len());
+        self.scale = scale;
+    }
+
+    /// New steps, keeping the high-water mark and the steps in force
+    pub fn set_limits(&mut self, limits: DrawdownLimits) {
+        self.level = self.level.min(limits.steps.len());
+        self.limits = limits;
+    }
+
+    pub fn drawdown_pct(&self, equity: f64) -> f64 {
+        if self.high_water_mark <= 0.0 {
+            return 0.0;
+        }
+        ((self.high_water_mark - equity) / self.high_water_mark * 100.0).max(0.0)
+    }
+
+    /// Update from the latest equity and consecutive losing trades; returns
+    /// the multiplier for new entries.
+    pub fn observe(&mut self, equity: f64, streak: u64) -> f64 {
+        self.high_water_mark = self.high_water_mark.max(equity);
+        let drawdown = self.drawdown_pct(equity);
+
+        let steps = &self.limits.steps;
+        let crossed = steps
+            .iter()
+            .filter(|step| drawdown >= step.drawdown_pct)
+            .count();
+        if crossed > self.level {
+            self.level = crossed;
+        } else {
+            while self.level > 0
+                && drawdown < steps[self.level - 1].drawdown_pct - self.limits.recovery_pct
+            {
+                self.level -= 1;
+            }
+        }
+
+        let drawdown_scale = match self.level {
+            0 => 1.0,
+            level => steps[level - 1].scale,
+        };
+        let streak_scale = self
+            .limits
+            .streak_steps
+            .iter()
+            .filter(|step| streak >= step.losses)
+            .map(|step| step.scale)
+            .fold(1.0, f64::min);
+
+        let scale = drawdown_scale.min(streak_scale);
+        if scale != self.scale {
+            println!(
+                "[INFO][RISK] Size scale {:.2} -> {:.2} (drawdown {:.2}%, losing streak {})",
+                self.scale, scale, drawdown, streak
+            );
+            self.scale = scale;
+        }
+        scale
+    }
+}
+
+/// The day, kill switch and drawdown tracking of a `RiskManager`, carried
+/// across restarts by executioner snapshots.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RiskState {
+    pub day: Option<u64>,
+    pub day_start_equity: f64,
+    pub halted: Option<String>,
+    pub high_water_mark: f64,
+    pub drawdown_level: usize,
+    pub size_scale: f64,
+}
+
+/// Checks orders against `RiskLimits` and owns the daily loss kill switch.
+///
+/// Sells always pass: they only reduce exposure. Buys are scaled by the
+/// `DrawdownGovernor`, shrunk to the tightest remaining headroom and rejected
+/// when none is left. Once the day's
+/// loss reaches `daily_loss_limit_pct` the manager stays halted, across days
+/// and restarts when `halt_file` is set, until `reset` is called or the halt
+/// file is removed.
+#[derive(Debug)]
+pub struct RiskManager {
+    limits: RiskLimits,
+    day: Option<u64>,
+    day_start_equity: f64,
+    halted: Option<String>,
+    // the halt file is on disk because of this halt, so removing it resets
+    halt_file_written: bool,
+    governor: DrawdownGovernor,
+}
+
+impl RiskManager {
+    pub fn new(limits: RiskLimits) -> Self {
+        let halted = limits
+            .halt_file
+            .as_ref()
+            .filter(|path| Path::new(path).exists())
+            .map(|path| {
+                let reason = fs::read_to_string(path).unwrap_or_default();
+                println!(
+                    "[WARN][RISK] Halt file {} present, entries blocked until it is removed",
+                    path
+                );
+                reason.trim().to_string()
+            });
+        Self {
+            governor: DrawdownGovernor::new(limits.drawdown.clone()),
+            limits,
+            day: None,
+            day_start_equity: 0.0,
+            halt_file_written: halted.is_some(),
+            halted,
+        }
+    }
+
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    pub fn state(&self) -> RiskState {
+        let (high_water_mark, drawdown_level, size_scale) = self.governor.state();
+        RiskState {
+            day: self.day,
+            day_start_equity: self.day_start_equity,
+            halted: self.halted.clone(),
+            high_water_mark,
+            drawdown_level,
+            size_scale,
+        }
+    }
+
+    /// Resume from a snapshot. With a `halt_file` configured the file decides
+    /// whether entries are halted, so removing it still lifts the switch.
+    pub fn restore(&mut self, state: RiskState) {
+        self.day = state.day;
+        self.day_start_equity = state.day_start_equity;
+        if self.limits.halt_file.is_none() {
+            self.halted = state.halted;
+        }
+        self.governor.restore((
+            state.high_water_mark,
+            state.drawdown_level,
+            state.size_scale,
+        ));
+    }
+
+    /// Swap in edited limits. The day's starting equity, the drawdown
+    /// high-water mark and a tripped kill switch carry over, and so does the
+    /// halt file, which is only read at startup.
+    pub fn set_limits(&mut self, mut limits: RiskLimits) {
+        limits.halt_file = self.limits.halt_file.take();
+        self.governor.set_limits(limits.drawdown.clone());
+        self.limits = limits;
+    }
+
+    /// Multiplier applied to new entries by the drawdown governor
+    pub fn size_scale(&self) -> f64 {
+        self.governor.scale()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted.is_some()
+    }
+
+    /// Re-enable entries after the kill switch tripped.
+    pub fn reset(&mut self) {
+        if let Some(path) = &self.limits.halt_file
+            && let Err(e) = fs::remove_file(path)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            println!("[ERROR][RISK] Could not remove halt file {}: {}", path, e);
+        }
+        if self.halted.take().is_some() {
+            println!("[INFO][RISK] Kill switch reset, entries enabled");
+        }
+        self.halt_file_written = false;
+        self.day = None;
+    }
+
+    /// Track equity against its high-water mark and the start of the current
+    /// UTC day. `streak` is the number of consecutive losing trades. Returns
+    /// true exactly once, when the daily loss limit is first breached; the
+    /// caller should then flatten everything.
+    pub fn observe(&mut self, now: u64, equity: f64, streak: u64) -> bool {
+        self.governor.observe(equity, streak);
+        if self.is_halted() {
+            // a halt whose file could not be written lasts until restart
+            if self.halt_file_written
+                && let Some(path) = &self.limits.halt_file
+                && !Path::new(path).exists()
+            {
+                self.reset();
+            } else {
+                return false;
+            }
+        }
+
+        let day = now / SECS_PER_DAY;
+        if self.day != Some(day) {
+            self.day = Some(day);
+            self.day_start_equity = equity;
+        }
+        if self.limits.daily_loss_limit_pct <= 0.0 || self.day_start_equity <= 0.0 {
+            return false;
+        }
+
+        let loss_pct = (self.day_start_equity - equity) / self.day_start_equity * 100.0;
+        if loss_pct < self.limits.daily_loss_limit_pct {
+            return false;
+        }
+
+        let reason = format!(
+            "daily loss {:.2}% from {:.2} to {:.2} breached {:.2}% limit",
+            loss_pct, self.day_start_equity, equity, self.limits.daily_loss_limit_pct
+        );
+        println!("[ERROR][RISK] Kill switch tripped: {}", reason);
+        if let Some(path) = &self.limits.halt_file {
+            match fs::write(path, &reason) {
+                Ok(()) => self.halt_file_written = true,
+                Err(e) => println!(
+                    "[ERROR][RISK] Could not write halt file {}, halted until restart: {}",
+                    path, e
+                ),
+            }
+        }
+        self.halted = Some(reason);
+        true
+    }
+
+    /// Approve `order` for `symbol`, possibly with a smaller quantity rounded
+    /// down to `precision` decimals. `price` values market orders.
+    pub fn check(
+        &self,
+        order: Order,
+        symbol: &str,
+        price: f64,
+        precision: u64,
+        exposure: &Exposure,
+        correlations: &CorrelationEstimator,
+    ) -> Result<Order, RiskRejection> {
+        if matches!(order.side, OrderSide::Sell) {
+            return Ok(order);
+        }
+        if let Some(reason) = &self.halted {
+            return Err(RiskRejection::Halted(reason.clone()));
+        }
+        let price = order.price.unwrap_or(price);
+        if price <= 0.0 || !price.is_finite() {
+            return Err(RiskRejection::NoPrice);
+        }
+        if exposure.symbol_notional <= 0.0
+            && exposure.open_positions >= self.limits.max_open_positions
+        {
+            return Err(RiskRejection::MaxOpenPositions {
+                open: exposure.open_positions,
+                limit: self.limits.max_open_positions,
+            });
+        }
+
+        let limits = &self.limits.correlation;
+        let beta = |s: &str| {
+            correlations
+                .beta(s, &limits.benchmark, limits.min_samples)
+                .unwrap_or(1.0)
+        };
+        let beta_exposure: f64 = exposure
+            .holdings
+            .iter()
+            .map(|(held, notional)| notional * beta(held))
+            .sum();
+        let beta_cap = limits.max_beta_exposure * exposure.equity;
+        let beta_room = match beta(symbol) {
+            b if b > 0.0 => (beta_cap - beta_exposure) / b,
+            _ => f64::MAX,
+        };
+
+        // tightest remaining room, in USD
+        let (limit, cap, room) = [
+            (
+                "symbol notional cap",
+                self.limits.symbol_cap(symbol),
+                self.limits.symbol_cap(symbol) - exposure.symbol_notional,
+            ),
+            (
+                "gross exposure cap",
+                self.limits.max_gross_exposure,
+                self.limits.max_gross_exposure - exposure.gross,
+            ),
+            (
+                "per-trade capital fraction",
+                self.limits.max_trade_fraction * exposure.equity,
+                self.limits.max_trade_fraction * exposure.equity,
+            ),
+            ("beta-adjusted exposure cap", beta_cap, beta_room),
+        ]
+        .into_iter()
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .expect("non-empty");
+
+        let factor = 10f64.powi(precision as i32);
+        let max_qty = (room.max(0.0) / price * factor).floor() / factor;
+        if max_qty <= 0.0 {
+            return Err(RiskRejection::NoHeadroom { limit, cap });
+        }
+
+        let scale = self.governor.scale();
+        let quantity = if scale < 1.0 {
+            (order.quantity * scale * factor).floor() / factor
+        } else {
+            order.quantity
+        };
+        if quantity <= 0.0 {
+            return Err(RiskRejection::ScaledToZero { scale });
+        }
+        let quantity = quantity.min(max_qty);
+
+        let before = correlated_exposure(&exposure.holdings, correlations, limits.min_samples);
+        let mut after_holdings = exposure.holdings.clone();
+        match after_holdings.iter_mut().find(|(held, _)| held == symbol) {
+            Some((_, notional)) => *notional += quantity * price,
+            None => after_holdings.push((symbol.to_string(), quantity * price)),
+        }
+        let after = correlated_exposure(&after_holdings, correlations, limits.min_samples);
+        if exposure.equity > 0.0 && after > before {
+            let ratio = after / exposure.equity;
+            if ratio > limits.max_correlated_exposure {
+                return Err(RiskRejection::Concentrated {
+                    exposure: ratio,
+                    limit: limits.max_correlated_exposure,
+                });
+            }
+        }
+
+        Ok(Order { quantity, ..order })
+    }
+}
+
+/// sqrt(w' C w) for USD `holdings` and their correlations, unknown pairs
+/// counted as perfectly correlated
+pub fn correlated_exposure(
+    holdings: &[(String, f64)],
+    correlations: &CorrelationEstimator,
+    min_samples: usize,
+) -> f64 {
+    let mut total = 0.0;
+    for (a, wa) in holdings {
+        for (b, wb) in holdings {
+            let rho = correlations.correlation(a, b, min_samples).unwrap_or(1.0);
+            total += wa * wb * rho;
+        }
+    }
+    total.max(0.0).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roostoo::OrderType;
+
+    fn buy(quantity: f64) -> Order {
+        Order {
+            pair: "BTC/USD".to_string(),
+            side: OrderSide::Buy,
+            order_type: OrderType::Market,
+            quantity,
+            price: None,
+        }
+    }
+
+    fn exposure() -> Exposure {
+        Exposure {
+            equity: 50_000.0,
+            gross: 0.0,
+            open_positions: 0,
+            symbol_notional: 0.0,
+            holdings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn buys_shrink_to_tightest_limit() {
+        let risk = RiskManager::new(RiskLimits::default());
+        let none = CorrelationEstimator::new(60, 10);
+        // 0.25 * 50k = 12.5k per trade, 10k per symbol, 40k - 35k = 5k gross
+        let held = Exposure {
+            gross: 35_000.0,
+            ..exposure()
+        };
+        let order = risk
+            .check(buy(1.0), "BTC", 10_000.0, 3, &held, &none)
+            .unwrap();
+        assert_eq!(order.quantity, 0.5);
+
+        let full = Exposure {
+            gross: 40_000.0,
+            ..exposure()
+        };
+        assert!(matches!(
+            risk.check(buy(1.0), "BTC", 10_000.0, 3, &full, &none),
+            Err(RiskRejection::NoHeadroom {
+                limit: "gross exposure cap",
+                ..
+            })
+        ));
+
+        let crowded = Exposure {
+            open_positions: 15,
+            ..exposure()
+        };
+        assert!(matches!(
+            risk.check(buy(0.1), "BTC", 10_000.0, 3, &crowded, &none),
+            Err(RiskRejection::MaxOpenPositions { .. })
+        ));
+    }
+
+    #[test]
+    fn daily_loss_halts_until_reset() {
+        let mut risk = RiskManager::new(RiskLimits::default());
+        let none = CorrelationEstimator::new(60, 10);
+        let day = 1_700_006_400; // start of a UTC day
+        assert!(!risk.observe(day, 50_000.0, 0));
+        assert!(!risk.observe(day + 60, 48_000.0, 0));
+        assert!(risk.observe(day + 120, 47_400.0, 0));
+        // stays halted, and only reports the trip once
+        assert!(!risk.observe(day + SECS_PER_DAY, 50_000.0, 0));
+        assert!(risk.is_halted());
+        assert!(matches!(
+            risk.check(buy(0.1), "BTC", 100.0, 3, &exposure(), &none),
+            Err(RiskRejection::Halted(_))
+        ));
+
+        risk.reset();
+        assert!(
+            risk.check(buy(0.1), "BTC", 100.0, 3, &exposure(), &none)
+                .is_ok()
+        );
+
+        // a halt file that could not be written is not taken as removed
+        let mut risk = RiskManager::new(RiskLimits {
+            halt_file: Some("/nonexistent/fourier/HALT".to_string()),
+            ..RiskLimits::default()
+        });
+        assert!(!risk.observe(day, 50_000.0, 0));
+        assert!(risk.observe(day + 60, 47_400.0, 0));
+        assert!(!risk.observe(day + 120, 47_400.0, 0));
+        assert!(risk.is_halted());
+    }
+
+    #[test]
+    fn correlated_entries_are_capped_and_rejected() {
+        let risk = RiskManager::new(RiskLimits::default());
+        let unknown = CorrelationEstimator::new(60, 10);
+        // 19k held in an unestimated alt counts at beta 1 against 0.8 * 50k
+        let held = Exposure {
+            gross: 19_000.0,
+            open_positions: 1,
+            holdings: vec![("SOL".to_string(), 19_000.0)],
+            ..exposure()
+        };
+        let order = risk
+            .check(buy(1.0), "ETH", 10_000.0, 3, &held, &unknown)
+            .unwrap();
+        assert_eq!(order.quantity, 1.0);
+
+        // 25k more would put 45k of perfectly correlated exposure on 50k
+        let heavy = Exposure {
+            gross: 35_000.0,
+            open_positions: 2,
+            holdings: vec![("SOL".to_string(), 25_000.0), ("XRP".to_string(), 10_000.0)],
+            ..exposure()
+        };
+        assert!(matches!(
+            risk.check(buy(0.1), "ETH", 10_000.0, 3, &heavy, &unknown),
+            Err(RiskRejection::Concentrated { .. })
+        ));
+    }
+
+    #[test]
+    fn drawdown_scale_steps_down_and_recovers_with_hysteresis() {
+        let mut governor = DrawdownGovernor::new(DrawdownLimits::default());
+        assert_eq!(governor.observe(100.0, 0), 1.0);
+        assert_eq!(governor.observe(94.0, 0), 0.5);
+        assert_eq!(governor.observe(89.0, 0), 0.25);
+        // 8% is back under the 10% step but not under 10 - 2
+        assert_eq!(governor.observe(92.0, 0), 0.25);
+        assert_eq!(governor.observe(92.5, 0), 0.5);
+        assert_eq!(governor.observe(97.5, 0), 1.0);
+        // losing streak de-risks on its own and clears with it
+        assert_eq!(governor.observe(100.0, 5), 0.25);
+        assert_eq!(governor.observe(100.0, 0), 1.0);
+    }
+
+    #[test]
+    fn sample_config_risk_section_is_valid() {
+        let config = ConfigFile::load(concat!(env!("CARGO_MANIFEST_DIR"), "/config.yaml")).unwrap();
+        let limits = RiskLimits::from_config(&config).unwrap();
+        assert!(limits.symbol_cap("BONK") < limits.max_symbol_notional);
+    }
+}
@@ -0,0 +1,433 @@
+use crate::backtest::max_drawdown_pct;
+use crate::journal::TradeRecord;
+use serde::{Deserialize, Serialize};
+
+const EULER_MASCHERONI: f64 = 0.577_215_664_901_532_9;
+
+/// Small deterministic PRNG (SplitMix64) so resampling is reproducible from a seed.
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+}
+
+/// Settings for the resampling run, all optional in YAML.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RobustnessConfig {
+    pub samples: usize,   // Monte Carlo paths
+    pub block_len: usize, // trades per bootstrap block, keeps streaks together
+    pub seed: u64,
+    pub cscv_blocks: usize, // even number of blocks for the PBO estimate
+}
+
+impl Default for RobustnessConfig {
+    fn default() -> Self {
+        Self {
+            samples: 1000,
+            block_len: 5,
+            seed: 42,
+            cscv_blocks: 16,
+        }
+    }
+}
+
+/// Percentiles of a resampled statistic.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct Distribution {
+    pub mean: f64,
+    pub p05: f64,
+    pub p25: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p95: f64,
+}
+
+impl Distribution {
+    pub fn from_samples(mut samples: Vec<f64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let at = |q: f64| samples[((samples.len() - 1) as f64 * q).round() as usize];
+        Self {
+            mean: samples.iter().sum::<f64>() / samples.len() as f64,
+            p05: at(0.05),
+            p25: at(0.25),
+            p50: at(0.50),
+            p75: at(0.75),
+            p95: at(0.95),
+        }
+    }
+}
+
+/// Final equity and max drawdown over resampled trade sequences.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MonteCarloSummary {
+    pub final_equity: Distribution,
+    pub max_drawdown_pct: Distribution,
+}
+
+/// Each trade's pnl as a return on the equity it was taken with, so resampled
+/// paths compound the same way the original did.
+pub fn trade_returns(trades: &[TradeRecord], initial_capital: f64) -> Vec<f64> {
+    let mut equity = initial_capital;
+    let mut returns = Vec::with_capacity(trades.len());
+    for trade in trades {
+        if equity <= 0.0 {
+            break;
+        }
+        returns.push(trade.pnl / equity);
+        equity += trade.pnl;
+    }
+    returns
+}
+
+fn path_stats(returns: &[f64], initial_capital: f64) -> (f64, f64) {
+    let mut equity = Vec::with_capacity(returns.len() + 1);
+    equity.push(initial_capital);
+    let mut current = initial_capital;
+    for r in returns {
+        current *= 1.0 + r;
+        equity.push(current);
+    }
+    (current, max_drawdown_pct(&equity))
+}
+
+/// Random permutations of the trade order. Final equity is unchanged by
+/// reordering, so this mainly shows how lucky the drawdown was.
+pub fn shuffle_trades(
+    returns: &[f64],
+    initial_capital: f64,
+    config: &RobustnessConfig,
+) -> MonteCarloSummary {
+    let mut rng = SplitMix64::new(config.seed);
+    let mut path = returns.to_vec();
+    let mut finals = Vec::with_capacity(config.samples);
+    let mut drawdowns = Vec::with_capacity(config.samples);
+    for _ in 0..config.samples {
+        // Fisher-Yates
+        for i in (1..path.len()).rev() {
+            let j = rng.below(i + 1);
+            path.swap(i, j);
+        }
+        let (last, dd) = path_stats(&path, initial_capital);
+        finals.push(last);
+        drawdowns.push(dd);
+    }
+    MonteCarloSummary {
+        final_equity: Distribution::from_samples(finals),
+        max_drawdown_pct: Distribution::from_samples(drawdowns),
+    }
+}
+
+/// Moving-block bootstrap: paths of the same length built from randomly
+/// chosen runs of `block_len` consecutive trades, drawn with replacement.
+pub fn block_bootstrap(
+    returns: &[f64],
+    initial_capital: f64,
+    config: &RobustnessConfig,
+) -> MonteCarloSummary {
+    if returns.is_empty() {
+        return MonteCarloSummary::default();
+    }
+    let block = config.block_len.clamp(1, returns.len());
+    let starts = returns.len() - block + 1;
+    let mut rng = SplitMix64::new(config.seed);
+    let mut finals = Vec::with_capacity(config.samples);
+    let mut drawdowns = Vec::with_capacity(config.samples);
+    let mut path = Vec::with_capacity(returns.len());
+
+    for _ in 0..config.samples {
+        path.clear();
+        while path.len() < returns.len() {
+            let start = rng.below(starts);
+            let take = block.min(returns.len() - path.len());
+            path.extend_from_slice(&returns[start..start + take]);
+        }
+        let (last, dd) = path_stats(&path, initial_capital);
+        finals.push(last);
+        drawdowns.push(dd);
+    }
+    MonteCarloSummary {
+        final_equity: Distribution::from_samples(finals),
+        max_drawdown_pct: Distribution::from_samples(drawdowns),
+    }
+}
+
+/// Standard normal CDF
+pub fn normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+// Abramowitz & Stegun 7.1.26, absolute error below 1.5e-7
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    sign * (1.0 - poly * (-x * x).exp())
+}
+
+/// Inverse of the standard normal CDF (Acklam's rational approximation)
+pub fn normal_inv_cdf(p: f64) -> f64 {
+    if p <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1.0 {
+        return f64::INFINITY;
+    }
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    let low = 0.024_25;
+    if p < low {
+        let q = (-2.0 * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    } else if p <= 1.0 - low {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -normal_inv_cdf(1.0 - p)
+    }
+}
+
+/// Per-period (not annualised) Sharpe, skewness and non-excess kurtosis.
+pub fn moments(returns: &[f64]) -> (f64, f64, f64) {
+    let n = returns.len() as f64;
+    if n < 2.0 {
+        return (0.0, 0.0, 3.0);
+    }
+    let mean = returns.iter().sum::<f64>() / n;
+    let m2 = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n;
+    if m2 <= 0.0 {
+        return (0.0, 0.0, 3.0);
+    }
+    let m3 = returns.iter().map(|r| (r - mean).powi(3)).sum::<f64>() / n;
+    let m4 = returns.iter().map(|r| (r - mean).powi(4)).sum::<f64>() / n;
+    let sd = m2.sqrt();
+    (mean / sd, m3 / sd.powi(3), m4 / (m2 * m2))
+}
+
+/// Sharpe ratio expected from the best of `trials` unskilled strategies whose
+/// Sharpe ratios have variance `sharpe_variance` (Bailey & Lopez de Prado).
+pub fn expected_max_sharpe(trials: usize, sharpe_variance: f64) -> f64 {
+    if trials < 2 {
+        return 0.0;
+    }
+    let n = trials as f64;
+    sharpe_variance.max(0.0).sqrt()
+        * ((1.0 - EULER_MASCHERONI) * normal_inv_cdf(1.0 - 1.0 / n)
+            + EULER_MASCHERONI * normal_inv_cdf(1.0 - 1.0 / (n * std::f64::consts::E)))
+}
+
+/// Probability that the selected strategy's true Sharpe is above what the
+/// best of `trial_sharpes` would show by luck. All Sharpes are per period,
+/// `returns` are the selected strategy's period returns.
+pub fn deflated_sharpe(returns: &[f64], trial_sharpes: &[f64]) -> f64 {
+    let (sharpe, skew, kurtosis) = moments(returns);
+    let n = trial_sharpes.len().max(1) as f64;
+    let mean = trial_sharpes.iter().sum::<f64>() / n;
+    let variance = trial_sharpes
+        .iter()
+        .map(|s| (s - mean).powi(2))
+        .sum::<f64>()
+        / n;
+    let benchmark = expected_max_sharpe(trial_sharpes.len(), variance);
+
+    let t = returns.len() as f64;
+    let denom = 1.0 - skew * sharpe + (kurtosis - 1.0) / 4.0 * sharpe * sharpe;
+    if t < 2.0 || denom <= 0.0 {
+        return 0.0;
+    }
+    normal_cdf((sharpe - benchmark) * (t - 1.0).sqrt() / denom.sqrt())
+}
+
+fn block_sharpe(series: &[f64], blocks: &[usize], block_len: usize) -> f64 {
+    let returns: Vec<f64> = blocks
+        .iter()
+        .flat_map(|b| series[b * block_len..(b + 1) * block_len].iter().copied())
+        .collect();
+    moments(&returns).0
+}
+
+/// Probability of backtest overfitting via combinatorially symmetric
+/// cross-validation. `trials` holds one period-return series per parameter set,
+/// all over the same periods. Returns the share of in-sample/out-of-sample
+/// splits where the in-sample winner ranks in the bottom half out of sample.
+pub fn probability_of_overfitting(trials: &[Vec<f64>], blocks: usize) -> Option<f64> {
+    let blocks = blocks - blocks % 2;
+    let length = trials.iter().map(|t| t.len()).min()?;
+    if trials.len() < 2 || blocks < 2 || length < blocks {
+        return None;
+    }
+    let block_len = length / blocks;
+
+    let mut overfit = 0usize;
+    let mut splits = 0usize;
+    for in_sample in combinations(blocks, blocks / 2) {
+        let out_of_sample: Vec<usize> = (0..blocks).filter(|b| !in_sample.contains(b)).collect();
+        let is_scores: Vec<f64> = trials
+            .iter()
+            .map(|t| block_sharpe(t, &in_sample, block_len))
+            .collect();
+        let oos_scores: Vec<f64> = trials
+            .iter()
+            .map(|t| block_sharpe(t, &out_of_sample, block_len))
+            .collect();
+
+        let best = (0..trials.len())
+            .max_by(|a, b| {
+                is_scores[*a]
+                    .partial_cmp(&is_scores[*b])
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap_or(0);
+        // relative rank of the in-sample winner out of sample, in (0, 1)
+        let below = oos_scores.iter().filter(|s| **s < oos_scores[best]).count();
+        let omega = (below as f64 + 1.0) / (trials.len() as f64 + 1.0);
+        if (omega / (1.0 - omega)).ln() <= 0.0 {
+            overfit += 1;
+        }
+        splits += 1;
+    }
+    Some(overfit as f64 / splits as f64)
+}
+
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    let mut result = Vec::new();
+    let mut current = Vec::with_capacity(k);
+    fn go(start: usize, n: usize, k: usize, current: &mut Vec<usize>, out: &mut Vec<Vec<usize>>) {
+        if current.len() == k {
+            out.push(current.clone());
+            return;
+        }
+        for i in start..n {
+            current.push(i);
+            go(i + 1, n, k, current, out);
+            current.pop();
+        }
+    }
+    go(0, n, k, &mut current, &mut result);
+    result
+}
+
+/// Everything computed for the selected run of a sweep.
+#[derive(Debug, Clone, Serialize)]
+pub struct RobustnessReport {
+    pub trades: usize,
+    pub shuffled: MonteCarloSummary,
+    pub bootstrap: MonteCarloSummary,
+    pub deflated_sharpe: f64,
+    pub probability_of_overfitting: Option<f64>,
+}
+
+/// `selected` is the chosen run's trades and period returns; `trial_returns`
+/// are the period returns of every run in the sweep, the chosen one included.
+pub fn analyse(
+    trades: &[TradeRecord],
+    selected_returns: &[f64],
+    trial_returns: &[Vec<f64>],
+    initial_capital: f64,
+    config: &RobustnessConfig,
+) -> RobustnessReport {
+    let returns = trade_returns(trades, initial_capital);
+    let trial_sharpes: Vec<f64> = trial_returns.iter().map(|r| moments(r).0).collect();
+    RobustnessReport {
+        trades: trades.len(),
+        shuffled: shuffle_trades(&returns, initial_capital, config),
+        bootstrap: block_bootstrap(&returns, initial_capital, config),
+        deflated_sharpe: deflated_sharpe(selected_returns, &trial_sharpes),
+        probability_of_overfitting: probability_of_overfitting(trial_returns, config.cscv_blocks),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inverse_cdf_round_trips() {
+        for p in [0.01, 0.1, 0.5, 0.9, 0.99] {
+            assert!((normal_cdf(normal_inv_cdf(p)) - p).abs() < 1e-6);
+        }
+        assert!((normal_inv_cdf(0.975) - 1.959_964).abs() < 1e-4);
+    }
+
+    #[test]
+    fn shuffling_keeps_final_equity() {
+        let returns = vec![0.01, -0.02, 0.03, -0.01, 0.02];
+        let summary = shuffle_trades(&returns, 1000.0, &RobustnessConfig::default());
+        let expected: f64 = returns.iter().fold(1000.0, |e, r| e * (1.0 + r));
+        assert!((summary.final_equity.p05 - expected).abs() < 1e-9);
+        assert!((summary.final_equity.p95 - expected).abs() < 1e-9);
+        assert!(summary.max_drawdown_pct.p95 >= summary.max_drawdown_pct.p05);
+    }
+
+    #[test]
+    fn more_trials_raise_the_bar() {
+        assert!(expected_max_sharpe(100, 0.01) > expected_max_sharpe(10, 0.01));
+        assert_eq!(expected_max_sharpe(1, 0.01), 0.0);
+    }
+
+    #[test]
+    fn dominant_trial_is_not_flagged_as_overfit() {
+        let mut rng = SplitMix64::new(7);
+        let series: Vec<f64> = (0..160)
+            .map(|_| (rng.below(1000) as f64 - 450.0) / 10_000.0)
+            .collect();
+        let better: Vec<f64> = series.iter().map(|r| r + 0.01).collect();
+        let pbo = probability_of_overfitting(&[series, better], 8).unwrap();
+        assert_eq!(pbo, 0.0);
+    }
+}
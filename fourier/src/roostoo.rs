@@ -220,7 +220,7 @@ impl std::fmt::Display for OrderType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum OrderSide {
     Buy,
     Sell,
@@ -397,7 +397,7 @@ impl RoostooClient {
             .await?;
 
         let pending_response: PendingCountResponse = response.json().await?;
-        return Ok(pending_response);
+        Ok(pending_response)
     }
 
     /// New order (Trade)
@@ -414,12 +414,12 @@ impl RoostooClient {
         let timestamp = self.get_timestamp();
 
         // Validate parameters
-        if let OrderType::Limit = order_type {
-            if price.is_none() {
-                return Err(RoostooError::InvalidParameter(
-                    "LIMIT orders require a price".to_string(),
-                ));
-            }
+        if let OrderType::Limit = order_type
+            && price.is_none()
+        {
+            return Err(RoostooError::InvalidParameter(
+                "LIMIT orders require a price".to_string(),
+            ));
         }
 
         let mut params = HashMap::new();
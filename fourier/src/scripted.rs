@@ -0,0 +1,535 @@
+use crate::config::{ConfigError, ConfigFile, Validate, check_range, fingerprint};
+use crate::exits::{ExitPolicies, ExitPolicy};
+use crate::fourier::{Candle, Position};
+use crate::indicators::Indicators;
+use crate::roostoo::{OrderSide, OrderType};
+use crate::strategy::{ExecContext, Order, SharedState, Strategy};
+use async_trait::async_trait;
+use rhai::{AST, Dynamic, Engine, Map, Scope};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+
+// functions a script has to define, each taking the candle's context
+const ENTRY_FN: &str = "should_long";
+const SIZE_FN: &str = "size";
+const EXIT_FN: &str = "should_exit";
+
+/// Tuning for `ScriptedStrategy`, loaded from the `scripted` section of the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScriptedParams {
+    pub path: String,        // Rhai script defining should_long, size and should_exit
+    pub reload_secs: u64,    // how often the script is checked for changes
+    pub max_operations: u64, // evaluation budget per call, so a runaway loop errors out
+    pub max_notional: f64,   // hard cap on entry size in USD
+    pub exits: ExitPolicies, // protective exits, kept even while the script is disabled
+}
+
+impl Default for ScriptedParams {
+    fn default() -> Self {
+        ScriptedParams {
+            path: "scripts/ema_cross.rhai".to_string(),
+            reload_secs: 5,
+            max_operations: 100_000,
+            max_notional: 10_000.0,
+            exits: ExitPolicies::new(vec![ExitPolicy::AtrStop {
+                period: 14,
+                multiple: 3.0,
+            }]),
+        }
+    }
+}
+
+impl Validate for ScriptedParams {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        check_range(scope, "reload_secs", self.reload_secs as f64, 1.0, f64::MAX)?;
+        check_range(
+            scope,
+            "max_operations",
+            self.max_operations as f64,
+            1.0,
+            f64::MAX,
+        )?;
+        check_range(scope, "max_notional", self.max_notional, 0.0, f64::MAX)?;
+        self.exits.validate(scope)
+    }
+}
+
+// what a script sees of one symbol; read-only, so scripts cannot change it
+#[derive(Debug, Clone)]
+struct ScriptContext {
+    symbol: String,
+    candles: Arc<Vec<Candle>>,
+    position: Position,
+    last_close: f64,
+    last_signal: f64,
+    precision: u64,
+    capital: f64, // available capital in USD
+    now: u64,
+}
+
+impl ScriptContext {
+    fn new(ctx: &ExecContext, candles: Arc<Vec<Candle>>, capital: f64) -> Self {
+        Self {
+            symbol: ctx.symbol.clone(),
+            candles,
+            position: ctx.position.clone(),
+            last_close: ctx.last_close,
+            last_signal: ctx.last_signal,
+            precision: ctx.precision,
+            capital,
+            now: ctx.now_secs(),
+        }
+    }
+
+    fn indicators(&self) -> Indicators<'_> {
+        Indicators::new(&self.candles)
+    }
+}
+
+// indicator periods come from scripts as signed integers
+fn period(value: i64) -> Option<usize> {
+    usize::try_from(value).ok().filter(|p| *p > 0)
+}
+
+// a missing indicator value is `()` to the script
+fn value(value: Option<f64>) -> Dynamic {
+    value.map(Dynamic::from).unwrap_or(Dynamic::UNIT)
+}
+
+fn engine(max_operations: u64) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(max_operations);
+
+    engine
+        .register_type_with_name::<ScriptContext>("Context")
+        .register_get("symbol", |c: &mut ScriptContext| c.symbol.clone())
+        .register_get("last_close", |c: &mut ScriptContext| c.last_close)
+        .register_get("last_signal", |c: &mut ScriptContext| c.last_signal)
+        .register_get("precision", |c: &mut ScriptContext| c.precision as i64)
+        .register_get("capital", |c: &mut ScriptContext| c.capital)
+        .register_get("now", |c: &mut ScriptContext| c.now as i64)
+        .register_get("candle_count", |c: &mut ScriptContext| {
+            c.candles.len() as i64
+        })
+        .register_get("position", |c: &mut ScriptContext| c.position.clone())
+        .register_fn("candle", |c: &mut ScriptContext, back: i64| {
+            let Some(candle) = usize::try_from(back)
+                .ok()
+                .and_then(|back| c.candles.iter().rev().nth(back))
+            else {
+                return Dynamic::UNIT;
+            };
+            let mut map = Map::new();
+            map.insert("open".into(), candle.open.into());
+            map.insert("high".into(), candle.high.into());
+            map.insert("low".into(), candle.low.into());
+            map.insert("close".into(), candle.close.into());
+            map.insert("volume".into(), candle.volume.into());
+            map.insert("close_time".into(), (candle.close_secs() as i64).into());
+            map.into()
+        })
+        .register_fn("ema", |c: &mut ScriptContext, p: i64| {
+            value(period(p).and_then(|p| c.indicators().ema(p)))
+        })
+        .register_fn("sma", |c: &mut ScriptContext, p: i64| {
+            value(period(p).and_then(|p| c.indicators().sma(p)))
+        })
+        .register_fn("rsi", |c: &mut ScriptContext, p: i64| {
+            value(period(p).and_then(|p| c.indicators().rsi(p)))
+        })
+        .register_fn("atr", |c: &mut ScriptContext, p: i64| {
+            value(period(p).and_then(|p| c.indicators().atr(p)))
+        })
+        .register_fn("stddev", |c: &mut ScriptContext, p: i64| {
+            value(period(p).and_then(|p| {
+                c.indicators()
+                    .stddev_series(c.candles.iter().map(|candle| candle.close), p)
+            }))
+        })
+        .register_fn("volatility", |c: &mut ScriptContext, p: i64| {
+            value(period(p).and_then(|p| c.indicators().annualised_volatility(p)))
+        })
+        .register_fn(
+            "highest_high",
+            |c: &mut ScriptContext, p: i64, skip: i64| {
+                let skip = usize::try_from(skip).ok();
+                value(
+                    period(p)
+                        .zip(skip)
+                        .and_then(|(p, skip)| c.indicators().highest_high(p, skip)),
+                )
+            },
+        )
+        .register_fn("lowest_low", |c: &mut ScriptContext, p: i64, skip: i64| {
+            let skip = usize::try_from(skip).ok();
+            value(
+                period(p)
+                    .zip(skip)
+                    .and_then(|(p, skip)| c.indicators().lowest_low(p, skip)),
+            )
+        });
+
+    engine
+        .register_type_with_name::<Position>("Position")
+        .register_get("quantity", |p: &mut Position| p.quantity)
+        .register_get("entry_price", |p: &mut Position| p.entry_price)
+        .register_get("entry_time", |p: &mut Position| match p.entry_time {
+            Some(time) => Dynamic::from(time as i64),
+            None => Dynamic::UNIT,
+        })
+        .register_get("realized_pnl", |p: &mut Position| p.realized_pnl)
+        .register_get("unrealized_pnl", |p: &mut Position| p.unrealized_pnl)
+        .register_get("is_open", |p: &mut Position| p.is_open())
+        .register_fn("unrealized_pct", |p: &mut Position, mark: f64| {
+            value(p.unrealized_pct(mark))
+        });
+
+    engine
+}
+
+// read and compile the script at `path`, checking it defines every rule
+fn compile(engine: &Engine, path: &str) -> Result<AST, ConfigError> {
+    let source =
+        fs::read_to_string(path).map_err(|e| ConfigError::ReadError(path.to_string(), e))?;
+    let ast = engine
+        .compile(&source)
+        .map_err(|e| ConfigError::ScriptError(path.to_string(), e.to_string()))?;
+    for name in [ENTRY_FN, SIZE_FN, EXIT_FN] {
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == name && f.params.len() == 1)
+        {
+            return Err(ConfigError::ScriptError(
+                path.to_string(),
+                format!("missing fn {}(ctx)", name),
+            ));
+        }
+    }
+    Ok(ast)
+}
+
+// whether `cached` holds the same candles as `candles`; history is only
+// ever appended to and trimmed at the front, so the ends and length suffice
+fn same_history(cached: &[Candle], candles: &[Candle]) -> bool {
+    cached.len() == candles.len()
+        && cached.first().map(|c| c.close_time) == candles.first().map(|c| c.close_time)
+        && cached.last().map(|c| c.close_time) == candles.last().map(|c| c.close_time)
+}
+
+// the compiled script; `ast` is None while the strategy is disabled
+struct Script {
+    ast: Option<AST>,
+    fingerprint: Option<(SystemTime, u64)>,
+    next_check: u64,
+}
+
+/// Entry, sizing and exit rules written in Rhai instead of Rust. The script
+/// at `path` defines `should_long(ctx)`, `size(ctx)` (base units to buy) and
+/// `should_exit(ctx)`, where `ctx` exposes the symbol's last close, position,
+/// available capital and indicators like `ctx.ema(12)` read-only.
+///
+/// The script is reloaded whenever it changes on disk. A script that fails
+/// to compile or errors while running disables the strategy, which then
+/// neither enters nor exits on its own, until a fixed version is saved; the
+/// protective `exits` keep running meanwhile.
+pub struct ScriptedStrategy {
+    pub params: ScriptedParams,
+    engine: Engine,
+    script: std::sync::Mutex<Script>,
+    history: std::sync::Mutex<HashMap<String, Arc<Vec<Candle>>>>, // per symbol, shared with scripts
+}
+
+impl ScriptedStrategy {
+    pub const SECTION: &'static str = "scripted";
+
+    /// Fails if the script cannot be read or compiled
+    pub fn new(params: ScriptedParams) -> Result<Self, ConfigError> {
+        let engine = engine(params.max_operations);
+        let ast = compile(&engine, &params.path)?;
+        let script = Script {
+            ast: Some(ast),
+            fingerprint: fingerprint(&params.path),
+            next_check: 0,
+        };
+        Ok(Self {
+            params,
+            engine,
+            script: std::sync::Mutex::new(script),
+            history: std::sync::Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn from_config(config: &ConfigFile) -> Result<Self, ConfigError> {
+        Self::new(config.settings(Self::SECTION)?.unwrap_or_default())
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::from_config(&ConfigFile::load(path)?)
+    }
+
+    /// False while a broken script has the strategy disabled
+    pub fn is_enabled(&self) -> bool {
+        self.script.lock().unwrap().ast.is_some()
+    }
+
+    // recompiles the script if it changed since the last check
+    fn check_script(&self, now: u64) {
+        let mut script = self.script.lock().unwrap();
+        if now < script.next_check {
+            return;
+        }
+        script.next_check = now + self.params.reload_secs;
+        let current = fingerprint(&self.params.path);
+        if current == script.fingerprint {
+            return;
+        }
+        script.fingerprint = current;
+        match compile(&self.engine, &self.params.path) {
+            Ok(ast) => {
+                println!("[INFO][SCRIPT] Loaded {}", self.params.path);
+                script.ast = Some(ast);
+            }
+            Err(e) => {
+                println!("[ERROR][SCRIPT] {}; strategy disabled", e);
+                script.ast = None;
+            }
+        }
+    }
+
+    // runs rule `name` and converts its result, disabling the strategy when
+    // either fails
+    fn call<T>(
+        &self,
+        name: &str,
+        ctx: ScriptContext,
+        convert: impl FnOnce(&Dynamic) -> Option<T>,
+    ) -> Option<T> {
+        let mut script = self.script.lock().unwrap();
+        let ast = script.ast.as_ref()?;
+        let symbol = ctx.symbol.clone();
+        let error = match self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), ast, name, (ctx,))
+        {
+            Ok(result) => match convert(&result) {
+                Some(converted) => return Some(converted),
+                None => format!("returned {}", result.type_name()),
+            },
+            Err(e) => e.to_string(),
+        };
+        println!(
+            "[ERROR][SCRIPT] {} {} for {}: {}; strategy disabled until the script changes",
+            self.params.path, name, symbol, error
+        );
+        script.ast = None;
+        None
+    }
+
+    // the symbol's candles as scripts see them. Every call on the same
+    // candle shares one copy, and a new candle only appends to it and trims
+    // its front instead of copying the whole history again
+    fn history(&self, ctx: &ExecContext) -> Arc<Vec<Candle>> {
+        let mut history = self.history.lock().unwrap();
+        let cached = history.entry(ctx.symbol.clone()).or_default();
+        if !same_history(cached, &ctx.candles) {
+            // no script holds on to it between calls, so this updates in place
+            let candles = Arc::make_mut(cached);
+            let known = candles.last().and_then(|last| {
+                ctx.candles
+                    .iter()
+                    .rposition(|c| c.close_time == last.close_time)
+            });
+            if let Some(known) = known {
+                candles.extend_from_slice(&ctx.candles[known + 1..]);
+                let excess = candles.len().saturating_sub(ctx.candles.len());
+                candles.drain(..excess);
+            }
+            if !same_history(candles, &ctx.candles) {
+                *candles = ctx.candles.clone();
+            }
+        }
+        Arc::clone(cached)
+    }
+
+    async fn context(
+        &self,
+        ctx: &ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> ScriptContext {
+        let capital = shared_state.lock().await.available_capital();
+        ScriptContext::new(ctx, self.history(ctx), capital)
+    }
+}
+
+#[async_trait]
+impl Strategy for ScriptedStrategy {
+    async fn should_long(
+        &self,
+        ctx: &mut ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        if ctx.position.is_open() {
+            return false;
+        }
+        self.check_script(ctx.now_secs());
+        let script_ctx = self.context(ctx, shared_state).await;
+        self.call(ENTRY_FN, script_ctx, |r| r.as_bool().ok())
+            .unwrap_or(false)
+    }
+
+    async fn go_long(
+        &self,
+        ctx: &ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> Option<Order> {
+        let script_ctx = self.context(ctx, shared_state).await;
+        let size = self.call(SIZE_FN, script_ctx, |r| {
+            r.as_float()
+                .ok()
+                .or_else(|| r.as_int().ok().map(|q| q as f64))
+        })?;
+        let cap = if ctx.last_close > 0.0 {
+            self.params.max_notional / ctx.last_close
+        } else {
+            0.0
+        };
+        let quantity = size.min(cap);
+        if !quantity.is_finite() || quantity <= 0.0 {
+            return None;
+        }
+
+        Some(Order {
+            pair: ctx.pair(),
+            side: OrderSide::Buy,
+            order_type: OrderType::Market,
+            quantity,
+            price: None,
+        })
+    }
+
+    async fn update_position(
+        &self,
+        ctx: &mut ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        if self.params.exits.evaluate(ctx).is_some() {
+            return true;
+        }
+        if !ctx.position.is_open() {
+            return false;
+        }
+        self.check_script(ctx.now_secs());
+        let script_ctx = self.context(ctx, shared_state).await;
+        self.call(EXIT_FN, script_ctx, |r| r.as_bool().ok())
+            .unwrap_or(false)
+    }
+
+    fn protective_exits(&self, _symbol: &str) -> Option<&ExitPolicies> {
+        Some(&self.params.exits)
+    }
+
+    fn reload(&mut self, config: &ConfigFile) -> Result<(), ConfigError> {
+        let params: ScriptedParams = config.settings(Self::SECTION)?.unwrap_or_default();
+        if params.path != self.params.path || params.max_operations != self.params.max_operations {
+            *self = Self::new(params)?;
+        } else {
+            self.params = params;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = "
+        fn should_long(ctx) { ctx.last_close > ctx.sma(3) }
+        fn size(ctx) { 2 }
+        fn should_exit(ctx) { ctx.position.is_open }
+    ";
+
+    fn context(closes: &[f64]) -> ExecContext {
+        let candles: Vec<Candle> = closes
+            .iter()
+            .enumerate()
+            .map(|(i, close)| Candle {
+                close_time: (i as u64 + 1) * 60_000,
+                close: *close,
+                high: *close,
+                low: *close,
+                ..Default::default()
+            })
+            .collect();
+        ExecContext::for_test("BTC", candles)
+    }
+
+    #[tokio::test]
+    async fn errors_disable_the_script_until_it_is_fixed() {
+        let path =
+            std::env::temp_dir().join(format!("fourier-scripted-{}.rhai", std::process::id()));
+        fs::write(&path, RULES).unwrap();
+        let strategy = ScriptedStrategy::new(ScriptedParams {
+            path: path.display().to_string(),
+            reload_secs: 1,
+            ..Default::default()
+        })
+        .unwrap();
+        let shared = Arc::new(Mutex::new(SharedState::new(10_000.0)));
+
+        let mut ctx = context(&[100.0, 100.0, 100.0, 103.0]);
+        assert!(strategy.should_long(&mut ctx, shared.clone()).await);
+        let order = strategy.go_long(&ctx, shared.clone()).await.unwrap();
+        assert_eq!(order.quantity, 2.0);
+
+        // a runtime error switches the strategy off instead of panicking
+        fs::write(&path, RULES.replace("ctx.sma(3)", "ctx.missing")).unwrap();
+        ctx.candles.last_mut().unwrap().close_time += 60_000;
+        assert!(!strategy.should_long(&mut ctx, shared.clone()).await);
+        assert!(!strategy.is_enabled());
+        assert!(strategy.go_long(&ctx, shared.clone()).await.is_none());
+
+        // saving a fixed script turns it back on
+        fs::write(&path, RULES.replace("2 }", "1.5 }")).unwrap();
+        ctx.candles.last_mut().unwrap().close_time += 60_000;
+        assert!(strategy.should_long(&mut ctx, shared.clone()).await);
+        assert!(strategy.is_enabled());
+        let order = strategy.go_long(&ctx, shared).await.unwrap();
+        assert_eq!(order.quantity, 1.5);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn calls_on_one_candle_share_the_history_and_new_candles_extend_it() {
+        let path = std::env::temp_dir().join(format!(
+            "fourier-scripted-history-{}.rhai",
+            std::process::id()
+        ));
+        fs::write(&path, RULES).unwrap();
+        let strategy = ScriptedStrategy::new(ScriptedParams {
+            path: path.display().to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut ctx = context(&[100.0, 101.0, 102.0]);
+        let first = strategy.history(&ctx);
+        assert!(Arc::ptr_eq(&first, &strategy.history(&ctx)));
+        let closes = |candles: &[Candle]| candles.iter().map(|c| c.close).collect::<Vec<_>>();
+        assert_eq!(closes(&first), closes(&ctx.candles));
+        drop(first);
+
+        // a new candle with the oldest trimmed, as the executioner keeps it
+        let next = context(&[100.0, 101.0, 102.0, 103.0]).candles[3];
+        ctx.candles.remove(0);
+        ctx.candles.push(next);
+        assert_eq!(closes(&strategy.history(&ctx)), vec![101.0, 102.0, 103.0]);
+    }
+}
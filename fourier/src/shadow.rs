@@ -0,0 +1,312 @@
+use crate::backtest::BacktestReport;
+use crate::config::{ConfigError, ConfigFile, Validate, check_range};
+use crate::fourier::Candle;
+use crate::registry;
+use crate::risk::RiskLimits;
+use crate::snapshot::{ExecutionerSnapshot, SnapshotError};
+use crate::strategy::{CandleData, Executioner, Strategy, TraderConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::mpsc;
+
+/// Candidate strategies evaluated live next to the traded one, from the
+/// `shadows` section:
+/// ```yaml
+/// shadows:
+///   strategies: [bollinger, breakout]
+///   capital: 10000.0
+///   report_secs: 3600
+/// ```
+/// Each is built from its own section and trades `capital` of virtual money
+/// under the live risk limits.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShadowConfig {
+    pub strategies: Vec<String>,
+    pub capital: f64,     // virtual starting capital of each shadow, USD
+    pub report_secs: u64, // how often PnL is logged next to the live strategy
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        Self {
+            strategies: Vec::new(),
+            capital: 10_000.0,
+            report_secs: 3_600,
+        }
+    }
+}
+
+impl Validate for ShadowConfig {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        check_range(scope, "capital", self.capital, 1.0, f64::MAX)?;
+        check_range(scope, "report_secs", self.report_secs as f64, 1.0, f64::MAX)
+    }
+}
+
+impl ShadowConfig {
+    pub const SECTION: &'static str = "shadows";
+
+    /// Shadows if the config file has a `shadows` section
+    pub fn from_config(config: &ConfigFile) -> Result<Option<Self>, ConfigError> {
+        config.settings(Self::SECTION)
+    }
+
+    /// Every listed strategy, built from its section of `config`
+    pub fn build(
+        &self,
+        config: &ConfigFile,
+        risk_limits: &RiskLimits,
+    ) -> Result<Shadows, ConfigError> {
+        let mut shadows = Shadows::new(self.report_secs);
+        for name in &self.strategies {
+            let strategy = registry::build(name, config)?;
+            shadows.add(Shadow::new(
+                name,
+                strategy,
+                self.capital,
+                risk_limits.clone(),
+            ));
+        }
+        Ok(shadows)
+    }
+}
+
+/// One strategy paper trading the live candles. Orders are filled at the
+/// candle's close (or resting limit price) as in a backtest and never reach
+/// the exchange.
+pub struct Shadow {
+    name: String,
+    initial_capital: f64,
+    executioner: Executioner<Box<dyn Strategy>>,
+}
+
+impl Shadow {
+    pub fn new(
+        name: &str,
+        strategy: Box<dyn Strategy>,
+        initial_capital: f64,
+        mut risk_limits: RiskLimits,
+    ) -> Self {
+        // tripping its own loss limit must not halt live trading
+        risk_limits.halt_file = None;
+        let (_, candle_rx) = mpsc::channel(1);
+        let (oe_tx, _) = mpsc::channel(1);
+        let mut executioner = Executioner::new(TraderConfig {
+            initial_capital,
+            strategy,
+            symbol_strategies: HashMap::new(),
+            candle_data_rx: candle_rx,
+            order_engine_tx: oe_tx,
+            api_key: "SHADOW".to_string(),
+            api_secret: "SHADOW".to_string(),
+            initial_positions: HashMap::new(),
+            risk_limits,
+            portfolio: None,
+            shadows: None,
+            config_watch: None,
+            snapshot: None,
+        });
+        executioner.set_verbose(false);
+        executioner.set_backtesting(true);
+        Self {
+            name: name.to_string(),
+            initial_capital,
+            executioner,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Virtual capital, positions and trades so far
+    pub async fn report(&self) -> BacktestReport {
+        let equity = self.executioner.equity().await;
+        BacktestReport::from_journal(self.initial_capital, equity, self.executioner.journal())
+    }
+}
+
+/// The shadows of one live `Executioner`, with their PnL logged every
+/// `report_secs` of candle time.
+pub struct Shadows {
+    members: Vec<Shadow>,
+    report_secs: u64,
+    next_report: Option<u64>,
+}
+
+impl Shadows {
+    pub fn new(report_secs: u64) -> Self {
+        Self {
+            members: Vec::new(),
+            report_secs,
+            next_report: None,
+        }
+    }
+
+    pub fn add(&mut self, shadow: Shadow) {
+        self.members.push(shadow);
+    }
+
+    pub fn add_symbol(&mut self, symbol: &str, precision: u64) {
+        for shadow in &mut self.members {
+            shadow.executioner.add_symbol(symbol.to_string(), precision);
+        }
+    }
+
+    pub fn warm_up(&mut self, history: &[(String, Vec<Candle>)]) {
+        for shadow in &mut self.members {
+            shadow.executioner.warm_up(history);
+        }
+    }
+
+    pub async fn start(&mut self) {
+        for shadow in &mut self.members {
+            shadow.executioner.start().await;
+        }
+    }
+
+    pub async fn step(&mut self, candle_message: &CandleData) {
+        for shadow in &mut self.members {
+            shadow.executioner.step(candle_message.clone()).await;
+        }
+    }
+
+    pub async fn finish(&mut self) {
+        for shadow in &mut self.members {
+            shadow.executioner.finish().await;
+        }
+    }
+
+    /// Reloads each shadow's parameters, logging the ones that reject `new`
+    pub fn reload_config(&mut self, old: &ConfigFile, new: &ConfigFile) {
+        for shadow in &mut self.members {
+            if let Err(e) = shadow.executioner.reload_config(old, new) {
+                println!("[ERROR][SHADOW] {} kept its parameters: {}", shadow.name, e);
+            }
+        }
+    }
+
+    /// Each shadow's snapshot, by name
+    pub fn snapshot(&self) -> Result<BTreeMap<String, ExecutionerSnapshot>, SnapshotError> {
+        self.members
+            .iter()
+            .map(|shadow| Ok((shadow.name.clone(), shadow.executioner.snapshot()?)))
+            .collect()
+    }
+
+    /// Restores the shadows found in `snapshots`. A shadow that rejects its
+    /// snapshot is logged and paper trades on rather than hold back live
+    /// trading.
+    pub fn restore(&mut self, mut snapshots: BTreeMap<String, ExecutionerSnapshot>) {
+        for shadow in &mut self.members {
+            let Some(snapshot) = snapshots.remove(&shadow.name) else {
+                continue;
+            };
+            if let Err(e) = shadow.executioner.restore(snapshot) {
+                println!(
+                    "[ERROR][SHADOW] {} could not be restored: {}",
+                    shadow.name, e
+                );
+            }
+        }
+    }
+
+    /// Whether a report is due at `now`; the first one comes a full
+    /// interval after the first candle.
+    pub fn report_due(&mut self, now: u64) -> bool {
+        let next = *self.next_report.get_or_insert(now + self.report_secs);
+        if now < next {
+            return false;
+        }
+        self.next_report = Some(now + self.report_secs);
+        true
+    }
+
+    /// Each shadow's report, by name
+    pub async fn reports(&self) -> Vec<(String, BacktestReport)> {
+        let mut reports = Vec::with_capacity(self.members.len());
+        for shadow in &self.members {
+            reports.push((shadow.name.clone(), shadow.report().await));
+        }
+        reports
+    }
+
+    /// Logs the live strategy's PnL with every shadow's below it
+    pub async fn log(&self, live: &BacktestReport) {
+        log_report("live", live);
+        for (name, report) in self.reports().await {
+            log_report(&name, &report);
+        }
+    }
+}
+
+fn log_report(name: &str, report: &BacktestReport) {
+    println!(
+        "[INFO][SHADOW] {}: pnl {:.2} ({:.2}%) drawdown {:.2}% trades {} win rate {:.0}%",
+        name,
+        report.final_equity - report.initial_capital,
+        report.total_return_pct,
+        report.max_drawdown_pct,
+        report.trade_count,
+        report.win_rate * 100.0,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fourier::Candle;
+    use crate::strategy::Churn;
+
+    #[tokio::test]
+    async fn shadows_trade_virtual_capital_beside_the_live_strategy() {
+        let mut shadows = Shadows::new(60);
+        shadows.add(Shadow::new(
+            "churn",
+            Box::new(Churn::new(true)),
+            1_000.0,
+            RiskLimits::default(),
+        ));
+        let (candle_tx, candle_rx) = mpsc::channel(16);
+        let (config, _oe_rx) = TraderConfig::for_test(Churn::new(false), candle_rx);
+        let mut live = Executioner::for_test(TraderConfig {
+            shadows: Some(shadows),
+            ..config
+        });
+        for i in 1..=6u64 {
+            let price = 100.0 + i as f64;
+            let candle = Candle {
+                open_time: (i - 1) * 60_000,
+                close_time: i * 60_000,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume: 1.0,
+                trade_count: 1,
+            };
+            candle_tx
+                .send(CandleData {
+                    symbol: "BTC".to_string(),
+                    candle,
+                    quote: None,
+                })
+                .await
+                .unwrap();
+        }
+        drop(candle_tx);
+        live.run(true).await;
+
+        let report = live.report().await;
+        assert_eq!(report.trade_count, 0);
+        assert_eq!(report.final_equity, 10_000.0);
+        let shadow_reports = live.shadow_reports().await;
+        assert_eq!(shadow_reports.len(), 1);
+        let (name, shadow) = &shadow_reports[0];
+        assert_eq!(name, "churn");
+        assert_eq!(shadow.initial_capital, 1_000.0);
+        assert!(shadow.trade_count > 0);
+        assert!(shadow.final_equity != 1_000.0);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::config::{ConfigError, Validate, check_range};
+use crate::fourier::Candle;
+use crate::indicators::Indicators;
+use serde::{Deserialize, Serialize};
+
+/// Everything a sizer may look at when an entry is about to be sent.
+#[derive(Debug, Clone, Copy)]
+pub struct SizingInput<'a> {
+    pub capital: f64, // available, i.e. not reserved for other orders
+    pub price: f64,
+    pub candles: &'a [Candle],
+    pub trade_returns: &'a [f64], // recent closed trades as fractions, oldest first
+    pub open_positions: usize,    // across all symbols, excluding this entry
+}
+
+/// Turns an entry signal into a quantity in base units. Strategies apply
+/// their own hard caps (e.g. `max_notional`) on top.
+pub trait PositionSizer {
+    fn size(&self, input: &SizingInput) -> f64;
+}
+
+/// Risk `risk_fraction` of capital per ATR of adverse movement.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FixedFractional {
+    pub risk_fraction: f64,
+    pub atr_period: usize,
+}
+
+impl Default for FixedFractional {
+    fn default() -> Self {
+        Self {
+            risk_fraction: 0.02,
+            atr_period: 14,
+        }
+    }
+}
+
+impl PositionSizer for FixedFractional {
+    fn size(&self, input: &SizingInput) -> f64 {
+        let atr = Indicators::new(input.candles)
+            .atr(self.atr_period)
+            .unwrap_or(input.price * 0.01);
+        (input.capital * self.risk_fraction).max(0.0) / atr.max(1e-6)
+    }
+}
+
+/// Hold the notional whose annualised volatility equals `target_vol` of
+/// capital, e.g. 0.5 for 50% a year, at most `max_fraction` of capital.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct VolatilityTarget {
+    pub target_vol: f64,
+    pub lookback: usize, // candles of returns
+    pub max_fraction: f64,
+}
+
+impl Default for VolatilityTarget {
+    fn default() -> Self {
+        Self {
+            target_vol: 0.5,
+            lookback: 300,
+            max_fraction: 0.2,
+        }
+    }
+}
+
+impl PositionSizer for VolatilityTarget {
+    fn size(&self, input: &SizingInput) -> f64 {
+        let Some(vol) = Indicators::new(input.candles).annualised_volatility(self.lookback) else {
+            return 0.0;
+        };
+        let fraction = (self.target_vol / vol.max(1e-9)).min(self.max_fraction);
+        notional_to_quantity(input.capital * fraction, input.price)
+    }
+}
+
+/// `kelly_fraction` of the Kelly bet implied by the win rate and payoff of
+/// the last `lookback_trades` closed trades. Until `min_trades` have closed
+/// it bets `fallback_fraction` of capital.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FractionalKelly {
+    pub kelly_fraction: f64,
+    pub lookback_trades: usize,
+    pub min_trades: usize,
+    pub fallback_fraction: f64,
+    pub max_fraction: f64,
+}
+
+impl Default for FractionalKelly {
+    fn default() -> Self {
+        Self {
+            kelly_fraction: 0.5,
+            lookback_trades: 50,
+            min_trades: 10,
+            fallback_fraction: 0.05,
+            max_fraction: 0.2,
+        }
+    }
+}
+
+impl FractionalKelly {
+    /// Full Kelly fraction `p - (1 - p) / b`, None with too little history
+    pub fn kelly(&self, trade_returns: &[f64]) -> Option<f64> {
+        let recent = &trade_returns[trade_returns.len().saturating_sub(self.lookback_trades)..];
+        if recent.len() < self.min_trades.max(1) {
+            return None;
+        }
+        let wins: Vec<f64> = recent.iter().copied().filter(|r| *r > 0.0).collect();
+        let losses: Vec<f64> = recent.iter().copied().filter(|r| *r <= 0.0).collect();
+        if wins.is_empty() {
+            return Some(0.0);
+        }
+        let avg_loss = losses.iter().map(|r| -r).sum::<f64>() / losses.len().max(1) as f64;
+        if avg_loss <= 0.0 {
+            return Some(1.0);
+        }
+        let p = wins.len() as f64 / recent.len() as f64;
+        let payoff = wins.iter().sum::<f64>() / wins.len() as f64 / avg_loss;
+        Some(p - (1.0 - p) / payoff)
+    }
+}
+
+impl PositionSizer for FractionalKelly {
+    fn size(&self, input: &SizingInput) -> f64 {
+        let fraction = match self.kelly(input.trade_returns) {
+            Some(kelly) => (kelly * self.kelly_fraction).clamp(0.0, self.max_fraction),
+            None => self.fallback_fraction,
+        };
+        notional_to_quantity(input.capital * fraction, input.price)
+    }
+}
+
+/// Split an annualised risk budget of `risk_budget` times capital evenly over
+/// the open positions plus this entry, so each holds notional inversely
+/// proportional to its own volatility. Correlations are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct EqualRisk {
+    pub risk_budget: f64,
+    pub lookback: usize,
+    pub max_fraction: f64,
+}
+
+impl Default for EqualRisk {
+    fn default() -> Self {
+        Self {
+            risk_budget: 0.5,
+            lookback: 300,
+            max_fraction: 0.2,
+        }
+    }
+}
+
+impl PositionSizer for EqualRisk {
+    fn size(&self, input: &SizingInput) -> f64 {
+        let Some(vol) = Indicators::new(input.candles).annualised_volatility(self.lookback) else {
+            return 0.0;
+        };
+        let share = self.risk_budget / (input.open_positions + 1) as f64;
+        let fraction = (share / vol.max(1e-9)).min(self.max_fraction);
+        notional_to_quantity(input.capital * fraction, input.price)
+    }
+}
+
+/// Sizer chosen in configuration, e.g.
+/// ```yaml
+/// sizing:
+///   kind: volatility_target
+///   target_vol: 0.4
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Sizing {
+    FixedFractional(FixedFractional),
+    VolatilityTarget(VolatilityTarget),
+    FractionalKelly(FractionalKelly),
+    EqualRisk(EqualRisk),
+}
+
+impl Default for Sizing {
+    fn default() -> Self {
+        Sizing::FixedFractional(FixedFractional::default())
+    }
+}
+
+impl PositionSizer for Sizing {
+    fn size(&self, input: &SizingInput) -> f64 {
+        if input.price <= 0.0 || input.capital <= 0.0 {
+            return 0.0;
+        }
+        let quantity = match self {
+            Sizing::FixedFractional(sizer) => sizer.size(input),
+            Sizing::VolatilityTarget(sizer) => sizer.size(input),
+            Sizing::FractionalKelly(sizer) => sizer.size(input),
+            Sizing::EqualRisk(sizer) => sizer.size(input),
+        };
+        if quantity.is_finite() {
+            quantity.max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Validate for Sizing {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        match self {
+            Sizing::FixedFractional(s) => {
+                check_range(scope, "risk_fraction", s.risk_fraction, 0.0, 1.0)?;
+                check_range(scope, "atr_period", s.atr_period as f64, 1.0, 1024.0)
+            }
+            Sizing::VolatilityTarget(s) => {
+                check_range(scope, "target_vol", s.target_vol, 0.0, 10.0)?;
+                check_range(scope, "lookback", s.lookback as f64, 2.0, 2047.0)?;
+                check_range(scope, "max_fraction", s.max_fraction, 0.0, 1.0)
+            }
+            Sizing::FractionalKelly(s) => {
+                check_range(scope, "kelly_fraction", s.kelly_fraction, 0.0, 1.0)?;
+                check_range(
+                    scope,
+                    "lookback_trades",
+                    s.lookback_trades as f64,
+                    1.0,
+                    10_000.0,
+                )?;
+                check_range(
+                    scope,
+                    "min_trades",
+                    s.min_trades as f64,
+                    1.0,
+                    s.lookback_trades as f64,
+                )?;
+                check_range(scope, "fallback_fraction", s.fallback_fraction, 0.0, 1.0)?;
+                check_range(scope, "max_fraction", s.max_fraction, 0.0, 1.0)
+            }
+            Sizing::EqualRisk(s) => {
+                check_range(scope, "risk_budget", s.risk_budget, 0.0, 10.0)?;
+                check_range(scope, "lookback", s.lookback as f64, 2.0, 2047.0)?;
+                check_range(scope, "max_fraction", s.max_fraction, 0.0, 1.0)
+            }
+        }
+    }
+}
+
+fn notional_to_quantity(notional: f64, price: f64) -> f64 {
+    if price <= 0.0 {
+        return 0.0;
+    }
+    notional.max(0.0) / price
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kelly_follows_win_rate_and_payoff() {
+        let sizer = FractionalKelly {
+            min_trades: 4,
+            ..Default::default()
+        };
+        // 60% winners of +2%, losers of -1%: 0.6 - 0.4 / 2 = 0.4
+        let returns = [0.02, -0.01, 0.02, 0.02, -0.01];
+        assert!((sizer.kelly(&returns).unwrap() - 0.4).abs() < 1e-9);
+        assert_eq!(sizer.kelly(&returns[..3]), None);
+
+        // half Kelly of 0.4 is capped at max_fraction 0.2 of 10k at price 100
+        let input = SizingInput {
+            capital: 10_000.0,
+            price: 100.0,
+            candles: &[],
+            trade_returns: &returns,
+            open_positions: 0,
+        };
+        assert!((sizer.size(&input) - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sizing_kind_selects_sizer_from_yaml() {
+        let sizing: Sizing =
+            serde_yaml::from_str("kind: volatility_target\ntarget_vol: 0.4\n").unwrap();
+        assert_eq!(
+            sizing,
+            Sizing::VolatilityTarget(VolatilityTarget {
+                target_vol: 0.4,
+                ..Default::default()
+            })
+        );
+        assert!(serde_yaml::from_str::<Sizing>("kind: equal_risk\nbogus: 1\n").is_err());
+    }
+}
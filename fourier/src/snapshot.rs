@@ -0,0 +1,231 @@
+use crate::config::{ConfigError, ConfigFile, Validate, check_range};
+use crate::correlation::CorrelationEstimator;
+use crate::journal::Journal;
+use crate::ledger::CapitalLedger;
+use crate::order_engine::RestingOrders;
+use crate::risk::RiskState;
+use crate::strategy::ExecContext;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+/// Format of `ExecutionerSnapshot` files. Bump it whenever the snapshot or
+/// any strategy's `snapshot` state changes shape: older files are refused
+/// rather than half read.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("Could not access {0}: {1}")]
+    IoError(String, std::io::Error),
+
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Snapshot version {found} is incompatible, expected {expected}")]
+    Incompatible { found: u32, expected: u32 },
+
+    #[error("Strategy {0} rejected its state: {1}")]
+    StrategyError(String, serde_json::Error),
+
+    #[error("Shared state is locked, an order is in flight")]
+    Busy,
+}
+
+/// Periodic checkpoints of the live executioner, from the `snapshot`
+/// section:
+/// ```yaml
+/// snapshot:
+///   path: state/executioner.json
+///   interval_secs: 60
+/// ```
+/// The file is also written on shutdown and read back on the next start.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnapshotConfig {
+    pub path: String,
+    pub interval_secs: u64,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            path: "state/executioner.json".to_string(),
+            interval_secs: 60,
+        }
+    }
+}
+
+impl Validate for SnapshotConfig {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        check_range(
+            scope,
+            "interval_secs",
+            self.interval_secs as f64,
+            1.0,
+            f64::MAX,
+        )
+    }
+}
+
+impl SnapshotConfig {
+    pub const SECTION: &'static str = "snapshot";
+
+    /// Snapshots if the config file has a `snapshot` section
+    pub fn from_config(config: &ConfigFile) -> Result<Option<Self>, ConfigError> {
+        config.settings(Self::SECTION)
+    }
+}
+
+/// Everything an `Executioner` has built up while trading: the symbols'
+/// contexts, capital, resting orders, risk and correlation tracking, the
+/// journal and each strategy's own state. Restoring it into an executioner
+/// built from the same config resumes where this one left off.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExecutionerSnapshot {
+    pub version: u32,
+    pub saved_at: u64, // unix seconds
+    pub contexts: Vec<ExecContext>,
+    pub ledger: CapitalLedger,
+    pub streak: u64,
+    pub trade_returns: Vec<f64>,
+    pub open_positions: usize,
+    pub resting: RestingOrders,
+    pub risk: RiskState,
+    pub correlations: CorrelationEstimator,
+    pub journal: Journal,
+    pub timers: BTreeMap<u64, u64>, // interval -> next fire, unix seconds
+    pub next_rebalance: Option<u64>,
+    pub starting_equity: f64,
+    pub strategy: Option<serde_json::Value>,
+    pub symbol_strategies: BTreeMap<String, serde_json::Value>,
+    pub shadows: BTreeMap<String, ExecutionerSnapshot>,
+}
+
+// read first, so a file from another version is refused before its body
+// is parsed against this version's types
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+impl ExecutionerSnapshot {
+    pub fn check_version(&self) -> Result<(), SnapshotError> {
+        check_version(self.version)
+    }
+
+    /// Write to `path` through a temporary file, so a crash mid-write leaves
+    /// the previous snapshot intact
+    pub fn save(&self, path: &str) -> Result<(), SnapshotError> {
+        let target = Path::new(path);
+        if let Some(dir) = target.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| SnapshotError::IoError(dir.display().to_string(), e))?;
+        }
+        let tmp = target.with_extension("json.tmp");
+        let data = serde_json::to_vec(self)?;
+        fs::write(&tmp, data).map_err(|e| SnapshotError::IoError(tmp.display().to_string(), e))?;
+        fs::rename(&tmp, target).map_err(|e| SnapshotError::IoError(path.to_string(), e))
+    }
+
+    /// Read a snapshot written by `save`, refusing other versions
+    pub fn load(path: &str) -> Result<Self, SnapshotError> {
+        let data =
+            fs::read_to_string(path).map_err(|e| SnapshotError::IoError(path.to_string(), e))?;
+        let header: Header = serde_json::from_str(&data)?;
+        check_version(header.version)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+}
+
+fn check_version(found: u32) -> Result<(), SnapshotError> {
+    if found != SNAPSHOT_VERSION {
+        return Err(SnapshotError::Incompatible {
+            found,
+            expected: SNAPSHOT_VERSION,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fourier::Candle;
+    use crate::strategy::{CandleData, Churn, Executioner, TraderConfig};
+    use tokio::sync::mpsc;
+
+    fn executioner() -> Executioner<Churn> {
+        let (_, candle_rx) = mpsc::channel(1);
+        let (config, _) = TraderConfig::for_test(Churn::new(true), candle_rx);
+        let mut executioner = Executioner::for_test(config);
+        executioner.set_backtesting(true);
+        executioner
+    }
+
+    fn candle(i: u64) -> CandleData {
+        let price = 100.0 + (i % 3) as f64;
+        CandleData {
+            symbol: "BTC".to_string(),
+            candle: Candle {
+                open_time: (i - 1) * 60_000,
+                close_time: i * 60_000,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume: 1.0,
+                trade_count: 1,
+            },
+            quote: None,
+        }
+    }
+
+    // everything but the time it was taken
+    fn state(executioner: &Executioner<Churn>) -> serde_json::Value {
+        let mut state = serde_json::to_value(executioner.snapshot().unwrap()).unwrap();
+        state["saved_at"] = 0.into();
+        state
+    }
+
+    #[tokio::test]
+    async fn restored_executioner_resumes_where_the_snapshot_left_off() {
+        let mut original = executioner();
+        original.start().await;
+        for i in 1..=5 {
+            original.step(candle(i)).await;
+        }
+        let snapshot = original.snapshot().unwrap();
+        assert!(snapshot.strategy.as_ref().unwrap().as_u64().unwrap() > 0);
+        assert!(!snapshot.timers.is_empty());
+
+        let path = std::env::temp_dir().join(format!("fourier-snapshot-{}", std::process::id()));
+        let path = path.join("executioner.json").display().to_string();
+        snapshot.save(&path).unwrap();
+        let mut resumed = executioner();
+        resumed
+            .restore(ExecutionerSnapshot::load(&path).unwrap())
+            .unwrap();
+        assert_eq!(state(&resumed), state(&original));
+        assert_eq!(resumed.equity().await, original.equity().await);
+
+        for i in 6..=8 {
+            original.step(candle(i)).await;
+            resumed.step(candle(i)).await;
+        }
+        assert_eq!(state(&resumed), state(&original));
+
+        // a file from another version is refused before it is parsed
+        let mut newer = state(&original);
+        newer["version"] = (SNAPSHOT_VERSION + 1).into();
+        newer["contexts"] = "reshaped".into();
+        fs::write(&path, newer.to_string()).unwrap();
+        assert!(matches!(
+            ExecutionerSnapshot::load(&path),
+            Err(SnapshotError::Incompatible { found, .. }) if found == SNAPSHOT_VERSION + 1
+        ));
+        fs::remove_dir_all(Path::new(&path).parent().unwrap()).ok();
+    }
+}
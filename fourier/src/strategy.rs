@@ -1,20 +1,40 @@
-use crate::fourier::{Candle, Position};
-use crate::order_engine::OrderWithResponse;
-use crate::roostoo::{OrderDetail, OrderSide, OrderType, RoostooClient};
+use crate::backtest::BacktestReport;
+use crate::config::{ConfigError, ConfigFile, ConfigWatch, diff};
+use crate::correlation::CorrelationEstimator;
+use crate::exits::ExitPolicies;
+use crate::fourier::{Candle, Position, now_unix_secs};
+use crate::journal::{Journal, TradeRecord};
+use crate::ledger::{CapitalLedger, Reservation};
+use crate::order_engine::{EngineRequest, OrderWithResponse, RestingOrder, RestingOrders};
+use crate::portfolio::{Allocation, PortfolioConfig, rebalance_orders};
+use crate::registry;
+use crate::risk::{Exposure, RiskLimits, RiskManager};
+use crate::roostoo::{OrderDetail, OrderSide, OrderType, RoostooClient, TickerData};
+use crate::shadow::Shadows;
+use crate::snapshot::{ExecutionerSnapshot, SNAPSHOT_VERSION, SnapshotConfig, SnapshotError};
 use async_trait::async_trait;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::sync::{mpsc, oneshot};
+use tokio::time::{Duration, interval};
 
-const MAX_CANDLE_HISTORY: usize = 2048;
+pub const MAX_CANDLE_HISTORY: usize = 2048;
+const BACKTEST_FEE_RATE: f64 = 0.001;
+const BACKTEST_MAKER_FEE_RATE: f64 = 0.0005;
+// how often live resting orders are checked for fills, per symbol
+const RESTING_POLL_SECS: u64 = 5;
+const MAX_TRADE_RETURNS: usize = 500;
+// retry delay when an allocation could not be computed yet
+const ALLOCATION_RETRY_SECS: u64 = 60;
 
 pub struct Indicators {}
 
 impl Indicators {}
 
 // THis is context for a single trader
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecContext {
     pub symbol: String,
     pub candles: Vec<Candle>,
@@ -23,20 +43,119 @@ pub struct ExecContext {
     pub last_close: f64,
     pub last_signal: f64,
     pub precision: u64,
+    pub quote: Option<Quote>, // latest Roostoo top of book, when the feed carries one
+    #[serde(skip)]
+    pub verbose: bool, // the executioner's logging flag, for strategies' own logs
 }
 
 impl ExecContext {
-    fn update(&mut self, candle: Candle) {
+    // takes in a candle unless it is older than the latest held; a candle
+    // opening at the same time as the latest replaces it, as a forming kline
+    // is sent again while it fills. False when the candle was dropped.
+    fn update(&mut self, candle: Candle) -> bool {
+        match self.candles.last_mut() {
+            Some(last) if candle.close_time <= last.close_time => {
+                if candle.open_time != last.open_time {
+                    return false;
+                }
+                *last = candle;
+            }
+            _ => self.candles.push(candle),
+        }
         self.last_close = candle.close;
-        self.candles.push(candle);
+        self.position.mark(candle.close, candle.close_secs());
         if self.candles.len() > MAX_CANDLE_HISTORY {
             let drop_len = self.candles.len() - MAX_CANDLE_HISTORY;
             self.candles.drain(0..drop_len);
         }
         let _ = self.position.update_unrealized(self.last_close);
+        true
+    }
+
+    /// Time of the latest candle in unix seconds
+    pub fn now_secs(&self) -> u64 {
+        self.candles.last().map(|c| c.close_secs()).unwrap_or(0)
+    }
+
+    /// Exchange pair for this symbol, e.g. BTC/USD
+    pub fn pair(&self) -> String {
+        [self.symbol.clone(), "/USD".to_string()].concat()
+    }
+}
+
+#[cfg(test)]
+impl ExecContext {
+    /// A flat context for strategy tests, last close taken from `candles`
+    pub fn for_test(symbol: &str, candles: Vec<Candle>) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            last_close: candles.last().map(|c| c.close).unwrap_or(0.0),
+            candles,
+            position: Position::empty(symbol),
+            last_signal: 0.0,
+            precision: 3,
+            quote: None,
+            verbose: false,
+        }
+    }
+}
+
+/// Strategy for executioner tests: when enabled, buys one unit whenever flat
+/// and sells it on the next candle. Fills are counted as state to snapshot
+/// and a 120 second timer is kept.
+#[cfg(test)]
+pub struct Churn {
+    pub enabled: bool,
+    pub fills: u64,
+}
+
+#[cfg(test)]
+impl Churn {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, fills: 0 }
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl Strategy for Churn {
+    async fn should_long(&self, ctx: &mut ExecContext, _: Arc<Mutex<SharedState>>) -> bool {
+        self.enabled && !ctx.position.is_open()
+    }
+
+    async fn go_long(&self, ctx: &ExecContext, _: Arc<Mutex<SharedState>>) -> Option<Order> {
+        Some(Order {
+            pair: ctx.pair(),
+            side: OrderSide::Buy,
+            order_type: OrderType::Market,
+            quantity: 1.0,
+            price: None,
+        })
+    }
+
+    async fn update_position(&self, _: &mut ExecContext, _: Arc<Mutex<SharedState>>) -> bool {
+        true
+    }
+
+    async fn on_fill(&mut self, _: &ExecContext, _: &OrderDetail, _: Arc<Mutex<SharedState>>) {
+        self.fills += 1;
+    }
+
+    fn timers(&self) -> Vec<u64> {
+        vec![120]
+    }
+
+    fn snapshot(&self) -> Option<serde_json::Value> {
+        Some(self.fills.into())
+    }
+
+    fn restore(&mut self, state: serde_json::Value) -> Result<(), serde_json::Error> {
+        self.fills = serde_json::from_value(state)?;
+        Ok(())
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct Order {
     pub pair: String,
     pub side: OrderSide,
@@ -46,8 +165,22 @@ pub struct Order {
     // pub response: Option<mpsc::Receiver<PlaceOrderResponse>>
 }
 
+/// A change to the resting limit orders of one symbol, from `manage_orders`.
+#[derive(Debug, Clone)]
+pub enum OrderIntent {
+    /// Rest a limit order; `price` must be set.
+    Place(Order),
+    /// Cancel the resting order with this id.
+    Cancel(u64),
+}
+
+/// Trading logic plugged into the `Executioner`.
+///
+/// `should_long`, `go_long` and `update_position` are evaluated on every candle.
+/// The remaining methods are lifecycle hooks with empty defaults, so strategies
+/// only implement the ones they need to keep their own state.
 #[async_trait]
-pub trait Strategy {
+pub trait Strategy: Send + Sync {
     async fn should_long(
         &self,
         ctx: &mut ExecContext,
@@ -60,58 +193,557 @@ pub trait Strategy {
     ) -> Option<Order>;
     async fn update_position(
         &self,
-        ctx: &ExecContext,
+        ctx: &mut ExecContext,
         shared_state: Arc<Mutex<SharedState>>,
     ) -> bool;
+
+    /// Called once before the first candle with the positions bootstrapped
+    /// from the exchange wallet (symbol -> quantity).
+    async fn on_start(
+        &mut self,
+        _positions: &HashMap<String, f64>,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) {
+    }
+
+    /// Called after a fill has been applied to `ctx.position`.
+    async fn on_fill(
+        &mut self,
+        _ctx: &ExecContext,
+        _fill: &OrderDetail,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) {
+    }
+
+    /// Called when an order for `ctx` was rejected or never got a fill back.
+    async fn on_reject(
+        &mut self,
+        _ctx: &ExecContext,
+        _order: &Order,
+        _reason: &str,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) {
+    }
+
+    /// Intervals in seconds at which `on_timer` should fire.
+    fn timers(&self) -> Vec<u64> {
+        Vec::new()
+    }
+
+    /// Called for each interval from `timers` once it elapses. `now` is unix
+    /// seconds: wall clock when live, candle time when backtesting.
+    async fn on_timer(
+        &mut self,
+        _interval_secs: u64,
+        _now: u64,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) {
+    }
+
+    /// Seconds between `target_weights` calls; 0 (the default) never calls it.
+    fn allocation_secs(&self) -> u64 {
+        0
+    }
+
+    /// Cross-symbol view for strategies that allocate across the universe.
+    /// Sees every symbol's context at once; the returned allocation is traded
+    /// towards through the risk stage. None means not ready yet and is asked
+    /// again sooner; Some(None) leaves positions as they are until the next
+    /// `allocation_secs`.
+    async fn target_weights(
+        &mut self,
+        _contexts: &HashMap<String, ExecContext>,
+        _now: u64,
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> Option<Option<Allocation>> {
+        None
+    }
+
+    /// Called on the `allocation_secs` schedule while `Executioner::warm_up`
+    /// replays history, with the contexts `target_weights` would see, so
+    /// series the strategy samples itself are filled before trading starts.
+    /// Nothing is traded.
+    fn warm_up(&mut self, _contexts: &HashMap<String, ExecContext>, _now: u64) {}
+
+    /// Stops and other protective exits `update_position` applies to
+    /// `symbol`. Strategies combining others close a position as soon as any
+    /// member's protective exit triggers, instead of putting it to a vote.
+    fn protective_exits(&self, _symbol: &str) -> Option<&ExitPolicies> {
+        None
+    }
+
+    /// Limit orders for `ctx`, called on every candle after the entry and exit
+    /// signals with the orders already resting for the symbol. Fills of
+    /// resting orders are applied to `ctx.position` and reported to `on_fill`.
+    async fn manage_orders(
+        &mut self,
+        _ctx: &ExecContext,
+        _resting: &[RestingOrder],
+        _shared_state: Arc<Mutex<SharedState>>,
+    ) -> Vec<OrderIntent> {
+        Vec::new()
+    }
+
+    /// Called once after the candle feed closes.
+    async fn on_stop(&mut self, _shared_state: Arc<Mutex<SharedState>>) {}
+
+    /// Swap in parameters from an edited config file. Positions, resting
+    /// orders and any state the strategy has built up stay as they are; on
+    /// error the strategy must be left unchanged.
+    fn reload(&mut self, _config: &ConfigFile) -> Result<(), ConfigError> {
+        Ok(())
+    }
+
+    /// State built up while trading that must survive a restart, for
+    /// executioner snapshots. None when everything the strategy needs lives
+    /// in the contexts.
+    fn snapshot(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Take back the state returned by `snapshot`; on error the strategy
+    /// must be left unchanged.
+    fn restore(&mut self, _state: serde_json::Value) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
 }
 
+// lets a strategy picked at runtime, e.g. by `registry::build`, drive an
+// `Executioner` or sit inside another strategy
+#[async_trait]
+impl Strategy for Box<dyn Strategy> {
+    async fn should_long(
+        &self,
+        ctx: &mut ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        self.as_ref().should_long(ctx, shared_state).await
+    }
+
+    async fn go_long(
+        &self,
+        ctx: &ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> Option<Order> {
+        self.as_ref().go_long(ctx, shared_state).await
+    }
+
+    async fn update_position(
+        &self,
+        ctx: &mut ExecContext,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> bool {
+        self.as_ref().update_position(ctx, shared_state).await
+    }
+
+    async fn on_start(
+        &mut self,
+        positions: &HashMap<String, f64>,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) {
+        self.as_mut().on_start(positions, shared_state).await
+    }
+
+    async fn on_fill(
+        &mut self,
+        ctx: &ExecContext,
+        fill: &OrderDetail,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) {
+        self.as_mut().on_fill(ctx, fill, shared_state).await
+    }
+
+    async fn on_reject(
+        &mut self,
+        ctx: &ExecContext,
+        order: &Order,
+        reason: &str,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) {
+        self.as_mut()
+            .on_reject(ctx, order, reason, shared_state)
+            .await
+    }
+
+    fn timers(&self) -> Vec<u64> {
+        self.as_ref().timers()
+    }
+
+    async fn on_timer(
+        &mut self,
+        interval_secs: u64,
+        now: u64,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) {
+        self.as_mut()
+            .on_timer(interval_secs, now, shared_state)
+            .await
+    }
+
+    fn allocation_secs(&self) -> u64 {
+        self.as_ref().allocation_secs()
+    }
+
+    async fn target_weights(
+        &mut self,
+        contexts: &HashMap<String, ExecContext>,
+        now: u64,
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> Option<Option<Allocation>> {
+        self.as_mut()
+            .target_weights(contexts, now, shared_state)
+            .await
+    }
+
+    fn warm_up(&mut self, contexts: &HashMap<String, ExecContext>, now: u64) {
+        self.as_mut().warm_up(contexts, now)
+    }
+
+    fn protective_exits(&self, symbol: &str) -> Option<&ExitPolicies> {
+        self.as_ref().protective_exits(symbol)
+    }
+
+    async fn manage_orders(
+        &mut self,
+        ctx: &ExecContext,
+        resting: &[RestingOrder],
+        shared_state: Arc<Mutex<SharedState>>,
+    ) -> Vec<OrderIntent> {
+        self.as_mut()
+            .manage_orders(ctx, resting, shared_state)
+            .await
+    }
+
+    async fn on_stop(&mut self, shared_state: Arc<Mutex<SharedState>>) {
+        self.as_mut().on_stop(shared_state).await
+    }
+
+    fn reload(&mut self, config: &ConfigFile) -> Result<(), ConfigError> {
+        self.as_mut().reload(config)
+    }
+
+    fn snapshot(&self) -> Option<serde_json::Value> {
+        self.as_ref().snapshot()
+    }
+
+    fn restore(&mut self, state: serde_json::Value) -> Result<(), serde_json::Error> {
+        self.as_mut().restore(state)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct CandleData {
     pub symbol: String,
     pub candle: Candle,
+    pub quote: Option<Quote>,
+}
+
+/// Roostoo's best bid and ask for one symbol, as polled from its ticker or
+/// replayed from a recording. Candles come from Binance, so `last_close`
+/// against `mid()` is how far Roostoo lags the lead market.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Quote {
+    pub time: u64, // unix seconds
+    pub bid: f64,
+    pub ask: f64,
+    pub last: f64,
+}
+
+impl Quote {
+    pub fn from_ticker(time: u64, ticker: &TickerData) -> Self {
+        Quote {
+            time,
+            bid: ticker.max_bid,
+            ask: ticker.min_ask,
+            last: ticker.last_price,
+        }
+    }
+
+    pub fn mid(&self) -> f64 {
+        (self.bid + self.ask) / 2.0
+    }
+
+    /// Spread in basis points of the mid; None for a one-sided or crossed book
+    pub fn spread_bps(&self) -> Option<f64> {
+        (self.bid > 0.0 && self.ask > self.bid)
+            .then(|| (self.ask - self.bid) / self.mid() * 10_000.0)
+    }
 }
 
 // This is for hared state of ALL crypto traders
 pub struct SharedState {
-    pub capital: f64,
-    pub streak: u64,
+    pub ledger: CapitalLedger,
+    pub streak: u64,                  // consecutive losing trades, reset by a win
+    pub trade_returns: VecDeque<f64>, // recent closed trades as fractions, oldest first
+    pub open_positions: usize,
+}
+
+impl SharedState {
+    pub fn new(capital: f64) -> Self {
+        Self {
+            ledger: CapitalLedger::new(capital),
+            streak: 0,
+            trade_returns: VecDeque::new(),
+            open_positions: 0,
+        }
+    }
+
+    fn record_trade_return(&mut self, trade_return: f64) {
+        self.trade_returns.push_back(trade_return);
+        if self.trade_returns.len() > MAX_TRADE_RETURNS {
+            self.trade_returns.pop_front();
+        }
+    }
+
+    /// USD not yet promised to in-flight orders; size new entries from this
+    pub fn available_capital(&self) -> f64 {
+        self.ledger.available()
+    }
+}
+
+// the executioner's strategy plus the symbols assigned their own
+struct Strategies<T> {
+    default: T,
+    by_symbol: BTreeMap<String, Box<dyn Strategy>>,
+}
+
+impl<T: Strategy> Strategies<T> {
+    fn get(&self, symbol: &str) -> &dyn Strategy {
+        match self.by_symbol.get(symbol) {
+            Some(strategy) => strategy.as_ref(),
+            None => &self.default,
+        }
+    }
+
+    fn get_mut(&mut self, symbol: &str) -> &mut dyn Strategy {
+        match self.by_symbol.get_mut(symbol) {
+            Some(strategy) => strategy.as_mut(),
+            None => &mut self.default,
+        }
+    }
+
+    // every distinct strategy, for lifecycle hooks
+    fn all_mut(&mut self) -> Vec<&mut dyn Strategy> {
+        let mut all: Vec<&mut dyn Strategy> = vec![&mut self.default];
+        all.extend(
+            self.by_symbol
+                .values_mut()
+                .map(|strategy| strategy.as_mut() as &mut dyn Strategy),
+        );
+        all
+    }
+
+    // union of every strategy's timer intervals
+    fn timers(&self) -> Vec<u64> {
+        let mut timers: Vec<u64> = std::iter::once(&self.default as &dyn Strategy)
+            .chain(self.by_symbol.values().map(|strategy| strategy.as_ref()))
+            .flat_map(|strategy| strategy.timers())
+            .filter(|secs| *secs > 0)
+            .collect();
+        timers.sort_unstable();
+        timers.dedup();
+        timers
+    }
+}
+
+struct StrategyTimer {
+    interval_secs: u64,
+    next_fire: Option<u64>,
 }
 
 pub struct Executioner<T: Strategy + Send> {
     cryptos: HashMap<String, ExecContext>, // crypt -> context
     shared_state: Arc<Mutex<SharedState>>,
-    strategy: T,
-    order_engine: mpsc::Sender<OrderWithResponse>,
+    strategy: Strategies<T>,
+    order_engine: mpsc::Sender<EngineRequest>,
+    resting: RestingOrders,
+    next_poll: HashMap<String, u64>, // when each symbol's resting orders are next polled
     candle_input: mpsc::Receiver<CandleData>,
     client: RoostooClient,
     bootstrap_positions: HashMap<String, f64>,
+    timers: Vec<StrategyTimer>,
+    risk: RiskManager,
+    correlations: CorrelationEstimator,
+    portfolio: Option<PortfolioConfig>,
+    next_rebalance: Option<u64>,
+    backtesting: bool,
+    verbose: bool,
+    flat_at_end: bool, // sell everything held in `finish`
+    index: usize,
+    journal: Journal,
+    starting_equity: f64, // capital plus bootstrapped positions, for PnL
+    shadows: Option<Shadows>,
+    config_watch: Option<ConfigWatch>,
+    snapshot_config: Option<SnapshotConfig>,
+    next_snapshot: Option<u64>,
 }
 
 // a.rs
 pub struct TraderConfig<T: Strategy + Send> {
     pub initial_capital: f64,
     pub strategy: T,
+    // symbols that trade their own strategy instead of `strategy`
+    pub symbol_strategies: HashMap<String, Box<dyn Strategy>>,
     pub candle_data_rx: mpsc::Receiver<CandleData>,
-    pub order_engine_tx: mpsc::Sender<OrderWithResponse>,
+    pub order_engine_tx: mpsc::Sender<EngineRequest>,
     pub api_key: String,
     pub api_secret: String,
     pub initial_positions: HashMap<String, f64>,
+    pub risk_limits: RiskLimits,
+    pub portfolio: Option<PortfolioConfig>, // target-weight mode instead of entry signals
+    pub shadows: Option<Shadows>,           // paper traded alongside for comparison
+    pub config_watch: Option<ConfigWatch>,  // file to reload parameters from when live
+    pub snapshot: Option<SnapshotConfig>,   // where to checkpoint state when live
+}
+
+#[cfg(test)]
+impl<T: Strategy + Send> TraderConfig<T> {
+    /// Config for executioner tests: 10,000 of capital, default limits and
+    /// nothing optional. The order engine's end of the channel is returned
+    /// so live orders can be answered, or dropped to refuse them.
+    pub fn for_test(
+        strategy: T,
+        candle_data_rx: mpsc::Receiver<CandleData>,
+    ) -> (Self, mpsc::Receiver<EngineRequest>) {
+        let (order_engine_tx, order_engine_rx) = mpsc::channel(1);
+        let config = Self {
+            initial_capital: 10_000.0,
+            strategy,
+            symbol_strategies: HashMap::new(),
+            candle_data_rx,
+            order_engine_tx,
+            api_key: "TEST".to_string(),
+            api_secret: "TEST".to_string(),
+            initial_positions: HashMap::new(),
+            risk_limits: RiskLimits::default(),
+            portfolio: None,
+            shadows: None,
+            config_watch: None,
+            snapshot: None,
+        };
+        (config, order_engine_rx)
+    }
+}
+
+#[cfg(test)]
+impl<T: Strategy + Send> Executioner<T> {
+    /// A quiet executioner trading BTC
+    pub fn for_test(config: TraderConfig<T>) -> Self {
+        let mut executioner = Self::new(config);
+        executioner.set_verbose(false);
+        executioner.add_symbol("BTC".to_string(), 3);
+        executioner
+    }
 }
 
 impl<T: Strategy + Send> Executioner<T> {
     pub fn new(config: TraderConfig<T>) -> Self {
         // TODO: read positions from cache
-        return Self {
+        let strategy = Strategies {
+            default: config.strategy,
+            by_symbol: config.symbol_strategies.into_iter().collect(),
+        };
+        let timers = strategy
+            .timers()
+            .into_iter()
+            .map(|interval_secs| StrategyTimer {
+                interval_secs,
+                next_fire: None,
+            })
+            .collect();
+        Self {
             cryptos: HashMap::new(),
-            shared_state: Arc::new(Mutex::new(SharedState {
-                capital: config.initial_capital,
-                streak: 0,
-            })),
-            strategy: config.strategy,
+            shared_state: Arc::new(Mutex::new(SharedState::new(config.initial_capital))),
+            strategy,
             order_engine: config.order_engine_tx,
+            resting: RestingOrders::default(),
+            next_poll: HashMap::new(),
             candle_input: config.candle_data_rx,
             client: RoostooClient::new(config.api_key, config.api_secret),
             bootstrap_positions: config.initial_positions,
-        };
+            timers,
+            correlations: config.risk_limits.correlation.estimator(),
+            risk: RiskManager::new(config.risk_limits),
+            portfolio: config.portfolio,
+            next_rebalance: None,
+            backtesting: false,
+            verbose: true,
+            flat_at_end: false,
+            index: 0,
+            journal: Journal::default(),
+            starting_equity: config.initial_capital,
+            shadows: config.shadows,
+            config_watch: config.config_watch,
+            snapshot_config: config.snapshot,
+            next_snapshot: None,
+        }
+    }
+
+    /// Per-candle and per-fill logging; backtest sweeps turn it off.
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+        for ctx in self.cryptos.values_mut() {
+            ctx.verbose = verbose;
+        }
+    }
+
+    /// Simulate fills instead of sending orders, as in a backtest. `run`
+    /// sets this itself; it is for executioners driven through `step`.
+    pub fn set_backtesting(&mut self, backtesting: bool) {
+        self.backtesting = backtesting;
+    }
+
+    /// Sell every open position at its last close in `finish`, fees
+    /// included, so the final equity is all cash; for backtests whose
+    /// capital is carried on as cash.
+    pub fn set_flat_at_end(&mut self, flat_at_end: bool) {
+        self.flat_at_end = flat_at_end;
+    }
+
+    /// Trades and equity curve recorded so far
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    /// Capital plus all open positions marked at their last close
+    pub async fn equity(&self) -> f64 {
+        let capital = self.shared_state.lock().await.ledger.balance();
+        capital
+            + self
+                .cryptos
+                .values()
+                .map(|ctx| ctx.position.notional(ctx.last_close))
+                .sum::<f64>()
+    }
+
+    /// PnL since the start, measured from capital plus any positions found
+    /// on the exchange at startup
+    pub async fn report(&self) -> BacktestReport {
+        let equity = self.equity().await;
+        BacktestReport::from_journal(self.starting_equity, equity, &self.journal)
+    }
+
+    /// Each shadow strategy's PnL, by name
+    pub async fn shadow_reports(&self) -> Vec<(String, BacktestReport)> {
+        match &self.shadows {
+            Some(shadows) => shadows.reports().await,
+            None => Vec::new(),
+        }
+    }
+
+    /// Limit orders currently resting, by symbol
+    pub fn resting_orders(&self) -> &RestingOrders {
+        &self.resting
+    }
+
+    /// Rolling return statistics across all symbols
+    pub fn correlations(&self) -> &CorrelationEstimator {
+        &self.correlations
+    }
+
+    /// Lift the daily loss kill switch so the strategy may enter again.
+    pub fn reset_kill_switch(&mut self) {
+        self.risk.reset();
     }
 
     pub fn add_symbol(&mut self, symbol: String, precision: u64) {
@@ -122,178 +754,1260 @@ impl<T: Strategy + Send> Executioner<T> {
             position: Position::empty(symbol.clone()),
             last_close: 0.0,
             last_signal: 0.0,
-            precision: precision,
+            precision,
+            quote: None,
+            verbose: self.verbose,
         };
 
+        if let Some(shadows) = &mut self.shadows {
+            shadows.add_symbol(&symbol, precision);
+        }
         self.cryptos.insert(symbol.clone(), exectx);
     }
 
-    pub async fn run(&mut self, backtesting: bool) {
-        let mut index: usize = 0;
-        while let Some(candle_message) = self.candle_input.recv().await {
-            let l = self.cryptos.len();
-            let mut ctx = match self.cryptos.remove(&candle_message.symbol) {
-                None => continue,
-                Some(c) => c,
-            };
-            ctx.update(candle_message.candle);
-            if let Some(qty) = self.bootstrap_positions.remove(&ctx.symbol) {
-                if qty > 0.0 && !ctx.position.is_open() && ctx.last_close > 0.0 {
-                    if let Err(err) = ctx.position.add_fill(qty, ctx.last_close, 0.0, None) {
-                        println!(
-                            "[ERROR][BOOTSTRAP] Failed to seed {} with {} units: {}",
-                            ctx.symbol, qty, err
-                        );
-                    } else {
-                        println!(
-                            "[INFO][BOOTSTRAP] Restored {} with existing position of {} units",
-                            ctx.symbol, qty
-                        );
-                    }
-                }
+    /// Like `add_symbol`, but `symbol` trades `strategy` instead of the
+    /// executioner's own. Capital, risk limits and the order engine stay
+    /// shared; cross-symbol allocation stays with the executioner's strategy.
+    pub fn add_symbol_with(&mut self, symbol: String, precision: u64, strategy: Box<dyn Strategy>) {
+        self.strategy.by_symbol.insert(symbol.clone(), strategy);
+        for interval_secs in self.strategy.timers() {
+            if !self.timers.iter().any(|t| t.interval_secs == interval_secs) {
+                self.timers.push(StrategyTimer {
+                    interval_secs,
+                    next_fire: None,
+                });
             }
-            index += 1;
+        }
+        self.add_symbol(symbol, precision);
+    }
 
-            let capital: f64;
-            {
-                let guard = self.shared_state.lock().await;
-                capital = guard.capital;
-            }
-            println!(
-                "[{}] Capital: {} Holding: {}",
-                ctx.symbol,
-                capital,
-                ctx.position.quantity * ctx.last_close,
-            );
+    /// `add_symbol_with` the strategy named `name`, e.g. `bollinger`, built
+    /// from its section of `config`
+    pub fn add_symbol_named(
+        &mut self,
+        symbol: String,
+        precision: u64,
+        name: &str,
+        config: &ConfigFile,
+    ) -> Result<(), ConfigError> {
+        let strategy = registry::build_for_symbol(name, config)?;
+        self.add_symbol_with(symbol, precision, strategy);
+        Ok(())
+    }
 
-            // just liquidated position for this ctx
-            if ctx.position.is_open()
-                && self
-                    .strategy
-                    .update_position(&ctx, self.shared_state.clone())
-                    .await
-            {
-                if backtesting {
-                    let price = ctx.last_close;
-                    let qty = ctx.position.quantity;
-                    let fees = 0.001 * price * qty;
-                    if let Err(err) = ctx.position.close_all(price, fees) {
-                        println!("[ERROR][BACKTEST] Failed to close position: {}", err);
-                    }
-                    {
-                        let mut guard = self.shared_state.lock().await;
-                        guard.capital += qty * price - fees;
-                    }
-                } else {
-                    let order = Order {
-                        pair: [ctx.symbol.clone(), "/USD".to_string()].concat(),
-                        side: OrderSide::Sell,
-                        order_type: OrderType::Market,
-                        quantity: ctx.position.quantity,
-                        price: None,
-                    };
-                    let (tx, rx) = oneshot::channel();
+    pub async fn run(&mut self, backtesting: bool) {
+        self.backtesting = backtesting;
+        self.start().await;
+        if let Some(shadows) = &mut self.shadows {
+            shadows.start().await;
+        }
 
-                    let orderwithresponse = OrderWithResponse {
-                        order: order,
-                        precision: ctx.precision,
-                        response: tx,
-                    };
-                    if let Err(e) = self.order_engine.send(orderwithresponse).await {
-                        println!("[ERROR][ORDERENGINE] Failed to dispatch close order: {}", e);
-                    } else {
-                        match rx.await {
-                            Ok(order_detail) => {
-                                if let Some((qty, price, fee)) = self.sync(Some(order_detail)).await
-                                {
-                                    if let Err(err) = ctx.position.reduce(qty, price, fee) {
-                                        println!("[ERROR][POSITION] Reduce failed: {}", err);
-                                    }
-                                } else {
-                                    println!(
-                                        "[ERROR][UPDATEPOSITION] Sync returned no fill details"
-                                    );
-                                }
-                            }
-                            Err(e) => {
-                                println!("[ERROR][ORDERENGINE] Could not receive fill: {}", e);
-                            }
+        if backtesting {
+            // timers follow candle time so backtests stay deterministic
+            while let Some(candle_message) = self.candle_input.recv().await {
+                let shadowed = self.shadows.is_some().then(|| candle_message.clone());
+                self.step(candle_message).await;
+                self.shadow(shadowed).await;
+            }
+        } else {
+            let mut clock = interval(Duration::from_secs(1));
+            loop {
+                tokio::select! {
+                    message = self.candle_input.recv() => match message {
+                        Some(candle_message) => {
+                            let shadowed = self.shadows.is_some().then(|| candle_message.clone());
+                            self.handle_candle(candle_message).await;
+                            self.shadow(shadowed).await;
                         }
+                        None => break,
+                    },
+                    _ = clock.tick() => {
+                        let now = now_unix_secs();
+                        self.watch_config(now);
+                        self.fire_timers(now).await;
+                        self.rebalance_portfolio(now).await;
+                        self.checkpoint(now, false);
+                    }
+                    _ = tokio::signal::ctrl_c() => {
+                        println!("[INFO][EXECUTIONER] Interrupted, shutting down");
+                        break;
                     }
                 }
             }
+        }
 
-            if self
-                .strategy
-                .should_long(&mut ctx, self.shared_state.clone())
-                .await
+        self.checkpoint(now_unix_secs(), true);
+        self.finish().await;
+        if let Some(mut shadows) = self.shadows.take() {
+            shadows.finish().await;
+            shadows.log(&self.report().await).await;
+            self.shadows = Some(shadows);
+        }
+    }
+
+    /// Load history into the symbols' contexts before the first live
+    /// candle, so indicators are warm and strategies can act on it. Candles
+    /// are replayed in close time order across symbols, without trading;
+    /// ones a restored snapshot already holds are skipped. A strategy
+    /// allocating across symbols has its `warm_up` called on its schedule.
+    pub fn warm_up(&mut self, history: &[(String, Vec<Candle>)]) {
+        let mut candles: Vec<(&str, &Candle)> = history
+            .iter()
+            .filter_map(|(symbol, candles)| {
+                let latest = self.cryptos.get(symbol)?.now_secs();
+                Some(
+                    candles
+                        .iter()
+                        .filter(move |c| c.close_secs() > latest)
+                        .map(move |c| (symbol.as_str(), c)),
+                )
+            })
+            .flatten()
+            .collect();
+        candles.sort_by_key(|(_, candle)| candle.close_secs());
+        let interval = match &self.portfolio {
+            Some(_) => 0,
+            None => self.strategy.default.allocation_secs(),
+        };
+        // the strategy's own clock; the first live allocation is not delayed
+        let mut next_sample = 0;
+        for (symbol, candle) in candles {
+            let now = candle.close_secs();
+            if let Some(ctx) = self.cryptos.get_mut(symbol)
+                && ctx.update(*candle)
             {
-                if let Some(order) = self.strategy.go_long(&ctx, self.shared_state.clone()).await {
-                    if backtesting {
-                        let qty = order.quantity;
-                        let price = ctx.last_close;
-                        let fee = qty * price * 0.001;
-                        if let Err(err) = ctx.position.add_fill(qty, price, fee, None) {
-                            println!("[ERROR][BACKTEST] Unable to add fill: {}", err);
-                        }
-                        {
-                            let mut guard = self.shared_state.lock().await;
-                            guard.capital -= qty * price + fee;
-                        }
-                    } else {
-                        let (tx, rx) = oneshot::channel();
-                        let orderwithresponse = OrderWithResponse {
-                            order: order,
-                            precision: ctx.precision,
-                            response: tx,
-                        };
-                        if let Err(e) = self.order_engine.send(orderwithresponse).await {
-                            println!("[ERROR][ORDERENGINE] Failed to dispatch open order: {}", e);
-                        } else {
-                            // update local stuff
-                            match rx.await {
-                                // hopefully instant?
-                                Ok(order_detail) => match self.sync(Some(order_detail)).await {
-                                    Some((qty, price, fee)) => {
-                                        if let Err(err) =
-                                            ctx.position.add_fill(qty, price, fee, None)
-                                        {
-                                            println!(
-                                                "[ERROR][POSITION] Failed to register fill: {}",
-                                                err
-                                            );
-                                        }
-                                    }
-                                    None => {
-                                        println!(
-                                            "[ERROR][UPDATEPOSITION] Sync returned no fill data"
-                                        );
-                                    }
-                                },
-                                Err(e) => {
-                                    println!(
-                                        "Could not receive data from OrderEngine oneshot: {}",
-                                        e
-                                    );
-                                }
-                            }
-                        }
-                    }
-                }
+                self.correlations.update(symbol, now, candle.close);
+            }
+            if interval > 0 && now >= next_sample {
+                let assigned = self.take_assigned();
+                self.strategy.default.warm_up(&self.cryptos, now);
+                self.cryptos.extend(assigned);
+                next_sample = now + interval;
             }
+        }
+        if let Some(shadows) = &mut self.shadows {
+            shadows.warm_up(history);
+        }
+    }
 
-            self.cryptos.insert(candle_message.symbol, ctx);
+    /// Runs the strategies' `on_start`; with `step` and `finish` this drives
+    /// the executioner by hand instead of through `run`.
+    pub async fn start(&mut self) {
+        for strategy in self.strategy.all_mut() {
+            strategy
+                .on_start(&self.bootstrap_positions, self.shared_state.clone())
+                .await;
+        }
+    }
 
-            // periodic wallet sync cause floating point is gay
-            if l > 0 && index % (l * 15) == 0 && !backtesting {
-                self.sync(None).await;
-            }
+    /// Handles one candle, then fires timers and rebalances at its close time
+    pub async fn step(&mut self, candle_message: CandleData) {
+        let now = candle_message.candle.close_secs();
+        self.handle_candle(candle_message).await;
+        self.fire_timers(now).await;
+        self.rebalance_portfolio(now).await;
+    }
+
+    /// Records the final equity and runs the strategies' `on_stop`, closing
+    /// every position first when set to end flat
+    pub async fn finish(&mut self) {
+        if self.flat_at_end {
+            self.close_all().await;
+        }
+        if let Some(now) = self.cryptos.values().map(|ctx| ctx.now_secs()).max() {
+            let equity = self.equity().await;
+            self.journal.record_equity(now, equity);
+        }
+        for strategy in self.strategy.all_mut() {
+            strategy.on_stop(self.shared_state.clone()).await;
         }
     }
 
-    // if argument to details None, sync capital. if given order, return qty,price
-    // ONLY UPDATES CAPTAL, NOT POSITION
-    async fn sync(&self, details: Option<OrderDetail>) -> Option<(f64, f64, f64)> {
+    /// Swap in strategy parameters and risk limits from `new`. The risk
+    /// section is checked first, then each strategy reloads in turn; when one
+    /// refuses, those already reloaded are rolled back to `old`, the config
+    /// they were built from, and the limits stay as they were. Positions,
+    /// resting orders and strategy state are kept, and correlations are
+    /// resampled from the candles held if their sampling changed.
+    pub fn reload_config(&mut self, old: &ConfigFile, new: &ConfigFile) -> Result<(), ConfigError> {
+        let limits = RiskLimits::from_config(new)?;
+        let mut reloaded = 0;
+        let mut result = Ok(());
+        for strategy in self.strategy.all_mut() {
+            result = strategy.reload(new);
+            if result.is_err() {
+                break;
+            }
+            reloaded += 1;
+        }
+        if let Err(e) = result {
+            for strategy in self.strategy.all_mut().into_iter().take(reloaded) {
+                if let Err(e) = strategy.reload(old) {
+                    println!("[ERROR][CONFIG] Failed to roll back a strategy: {}", e);
+                }
+            }
+            return Err(e);
+        }
+        let sampling =
+            |limits: &RiskLimits| (limits.correlation.sample_secs, limits.correlation.window);
+        let resample = sampling(&limits) != sampling(self.risk.limits());
+        self.risk.set_limits(limits);
+        if resample {
+            self.resample_correlations();
+        }
+        if let Some(shadows) = &mut self.shadows {
+            shadows.reload_config(old, new);
+        }
+        Ok(())
+    }
+
+    /// Everything needed to resume trading after a restart. Taken between
+    /// candles, when no order is in flight; shadows are included.
+    pub fn snapshot(&self) -> Result<ExecutionerSnapshot, SnapshotError> {
+        let shared = self
+            .shared_state
+            .try_lock()
+            .map_err(|_| SnapshotError::Busy)?;
+        let mut contexts: Vec<ExecContext> = self.cryptos.values().cloned().collect();
+        contexts.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        Ok(ExecutionerSnapshot {
+            version: SNAPSHOT_VERSION,
+            saved_at: now_unix_secs(),
+            contexts,
+            ledger: shared.ledger.clone(),
+            streak: shared.streak,
+            trade_returns: shared.trade_returns.iter().copied().collect(),
+            open_positions: shared.open_positions,
+            resting: self.resting.clone(),
+            risk: self.risk.state(),
+            correlations: self.correlations.clone(),
+            journal: self.journal.clone(),
+            timers: self
+                .timers
+                .iter()
+                .filter_map(|timer| Some((timer.interval_secs, timer.next_fire?)))
+                .collect(),
+            next_rebalance: self.next_rebalance,
+            starting_equity: self.starting_equity,
+            strategy: self.strategy.default.snapshot(),
+            symbol_strategies: self
+                .strategy
+                .by_symbol
+                .iter()
+                .filter_map(|(symbol, strategy)| Some((symbol.clone(), strategy.snapshot()?)))
+                .collect(),
+            shadows: match &self.shadows {
+                Some(shadows) => shadows.snapshot()?,
+                None => BTreeMap::new(),
+            },
+        })
+    }
+
+    /// Resume from `snapshot` before the first candle. Call it after
+    /// `add_symbol`: contexts are restored for the symbols traded now, with
+    /// their current precision, and strategies assigned to symbols by the
+    /// config get their state back. On error the executioner may be partly
+    /// restored and should not trade.
+    pub fn restore(&mut self, snapshot: ExecutionerSnapshot) -> Result<(), SnapshotError> {
+        snapshot.check_version()?;
+        let shared_state = self.shared_state.clone();
+        let mut shared = shared_state.try_lock().map_err(|_| SnapshotError::Busy)?;
+        if let Some(state) = snapshot.strategy {
+            self.strategy
+                .default
+                .restore(state)
+                .map_err(|e| SnapshotError::StrategyError("default".to_string(), e))?;
+        }
+        for (symbol, state) in snapshot.symbol_strategies {
+            match self.strategy.by_symbol.get_mut(&symbol) {
+                Some(strategy) => strategy
+                    .restore(state)
+                    .map_err(|e| SnapshotError::StrategyError(symbol, e))?,
+                None => println!(
+                    "[WARN][SNAPSHOT] {} no longer trades its own strategy, state dropped",
+                    symbol
+                ),
+            }
+        }
+        for restored in snapshot.contexts {
+            match self.cryptos.get_mut(&restored.symbol) {
+                Some(ctx) => {
+                    *ctx = ExecContext {
+                        precision: ctx.precision,
+                        verbose: ctx.verbose,
+                        ..restored
+                    }
+                }
+                None if restored.position.is_open() => println!(
+                    "[WARN][SNAPSHOT] {} is no longer traded, its open position of {} is left alone",
+                    restored.symbol, restored.position.quantity
+                ),
+                None => {}
+            }
+        }
+        shared.ledger = snapshot.ledger;
+        shared.streak = snapshot.streak;
+        shared.trade_returns = snapshot.trade_returns.into();
+        shared.open_positions = snapshot.open_positions;
+        self.resting = snapshot.resting;
+        self.risk.restore(snapshot.risk);
+        self.correlations = snapshot.correlations;
+        self.journal = snapshot.journal;
+        for timer in &mut self.timers {
+            timer.next_fire = snapshot.timers.get(&timer.interval_secs).copied();
+        }
+        self.next_rebalance = snapshot.next_rebalance;
+        self.starting_equity = snapshot.starting_equity;
+        if let Some(shadows) = &mut self.shadows {
+            shadows.restore(snapshot.shadows);
+        }
+        Ok(())
+    }
+
+    // writes a snapshot when one is due, or regardless when `force`d on
+    // shutdown; failures are logged and trading carries on
+    fn checkpoint(&mut self, now: u64, force: bool) {
+        let Some(config) = &self.snapshot_config else {
+            return;
+        };
+        let due = *self.next_snapshot.get_or_insert(now + config.interval_secs);
+        if !force && now < due {
+            return;
+        }
+        self.next_snapshot = Some(now + config.interval_secs);
+        match self
+            .snapshot()
+            .and_then(|snapshot| snapshot.save(&config.path))
+        {
+            Ok(()) if force => println!("[INFO][SNAPSHOT] Saved state to {}", config.path),
+            Ok(()) => {}
+            Err(e) => println!(
+                "[ERROR][SNAPSHOT] Could not save state to {}: {}",
+                config.path, e
+            ),
+        }
+    }
+
+    // rebuilds the correlation estimator with the current sampling from the
+    // candles the contexts hold, in close time order across symbols
+    fn resample_correlations(&mut self) {
+        let mut estimator = self.risk.limits().correlation.estimator();
+        let mut candles: Vec<(&str, &Candle)> = self
+            .cryptos
+            .values()
+            .flat_map(|ctx| ctx.candles.iter().map(move |c| (ctx.symbol.as_str(), c)))
+            .collect();
+        candles.sort_by_key(|(_, candle)| candle.close_secs());
+        for (symbol, candle) in candles {
+            estimator.update(symbol, candle.close_secs(), candle.close);
+        }
+        self.correlations = estimator;
+    }
+
+    // reloads the watched config file once it has changed on disk; the
+    // executioner is between candles here, so no order is in flight
+    fn watch_config(&mut self, now: u64) {
+        let Some(mut watch) = self.config_watch.take() else {
+            return;
+        };
+        if let Some(new) = watch.poll(now) {
+            let changes = diff(watch.current(), &new);
+            if changes.is_empty() {
+                watch.accept(new);
+            } else {
+                match self.reload_config(watch.current(), &new) {
+                    Ok(()) => {
+                        println!("[INFO][CONFIG] Reloaded {}", watch.path());
+                        for change in &changes {
+                            println!("[INFO][CONFIG]   {}", change);
+                        }
+                        watch.accept(new);
+                    }
+                    Err(e) => println!(
+                        "[ERROR][CONFIG] Rejected edit to {}, keeping the running parameters: {}",
+                        watch.path(),
+                        e
+                    ),
+                }
+            }
+        }
+        self.config_watch = Some(watch);
+    }
+
+    // feeds a candle the live strategy has seen to the shadows, and logs
+    // their PnL next to the live one when a report is due
+    async fn shadow(&mut self, candle_message: Option<CandleData>) {
+        let (Some(candle_message), Some(mut shadows)) = (candle_message, self.shadows.take())
+        else {
+            return;
+        };
+        let now = candle_message.candle.close_secs();
+        shadows.step(&candle_message).await;
+        if shadows.report_due(now) {
+            shadows.log(&self.report().await).await;
+        }
+        self.shadows = Some(shadows);
+    }
+
+    async fn handle_candle(&mut self, candle_message: CandleData) {
+        let l = self.cryptos.len();
+        let mut ctx = match self.cryptos.remove(&candle_message.symbol) {
+            None => return,
+            Some(c) => c,
+        };
+        if !ctx.update(candle_message.candle) {
+            // late, e.g. queued while warming up from history
+            self.cryptos.insert(candle_message.symbol, ctx);
+            return;
+        }
+        if candle_message.quote.is_some() {
+            ctx.quote = candle_message.quote;
+        }
+        self.correlations
+            .update(&ctx.symbol, ctx.now_secs(), ctx.last_close);
+        if let Some(qty) = self.bootstrap_positions.remove(&ctx.symbol)
+            && qty > 0.0
+            && !ctx.position.is_open()
+            && ctx.last_close > 0.0
+        {
+            if let Err(err) = ctx
+                .position
+                .add_fill(qty, ctx.last_close, 0.0, Some(ctx.now_secs()))
+            {
+                println!(
+                    "[ERROR][BOOTSTRAP] Failed to seed {} with {} units: {}",
+                    ctx.symbol, qty, err
+                );
+            } else {
+                self.starting_equity += qty * ctx.last_close;
+                println!(
+                    "[INFO][BOOTSTRAP] Restored {} with existing position of {} units",
+                    ctx.symbol, qty
+                );
+            }
+        }
+        self.fill_resting(&mut ctx).await;
+        self.index += 1;
+
+        let capital: f64;
+        {
+            let guard = self.shared_state.lock().await;
+            capital = guard.ledger.balance();
+        }
+        if self.verbose {
+            println!(
+                "[{}] Capital: {} Holding: {}",
+                ctx.symbol,
+                capital,
+                ctx.position.quantity * ctx.last_close,
+            );
+        }
+
+        let equity = self.equity().await + ctx.position.notional(ctx.last_close);
+        let streak = self.shared_state.lock().await.streak;
+        if self.risk.observe(ctx.now_secs(), equity, streak) {
+            self.flatten_all(&mut ctx).await;
+        }
+
+        // just liquidated position for this ctx
+        if self.portfolio.is_none()
+            && ctx.position.is_open()
+            && self
+                .strategy
+                .get(&ctx.symbol)
+                .update_position(&mut ctx, self.shared_state.clone())
+                .await
+        {
+            self.close_position(&mut ctx).await;
+        }
+
+        if self.portfolio.is_none()
+            && !self.risk.is_halted()
+            && self
+                .strategy
+                .get(&ctx.symbol)
+                .should_long(&mut ctx, self.shared_state.clone())
+                .await
+            && let Some(order) = self
+                .strategy
+                .get(&ctx.symbol)
+                .go_long(&ctx, self.shared_state.clone())
+                .await
+        {
+            self.buy(&mut ctx, order).await;
+        }
+
+        if self.portfolio.is_none() && !self.risk.is_halted() {
+            self.manage_orders(&mut ctx).await;
+        }
+
+        let now = ctx.now_secs();
+        self.cryptos.insert(candle_message.symbol, ctx);
+        let open_positions = self
+            .cryptos
+            .values()
+            .filter(|ctx| ctx.position.is_open())
+            .count();
+        self.shared_state.lock().await.open_positions = open_positions;
+
+        if self.journal.wants_sample(now) {
+            let equity = self.equity().await;
+            self.journal.record_equity(now, equity);
+        }
+
+        // periodic wallet sync cause floating point is gay
+        if l > 0 && self.index.is_multiple_of(l * 15) && !self.backtesting {
+            self.sync(None, None).await;
+        }
+    }
+
+    // cancels the resting orders of `ctx` and sells the whole position
+    async fn close_position(&mut self, ctx: &mut ExecContext) {
+        self.cancel_all_resting(ctx).await;
+        let quantity = ctx.position.quantity;
+        self.sell(ctx, quantity).await;
+    }
+
+    // runs a buy past the risk manager, sends it and applies the fill
+    async fn buy(&mut self, ctx: &mut ExecContext, order: Order) {
+        let Some(order) = self.check_risk(ctx, order).await else {
+            return;
+        };
+        let Some(fill) = self.execute(ctx, order).await else {
+            return;
+        };
+        self.apply_buy_fill(ctx, &fill).await;
+    }
+
+    // adds a buy fill to the position of `ctx`
+    async fn apply_buy_fill(&mut self, ctx: &mut ExecContext, fill: &OrderDetail) {
+        if let Err(err) = ctx.position.add_fill(
+            fill.filled_quantity,
+            fill.filled_aver_price,
+            fill.commission_charge_value,
+            Some(ctx.now_secs()),
+        ) {
+            println!("[ERROR][POSITION] Failed to register fill: {}", err);
+        }
+        self.strategy
+            .get_mut(&ctx.symbol)
+            .on_fill(ctx, fill, self.shared_state.clone())
+            .await;
+    }
+
+    // sells `quantity` of the position in `ctx` and journals the round trip
+    async fn sell(&mut self, ctx: &mut ExecContext, quantity: f64) {
+        if !ctx.position.is_open() || quantity <= 0.0 {
+            return;
+        }
+        let order = Order {
+            pair: ctx.pair(),
+            side: OrderSide::Sell,
+            order_type: OrderType::Market,
+            quantity: quantity.min(ctx.position.quantity),
+            price: None,
+        };
+        let Some(fill) = self.execute(ctx, order).await else {
+            return;
+        };
+        self.apply_sell_fill(ctx, &fill).await;
+    }
+
+    // takes a sell fill off the position of `ctx` and journals the round trip
+    async fn apply_sell_fill(&mut self, ctx: &mut ExecContext, fill: &OrderDetail) {
+        let entry_price = ctx.position.entry_price;
+        let entry_time = ctx.position.entry_time.unwrap_or(0);
+        let quantity = fill.filled_quantity.min(ctx.position.quantity);
+        match ctx.position.reduce(
+            fill.filled_quantity,
+            fill.filled_aver_price,
+            fill.commission_charge_value,
+        ) {
+            Ok(pnl) => {
+                let return_pct = if entry_price * quantity > 0.0 {
+                    pnl / (entry_price * quantity) * 100.0
+                } else {
+                    0.0
+                };
+                {
+                    let mut guard = self.shared_state.lock().await;
+                    guard.record_trade_return(return_pct / 100.0);
+                    guard.streak = if pnl < 0.0 { guard.streak + 1 } else { 0 };
+                }
+                self.journal.record_trade(TradeRecord {
+                    symbol: ctx.symbol.clone(),
+                    entry_time,
+                    exit_time: ctx.now_secs(),
+                    entry_price,
+                    exit_price: fill.filled_aver_price,
+                    quantity,
+                    pnl,
+                    return_pct,
+                })
+            }
+            Err(err) => println!("[ERROR][POSITION] Reduce failed: {}", err),
+        }
+        self.strategy
+            .get_mut(&ctx.symbol)
+            .on_fill(ctx, fill, self.shared_state.clone())
+            .await;
+    }
+
+    // trades towards target weights on a schedule: from the optimiser in
+    // portfolio mode, otherwise from strategies that allocate across symbols
+    async fn rebalance_portfolio(&mut self, now: u64) {
+        let interval = match &self.portfolio {
+            Some(portfolio) => portfolio.rebalance_secs,
+            None => self.strategy.default.allocation_secs(),
+        };
+        if interval == 0
+            || self.risk.is_halted()
+            || self.next_rebalance.is_some_and(|next| now < next)
+        {
+            return;
+        }
+
+        let allocation = match &self.portfolio {
+            Some(portfolio) => {
+                let mut symbols: Vec<String> = self.cryptos.keys().cloned().collect();
+                symbols.sort();
+                portfolio.allocation(&symbols, &self.correlations).map(Some)
+            }
+            None => {
+                let assigned = self.take_assigned();
+                let allocation = self
+                    .strategy
+                    .default
+                    .target_weights(&self.cryptos, now, self.shared_state.clone())
+                    .await;
+                self.cryptos.extend(assigned);
+                allocation
+            }
+        };
+        let Some(allocation) = allocation else {
+            // not enough history yet
+            self.next_rebalance = Some(now + interval.min(ALLOCATION_RETRY_SECS));
+            return;
+        };
+        self.next_rebalance = Some(now + interval);
+        if let Some(allocation) = allocation {
+            self.rebalance_to(
+                &allocation.weights,
+                allocation.min_order_notional,
+                allocation.turnover_budget,
+            )
+            .await;
+        }
+    }
+
+    // takes out the contexts of symbols trading their own strategy: they are
+    // not the default's to allocate, so it does not see them
+    fn take_assigned(&mut self) -> Vec<(String, ExecContext)> {
+        self.strategy
+            .by_symbol
+            .keys()
+            .filter_map(|symbol| self.cryptos.remove_entry(symbol))
+            .collect()
+    }
+
+    // whether rebalancing trades `symbol`: every symbol in portfolio mode,
+    // otherwise only those left to the executioner's own strategy
+    fn allocates(&self, symbol: &str) -> bool {
+        self.portfolio.is_some() || !self.strategy.by_symbol.contains_key(symbol)
+    }
+
+    // sends the orders that move positions towards `targets` (weights of
+    // equity); positions of symbols it does not allocate are left alone
+    async fn rebalance_to(
+        &mut self,
+        targets: &HashMap<String, f64>,
+        min_order_notional: f64,
+        turnover_budget: f64,
+    ) {
+        let equity = self.equity().await;
+        let targets: HashMap<String, f64> = targets
+            .iter()
+            .filter(|(symbol, _)| self.allocates(symbol))
+            .map(|(symbol, weight)| (symbol.clone(), *weight))
+            .collect();
+        let holdings: HashMap<String, f64> = self
+            .cryptos
+            .iter()
+            .filter(|(symbol, ctx)| ctx.position.is_open() && self.allocates(symbol))
+            .map(|(symbol, ctx)| (symbol.clone(), ctx.position.notional(ctx.last_close)))
+            .collect();
+        let prices: HashMap<String, f64> = self
+            .cryptos
+            .iter()
+            .map(|(symbol, ctx)| (symbol.clone(), ctx.last_close))
+            .collect();
+        let orders = rebalance_orders(
+            &targets,
+            &holdings,
+            &prices,
+            equity,
+            min_order_notional,
+            turnover_budget,
+        );
+        if self.verbose && !orders.is_empty() {
+            println!(
+                "[INFO][PORTFOLIO] Rebalancing {} symbols at equity {:.2}",
+                orders.len(),
+                equity
+            );
+        }
+
+        for rebalance in orders {
+            let Some(mut ctx) = self.cryptos.remove(&rebalance.symbol) else {
+                continue;
+            };
+            match rebalance.side {
+                OrderSide::Sell => self.sell(&mut ctx, rebalance.quantity).await,
+                OrderSide::Buy => {
+                    let order = Order {
+                        pair: ctx.pair(),
+                        side: OrderSide::Buy,
+                        order_type: OrderType::Market,
+                        quantity: rebalance.quantity,
+                        price: None,
+                    };
+                    self.buy(&mut ctx, order).await;
+                }
+            }
+            self.cryptos.insert(rebalance.symbol, ctx);
+        }
+    }
+
+    // kill switch: cancel every resting order and sell every open position,
+    // `ctx` included
+    async fn flatten_all(&mut self, ctx: &mut ExecContext) {
+        self.close_position(ctx).await;
+        self.close_all().await;
+    }
+
+    // closes the positions and resting orders of every symbol in `cryptos`
+    async fn close_all(&mut self) {
+        let symbols: Vec<String> = self
+            .cryptos
+            .iter()
+            .filter(|(symbol, other)| {
+                other.position.is_open() || !self.resting.symbol(symbol).is_empty()
+            })
+            .map(|(symbol, _)| symbol.clone())
+            .collect();
+        for symbol in symbols {
+            if let Some(mut other) = self.cryptos.remove(&symbol) {
+                self.close_position(&mut other).await;
+                self.cryptos.insert(symbol, other);
+            }
+        }
+    }
+
+    // runs `order` past the risk manager. `ctx` is out of `cryptos` while
+    // its candle is handled, so its holding is added back in here
+    async fn check_risk(&mut self, ctx: &ExecContext, order: Order) -> Option<Order> {
+        let held = ctx.position.notional(ctx.last_close);
+        let mut holdings: Vec<(String, f64)> = self
+            .cryptos
+            .values()
+            .chain(std::iter::once(ctx))
+            .filter(|held| held.position.is_open())
+            .map(|held| (held.symbol.clone(), held.position.notional(held.last_close)))
+            .collect();
+        holdings.sort_by(|a, b| a.0.cmp(&b.0));
+        // resting buys count towards the caps as if they had filled
+        let pending = self.resting.notional(&ctx.symbol, &OrderSide::Buy);
+        let exposure = Exposure {
+            equity: self.equity().await + held,
+            gross: holdings.iter().map(|(_, notional)| notional).sum::<f64>() + pending,
+            open_positions: holdings.len(),
+            symbol_notional: held + pending,
+            holdings,
+        };
+
+        let requested = order.quantity;
+        match self.risk.check(
+            order.clone(),
+            &ctx.symbol,
+            ctx.last_close,
+            ctx.precision,
+            &exposure,
+            &self.correlations,
+        ) {
+            Ok(approved) => {
+                if self.verbose && approved.quantity < requested {
+                    println!(
+                        "[WARN][RISK] {} buy cut from {} to {}",
+                        ctx.symbol, requested, approved.quantity
+                    );
+                }
+                Some(approved)
+            }
+            Err(rejection) => {
+                if self.verbose {
+                    println!("[WARN][RISK] {} buy rejected: {}", ctx.symbol, rejection);
+                }
+                self.strategy
+                    .get_mut(&ctx.symbol)
+                    .on_reject(
+                        ctx,
+                        &order,
+                        &rejection.to_string(),
+                        self.shared_state.clone(),
+                    )
+                    .await;
+                None
+            }
+        }
+    }
+
+    async fn fire_timers(&mut self, now: u64) {
+        for i in 0..self.timers.len() {
+            let interval_secs = self.timers[i].interval_secs;
+            match self.timers[i].next_fire {
+                None => self.timers[i].next_fire = Some(now + interval_secs),
+                Some(next) if now >= next => {
+                    self.timers[i].next_fire = Some(now + interval_secs);
+                    for strategy in self.strategy.all_mut() {
+                        if strategy.timers().contains(&interval_secs) {
+                            strategy
+                                .on_timer(interval_secs, now, self.shared_state.clone())
+                                .await;
+                        }
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    // sends order (or simulates it when backtesting) and updates capital.
+    // the caller applies the returned fill to the position
+    async fn execute(&mut self, ctx: &ExecContext, order: Order) -> Option<OrderDetail> {
+        // buys hold their cash until the fill settles so other symbols can't spend it
+        let reservation = match order.side {
+            OrderSide::Buy => {
+                let price = order.price.unwrap_or(ctx.last_close);
+                let amount = CapitalLedger::estimate(order.quantity, price);
+                let reserved = self
+                    .shared_state
+                    .lock()
+                    .await
+                    .ledger
+                    .reserve(&order.pair, amount);
+                if reserved.is_none() {
+                    let reason = format!("insufficient available capital for {:.2} USD", amount);
+                    if self.verbose {
+                        println!("[WARN][LEDGER] {} buy rejected: {}", ctx.symbol, reason);
+                    }
+                    self.strategy
+                        .get_mut(&ctx.symbol)
+                        .on_reject(ctx, &order, &reason, self.shared_state.clone())
+                        .await;
+                    return None;
+                }
+                reserved
+            }
+            OrderSide::Sell => None,
+        };
+
+        if self.backtesting {
+            let price = ctx.last_close;
+            let fill = OrderDetail {
+                pair: order.pair.clone(),
+                status: "FILLED".to_string(),
+                side: order.side.to_string(),
+                order_type: order.order_type.to_string(),
+                price,
+                quantity: order.quantity,
+                filled_quantity: order.quantity,
+                filled_aver_price: price,
+                commission_charge_value: BACKTEST_FEE_RATE * price * order.quantity,
+                commission_percent: BACKTEST_FEE_RATE,
+                ..Default::default()
+            };
+            self.sync(Some(fill.clone()), reservation).await?;
+            return Some(fill);
+        }
+
+        let reason = match self.send_order(ctx, &order).await {
+            // hopefully instant?
+            Ok(order_detail) => {
+                if self
+                    .sync(Some(order_detail.clone()), reservation)
+                    .await
+                    .is_some()
+                {
+                    return Some(order_detail);
+                }
+                println!("[ERROR][UPDATEPOSITION] Sync returned no fill data");
+                "unrecognised fill".to_string()
+            }
+            Err(reason) => reason,
+        };
+        if let Some(id) = reservation {
+            self.shared_state.lock().await.ledger.release(id);
+        }
+
+        self.strategy
+            .get_mut(&ctx.symbol)
+            .on_reject(ctx, &order, &reason, self.shared_state.clone())
+            .await;
+        None
+    }
+
+    // hands `order` to the order engine and waits for the exchange's answer
+    async fn send_order(&self, ctx: &ExecContext, order: &Order) -> Result<OrderDetail, String> {
+        let (tx, rx) = oneshot::channel();
+        let request = EngineRequest::Place(OrderWithResponse {
+            order: order.clone(),
+            precision: ctx.precision,
+            response: tx,
+        });
+        if let Err(e) = self.order_engine.send(request).await {
+            println!("[ERROR][ORDERENGINE] Failed to dispatch order: {}", e);
+            return Err(e.to_string());
+        }
+        match rx.await {
+            Ok(result) => result,
+            Err(e) => {
+                println!("[ERROR][ORDERENGINE] Could not receive fill: {}", e);
+                Err(e.to_string())
+            }
+        }
+    }
+
+    // orders matching `order_id` or `pair` as the exchange reports them
+    async fn query_orders(
+        &self,
+        order_id: Option<u64>,
+        pair: Option<String>,
+        pending_only: bool,
+    ) -> Option<Vec<OrderDetail>> {
+        let (tx, rx) = oneshot::channel();
+        let request = EngineRequest::Query {
+            order_id,
+            pair,
+            pending_only,
+            response: tx,
+        };
+        self.order_engine.send(request).await.ok()?;
+        rx.await.ok()?.ok()
+    }
+
+    // lets the strategy place and cancel limit orders for `ctx`
+    async fn manage_orders(&mut self, ctx: &mut ExecContext) {
+        let intents = self
+            .strategy
+            .get_mut(&ctx.symbol)
+            .manage_orders(
+                ctx,
+                self.resting.symbol(&ctx.symbol),
+                self.shared_state.clone(),
+            )
+            .await;
+        for intent in intents {
+            match intent {
+                OrderIntent::Place(order) => self.place_resting(ctx, order).await,
+                OrderIntent::Cancel(id) => self.cancel_resting(ctx, id).await,
+            }
+        }
+    }
+
+    // rests a limit order. buys pass the risk stage and hold their cash until
+    // filled or cancelled; sells must be covered by inventory that no other
+    // resting sell has claimed
+    async fn place_resting(&mut self, ctx: &mut ExecContext, order: Order) {
+        let reason = match (order.price, &order.side) {
+            (Some(price), _) if price <= 0.0 => Some("limit price must be positive".to_string()),
+            (None, _) => Some("limit order without a price".to_string()),
+            (Some(_), OrderSide::Sell) => {
+                let free =
+                    ctx.position.quantity - self.resting.remaining(&ctx.symbol, &OrderSide::Sell);
+                (order.quantity > free)
+                    .then(|| format!("sell of {} exceeds free inventory {}", order.quantity, free))
+            }
+            (Some(_), OrderSide::Buy) => None,
+        };
+        if let Some(reason) = reason {
+            if self.verbose {
+                println!("[WARN][RESTING] {} order rejected: {}", ctx.symbol, reason);
+            }
+            self.strategy
+                .get_mut(&ctx.symbol)
+                .on_reject(ctx, &order, &reason, self.shared_state.clone())
+                .await;
+            return;
+        }
+        let order = match order.side {
+            OrderSide::Buy => match self.check_risk(ctx, order).await {
+                Some(order) => order,
+                None => return,
+            },
+            OrderSide::Sell => order,
+        };
+        let price = order.price.unwrap_or(ctx.last_close);
+
+        let reservation = match order.side {
+            OrderSide::Buy => {
+                let amount = CapitalLedger::estimate(order.quantity, price);
+                let reserved = self
+                    .shared_state
+                    .lock()
+                    .await
+                    .ledger
+                    .reserve(&order.pair, amount);
+                if reserved.is_none() {
+                    let reason = format!("insufficient available capital for {:.2} USD", amount);
+                    if self.verbose {
+                        println!(
+                            "[WARN][LEDGER] {} limit buy rejected: {}",
+                            ctx.symbol, reason
+                        );
+                    }
+                    self.strategy
+                        .get_mut(&ctx.symbol)
+                        .on_reject(ctx, &order, &reason, self.shared_state.clone())
+                        .await;
+                    return;
+                }
+                reserved
+            }
+            OrderSide::Sell => None,
+        };
+
+        let (id, placed) = if self.backtesting {
+            (self.resting.next_simulated_id(), None)
+        } else {
+            match self.send_order(ctx, &order).await {
+                Ok(detail) => (detail.order_id, Some(detail)),
+                Err(reason) => {
+                    if let Some(id) = reservation {
+                        self.shared_state.lock().await.ledger.release(id);
+                    }
+                    self.strategy
+                        .get_mut(&ctx.symbol)
+                        .on_reject(ctx, &order, &reason, self.shared_state.clone())
+                        .await;
+                    return;
+                }
+            }
+        };
+        if self.verbose {
+            println!(
+                "[INFO][RESTING] {} {} {} @{} resting as {}",
+                ctx.symbol, order.side, order.quantity, price, id
+            );
+        }
+        self.resting.insert(RestingOrder {
+            id,
+            symbol: ctx.symbol.clone(),
+            side: order.side,
+            price,
+            quantity: order.quantity,
+            filled: 0.0,
+            fee_paid: 0.0,
+            reservation,
+        });
+        // a marketable limit may have filled on arrival
+        if let Some(detail) = placed {
+            self.reconcile_resting(ctx, &detail).await;
+        }
+    }
+
+    // cancels a resting order and frees what it still had reserved
+    async fn cancel_resting(&mut self, ctx: &mut ExecContext, id: u64) {
+        if self.resting.get_mut(&ctx.symbol, id).is_none() {
+            return;
+        }
+        if !self.backtesting {
+            let (tx, rx) = oneshot::channel();
+            let request = EngineRequest::Cancel {
+                order_id: id,
+                response: tx,
+            };
+            if self.order_engine.send(request).await.is_err() || !matches!(rx.await, Ok(Ok(()))) {
+                // most likely filled meanwhile; the next poll settles it
+                return;
+            }
+            // book fills that raced the cancel
+            if let Some(detail) = self
+                .query_orders(Some(id), None, false)
+                .await
+                .and_then(|details| details.into_iter().next())
+            {
+                self.reconcile_resting(ctx, &detail).await;
+            }
+        }
+        if let Some(order) = self.resting.remove(&ctx.symbol, id)
+            && let Some(reservation) = order.reservation
+        {
+            self.shared_state.lock().await.ledger.release(reservation);
+        }
+    }
+
+    async fn cancel_all_resting(&mut self, ctx: &mut ExecContext) {
+        let ids: Vec<u64> = self
+            .resting
+            .symbol(&ctx.symbol)
+            .iter()
+            .map(|order| order.id)
+            .collect();
+        for id in ids {
+            self.cancel_resting(ctx, id).await;
+        }
+    }
+
+    // applies fills of resting orders: simulated from the latest candle's
+    // range when backtesting, polled from the exchange when live
+    async fn fill_resting(&mut self, ctx: &mut ExecContext) {
+        if self.resting.symbol(&ctx.symbol).is_empty() {
+            return;
+        }
+
+        if self.backtesting {
+            let Some(candle) = ctx.candles.last().copied() else {
+                return;
+            };
+            // with a recorded ticker, a bid fills once Roostoo trades or
+            // offers at its price, and an ask once Roostoo trades or bids
+            // at its price; otherwise the Binance candle's range decides
+            let (low, high) = match ctx.quote {
+                Some(quote) if quote.spread_bps().is_some() => {
+                    let last = if quote.last > 0.0 {
+                        quote.last
+                    } else {
+                        quote.mid()
+                    };
+                    (quote.ask.min(last), quote.bid.max(last))
+                }
+                _ => (
+                    if candle.low > 0.0 {
+                        candle.low
+                    } else {
+                        candle.close
+                    },
+                    candle.high.max(candle.close),
+                ),
+            };
+            for id in self.resting.crossed(&ctx.symbol, low, high) {
+                let Some(order) = self.resting.get_mut(&ctx.symbol, id) else {
+                    continue;
+                };
+                let fill = OrderDetail {
+                    order_id: id,
+                    pair: ctx.pair(),
+                    status: "FILLED".to_string(),
+                    role: "MAKER".to_string(),
+                    side: order.side.to_string(),
+                    order_type: OrderType::Limit.to_string(),
+                    price: order.price,
+                    quantity: order.quantity,
+                    filled_quantity: order.quantity,
+                    filled_aver_price: order.price,
+                    commission_charge_value: BACKTEST_MAKER_FEE_RATE * order.price * order.quantity,
+                    commission_percent: BACKTEST_MAKER_FEE_RATE,
+                    ..Default::default()
+                };
+                self.reconcile_resting(ctx, &fill).await;
+            }
+            return;
+        }
+
+        let now = ctx.now_secs();
+        if self
+            .next_poll
+            .get(&ctx.symbol)
+            .is_some_and(|next| now < *next)
+        {
+            return;
+        }
+        self.next_poll
+            .insert(ctx.symbol.clone(), now + RESTING_POLL_SECS);
+        let Some(pending) = self.query_orders(None, Some(ctx.pair()), true).await else {
+            return;
+        };
+        let ids: Vec<u64> = self
+            .resting
+            .symbol(&ctx.symbol)
+            .iter()
+            .map(|order| order.id)
+            .collect();
+        for id in ids {
+            let detail = match pending.iter().find(|detail| detail.order_id == id) {
+                Some(detail) => Some(detail.clone()),
+                // no longer pending: filled, or cancelled outside the bot
+                None => self
+                    .query_orders(Some(id), None, false)
+                    .await
+                    .and_then(|details| details.into_iter().next()),
+            };
+            if let Some(detail) = detail {
+                self.reconcile_resting(ctx, &detail).await;
+            }
+        }
+    }
+
+    // books the part of a resting order's cumulative fill that is new, and
+    // drops the order once it is complete or no longer live
+    async fn reconcile_resting(&mut self, ctx: &mut ExecContext, detail: &OrderDetail) {
+        let Some(order) = self.resting.get_mut(&ctx.symbol, detail.order_id) else {
+            return;
+        };
+        let delta = (detail.filled_quantity - order.filled).max(0.0);
+        let fee = (detail.commission_charge_value - order.fee_paid).max(0.0);
+        order.filled += delta;
+        order.fee_paid += fee;
+        let done = order.remaining() <= 1e-12 || detail.status.to_uppercase().contains("CANCEL");
+        let (side, price, remaining, reservation) = (
+            order.side.clone(),
+            order.price,
+            order.remaining(),
+            order.reservation,
+        );
+
+        if delta > 0.0 {
+            let fill = OrderDetail {
+                filled_quantity: delta,
+                filled_aver_price: if detail.filled_aver_price > 0.0 {
+                    detail.filled_aver_price
+                } else {
+                    price
+                },
+                commission_charge_value: fee,
+                ..detail.clone()
+            };
+            // the reservation is settled with the last fill, and shrinks before that
+            let settle = if done { reservation } else { None };
+            if self.sync(Some(fill.clone()), settle).await.is_some() {
+                match side {
+                    OrderSide::Buy => self.apply_buy_fill(ctx, &fill).await,
+                    OrderSide::Sell => self.apply_sell_fill(ctx, &fill).await,
+                }
+            }
+        }
+
+        let mut guard = self.shared_state.lock().await;
+        match reservation {
+            Some(id) if done && delta <= 0.0 => guard.ledger.release(id),
+            Some(id) if !done => guard
+                .ledger
+                .resize(id, CapitalLedger::estimate(remaining, price)),
+            _ => {}
+        }
+        drop(guard);
+        if done {
+            self.resting.remove(&ctx.symbol, detail.order_id);
+        }
+    }
+
+    // if argument to details None, sync capital. if given order, return qty,price
+    // and settle its reservation. ONLY UPDATES CAPTAL, NOT POSITION
+    async fn sync(
+        &self,
+        details: Option<OrderDetail>,
+        reservation: Option<Reservation>,
+    ) -> Option<(f64, f64, f64)> {
         // let guard = self.shared_state.lock().await;
         match details {
             None => {
@@ -306,16 +2020,16 @@ impl<T: Strategy + Send> Executioner<T> {
                             println!("[ERROR][Sync] USD balance missing in wallet snapshot");
                             return None;
                         };
-                        let capital_copy = balance.free;
+                        let capital_copy = balance.free + balance.lock;
                         {
                             let mut guard = self.shared_state.lock().await;
-                            guard.capital = capital_copy;
+                            guard.ledger.sync_wallet(balance.free, balance.lock);
                         }
 
                         println!("[SUCCESS][Sync] Successfull. Capital: {}", capital_copy);
                     }
                 };
-                return None;
+                None
             }
             Some(details) => {
                 let sign: f64 = match details.side.as_str() {
@@ -330,6 +2044,9 @@ impl<T: Strategy + Send> Executioner<T> {
                     }
                 };
                 if sign == 0.0 {
+                    if let Some(id) = reservation {
+                        self.shared_state.lock().await.ledger.release(id);
+                    }
                     return None;
                 }
 
@@ -339,20 +2056,224 @@ impl<T: Strategy + Send> Executioner<T> {
                 let fee = details.commission_charge_value;
                 {
                     let mut guard = self.shared_state.lock().await;
-                    guard.capital += sign * qty * price - fee;
-                    capital_copy = guard.capital;
+                    let cash_delta = sign * qty * price - fee;
+                    match reservation {
+                        Some(id) => guard.ledger.settle(id, cash_delta),
+                        None => guard.ledger.apply(cash_delta),
+                    }
+                    capital_copy = guard.ledger.balance();
+                }
+                if self.verbose {
+                    println!(
+                        "[SUCCESS][SYNC] Order {}: sym: {} qty: {} price: {}, capital: {}",
+                        if sign > 0.0 { "SELL" } else { "BUY" },
+                        details.pair,
+                        qty,
+                        price,
+                        capital_copy
+                    );
                 }
-                println!(
-                    "[SUCCESS][SYNC] Order {}: sym: {} qty: {} price: {}, capital: {}",
-                    if sign > 0.0 { "SELL" } else { "BUY" },
-                    details.pair,
-                    qty,
-                    price,
-                    capital_copy
-                );
 
-                return Some((qty, price, fee));
+                Some((qty, price, fee))
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // logs every hook it sees; buys one unit whenever flat and sells it on
+    // the next candle
+    struct Recorder {
+        events: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl Recorder {
+        fn log(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    #[async_trait]
+    impl Strategy for Recorder {
+        async fn should_long(&self, ctx: &mut ExecContext, _: Arc<Mutex<SharedState>>) -> bool {
+            !ctx.position.is_open()
+        }
+
+        async fn go_long(&self, ctx: &ExecContext, _: Arc<Mutex<SharedState>>) -> Option<Order> {
+            Some(Order {
+                pair: ctx.pair(),
+                side: OrderSide::Buy,
+                order_type: OrderType::Market,
+                quantity: 1.0,
+                price: None,
+            })
+        }
+
+        async fn update_position(&self, _: &mut ExecContext, _: Arc<Mutex<SharedState>>) -> bool {
+            true
+        }
+
+        async fn on_start(&mut self, positions: &HashMap<String, f64>, _: Arc<Mutex<SharedState>>) {
+            self.log(format!("start {}", positions.len()));
+        }
+
+        async fn on_fill(
+            &mut self,
+            ctx: &ExecContext,
+            fill: &OrderDetail,
+            _: Arc<Mutex<SharedState>>,
+        ) {
+            self.log(format!(
+                "fill {} {} {} @{}",
+                ctx.symbol, fill.side, fill.filled_quantity, fill.filled_aver_price
+            ));
+        }
+
+        async fn on_reject(
+            &mut self,
+            ctx: &ExecContext,
+            _: &Order,
+            _: &str,
+            _: Arc<Mutex<SharedState>>,
+        ) {
+            self.log(format!("reject {}", ctx.symbol));
+        }
+
+        fn timers(&self) -> Vec<u64> {
+            vec![120]
+        }
+
+        async fn on_timer(&mut self, interval_secs: u64, now: u64, _: Arc<Mutex<SharedState>>) {
+            self.log(format!("timer {} @{}", interval_secs, now));
+        }
+
+        async fn on_stop(&mut self, _: Arc<Mutex<SharedState>>) {
+            self.log("stop".to_string());
+        }
+    }
+
+    fn executioner(
+        events: Arc<std::sync::Mutex<Vec<String>>>,
+        candle_rx: mpsc::Receiver<CandleData>,
+    ) -> (Executioner<Recorder>, mpsc::Receiver<EngineRequest>) {
+        let (config, oe_rx) = TraderConfig::for_test(Recorder { events }, candle_rx);
+        (Executioner::for_test(config), oe_rx)
+    }
+
+    fn candle(secs: u64, close: f64) -> Candle {
+        Candle {
+            open_time: (secs - 60) * 1_000,
+            close_time: secs * 1_000,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1.0,
+            trade_count: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn lifecycle_hooks_fire_in_order_and_timers_fire_once_per_interval() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (candle_tx, candle_rx) = mpsc::channel(8);
+        let (mut backtest, _oe_rx) = executioner(events.clone(), candle_rx);
+        let t = 1_700_000_000;
+        for i in 1..=3 {
+            let candle = candle(t + i * 60, 100.0);
+            candle_tx
+                .send(CandleData {
+                    symbol: "BTC".to_string(),
+                    candle,
+                    quote: None,
+                })
+                .await
+                .unwrap();
+        }
+        drop(candle_tx);
+        backtest.run(true).await;
+        let buy = "fill BTC BUY 1 @100".to_string();
+        let sell = "fill BTC SELL 1 @100".to_string();
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "start 0".to_string(),
+                buy.clone(),
+                sell.clone(),
+                buy.clone(),
+                sell,
+                buy,
+                format!("timer 120 @{}", t + 180),
+                "stop".to_string(),
+            ]
+        );
+
+        // a late or repeated candle does not fire a timer again, and a gap
+        // of several intervals fires it once
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (_, candle_rx) = mpsc::channel(1);
+        let (mut timed, _oe_rx) = executioner(events.clone(), candle_rx);
+        for secs in [t, t + 120, t + 60, t + 120, t + 1_000] {
+            timed.fire_timers(secs).await;
+        }
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                format!("timer 120 @{}", t + 120),
+                format!("timer 120 @{}", t + 1_000)
+            ]
+        );
+
+        // a live order the order engine cannot take is reported as rejected
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (_, candle_rx) = mpsc::channel(1);
+        let (mut live, oe_rx) = executioner(events.clone(), candle_rx);
+        drop(oe_rx);
+        live.handle_candle(CandleData {
+            symbol: "BTC".to_string(),
+            candle: candle(t, 100.0),
+            quote: None,
+        })
+        .await;
+        assert_eq!(*events.lock().unwrap(), ["reject BTC".to_string()]);
+    }
+
+    #[test]
+    fn candles_queued_behind_newer_ones_are_dropped() {
+        let t = 1_700_000_000;
+        let mut ctx = ExecContext::for_test("BTC", Vec::new());
+        assert!(ctx.update(candle(t + 60, 100.0)));
+        assert!(ctx.update(candle(t + 120, 101.0)));
+        // older than the latest
+        assert!(!ctx.update(candle(t + 60, 90.0)));
+        assert_eq!(ctx.last_close, 101.0);
+        // the forming candle sent again replaces it
+        assert!(ctx.update(candle(t + 120, 102.0)));
+        assert_eq!(ctx.candles.len(), 2);
+        assert_eq!(ctx.last_close, 102.0);
+        assert_eq!(ctx.candles[1].close, 102.0);
+    }
+
+    #[test]
+    fn reload_resamples_correlations_when_their_sampling_changes() {
+        let (_, candle_rx) = mpsc::channel(1);
+        let (mut executioner, _oe_rx) = executioner(Arc::default(), candle_rx);
+        let t = 1_700_000_000;
+        let history: Vec<Candle> = (1..=60)
+            .map(|i| candle(t + i * 60, 100.0 + (i % 7) as f64))
+            .collect();
+        executioner.warm_up(&[("BTC".to_string(), history)]);
+        assert_eq!(executioner.correlations().sample_secs(), 60);
+        let rows = executioner.correlations().len();
+
+        let old = ConfigFile::default();
+        let new = ConfigFile::parse("risk:\n  correlation:\n    sample_secs: 300\n").unwrap();
+        executioner.reload_config(&old, &new).unwrap();
+        assert_eq!(executioner.correlations().sample_secs(), 300);
+        assert!(!executioner.correlations().is_empty());
+        assert!(executioner.correlations().len() < rows);
+    }
+}
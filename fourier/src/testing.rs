@@ -78,7 +78,7 @@ fn main() {
         .iter()
         .map(|pair| {
             let x = pair.1 as f64;
-            return x.ln();
+            x.ln()
         })
         .collect();
 
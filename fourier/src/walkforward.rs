@@ -0,0 +1,374 @@
+use crate::backtest::{
+    BackTester, BacktestReport, PERIODS_PER_YEAR, max_drawdown_pct, period_returns, sharpe_ratio,
+};
+use crate::config::{SymbolParams, Validate};
+use crate::fourier::Candle;
+use crate::optimiser::{
+    Assignment, ParamRange, SweepSpec, describe, grid, params_for, rank, run_grid, value_label,
+};
+use crate::strategy::{MAX_CANDLE_HISTORY, Strategy};
+use anyhow::{Context, Result};
+use plotly::{Plot, Scatter};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Walk-forward description: a sweep plus the window lengths, in seconds of
+/// candle time. Windows advance by `out_of_sample_secs` so the out-of-sample
+/// pieces tile the data without overlap.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WalkForwardSpec {
+    #[serde(flatten)]
+    pub sweep: SweepSpec,
+    pub in_sample_secs: u64,
+    pub out_of_sample_secs: u64,
+}
+
+impl WalkForwardSpec {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("read walk-forward spec {:?}", path))?;
+        let mut spec: Self = serde_yaml::from_str(&data).context("parse walk-forward spec")?;
+        spec.sweep.load_risk_limits()?;
+        Ok(spec)
+    }
+}
+
+/// Candle index ranges of one walk-forward step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    pub in_sample: Range<usize>,
+    pub out_of_sample: Range<usize>,
+}
+
+impl Window {
+    /// The end of the in-sample window, at most what a context keeps, to
+    /// load before the out-of-sample run so it does not start cold
+    pub fn pre_roll(&self) -> Range<usize> {
+        let end = self.in_sample.end;
+        end.saturating_sub(MAX_CANDLE_HISTORY)
+            .max(self.in_sample.start)..end
+    }
+}
+
+/// One optimise-then-trade step.
+#[derive(Debug, Clone)]
+pub struct WindowResult {
+    pub window: Window,
+    pub chosen: Assignment,
+    pub in_sample_score: f64,
+    pub out_of_sample_score: f64,
+    pub report: BacktestReport, // out-of-sample run
+}
+
+/// All steps plus the stitched out-of-sample equity curve.
+#[derive(Debug, Clone)]
+pub struct WalkForwardReport {
+    pub windows: Vec<WindowResult>,
+    pub equity_curve: Vec<(u64, f64)>,
+    pub initial_capital: f64,
+}
+
+impl WalkForwardReport {
+    pub fn final_equity(&self) -> f64 {
+        self.equity_curve
+            .last()
+            .map(|(_, e)| *e)
+            .unwrap_or(self.initial_capital)
+    }
+
+    pub fn total_return_pct(&self) -> f64 {
+        (self.final_equity() / self.initial_capital - 1.0) * 100.0
+    }
+
+    pub fn max_drawdown_pct(&self) -> f64 {
+        let equity: Vec<f64> = self.equity_curve.iter().map(|(_, e)| *e).collect();
+        max_drawdown_pct(&equity)
+    }
+
+    pub fn sharpe(&self) -> f64 {
+        let equity: Vec<f64> = self.equity_curve.iter().map(|(_, e)| *e).collect();
+        sharpe_ratio(&period_returns(&equity), PERIODS_PER_YEAR)
+    }
+
+    /// Mean out-of-sample score over mean in-sample score. Near 1 means the
+    /// in-sample edge carried over; near 0 or negative means it was fitted noise.
+    pub fn efficiency(&self) -> f64 {
+        let n = self.windows.len().max(1) as f64;
+        let is = self.windows.iter().map(|w| w.in_sample_score).sum::<f64>() / n;
+        let oos = self
+            .windows
+            .iter()
+            .map(|w| w.out_of_sample_score)
+            .sum::<f64>()
+            / n;
+        if is.abs() < f64::EPSILON {
+            return 0.0;
+        }
+        oos / is
+    }
+}
+
+/// Split time-ordered `candles` into rolling windows. The last partial
+/// out-of-sample window is kept if it has any candles.
+pub fn windows(candles: &[Candle], in_sample_secs: u64, out_of_sample_secs: u64) -> Vec<Window> {
+    let mut result = Vec::new();
+    if candles.is_empty() || in_sample_secs == 0 || out_of_sample_secs == 0 {
+        return result;
+    }
+
+    // first index whose close time is at or after `time`
+    let index_at = |time: u64| candles.partition_point(|c| c.close_secs() < time);
+
+    let first = candles[0].close_secs();
+    let mut start = first;
+    loop {
+        let is_end = index_at(start + in_sample_secs);
+        let oos_end = index_at(start + in_sample_secs + out_of_sample_secs);
+        if is_end >= candles.len() {
+            break;
+        }
+        result.push(Window {
+            in_sample: index_at(start)..is_end,
+            out_of_sample: is_end..oos_end,
+        });
+        if oos_end >= candles.len() {
+            break;
+        }
+        start += out_of_sample_secs;
+    }
+    result
+}
+
+/// Optimise on each in-sample window, then trade the following out-of-sample
+/// window with the winner, warmed up on the in-sample candles. Capital
+/// carries from one out-of-sample window to the next; positions still open
+/// at a window's end are marked to market.
+pub async fn walk_forward<P, S, F>(
+    candles: &[Candle],
+    spec: &WalkForwardSpec,
+    section: &str,
+    make: F,
+) -> Result<WalkForwardReport>
+where
+    P: DeserializeOwned + Serialize + Default + Validate,
+    S: Strategy + Send + 'static,
+    F: Fn(SymbolParams<P>) -> S,
+{
+    let sweep = &spec.sweep;
+    let ranges: Vec<(String, ParamRange)> = sweep.parsed_ranges()?;
+    let assignments = grid(&ranges);
+    let mut capital = sweep.initial_capital;
+    let mut results = Vec::new();
+    let mut equity_curve = Vec::new();
+
+    let steps = windows(candles, spec.in_sample_secs, spec.out_of_sample_secs);
+    for (i, window) in steps.into_iter().enumerate() {
+        let in_sample = Arc::new(candles[window.in_sample.clone()].to_vec());
+        let mut trials =
+            run_grid::<P, S, _>(in_sample, sweep, section, assignments.clone(), &make).await;
+        rank(&mut trials, sweep.rank_by);
+        let Some(best) = trials.into_iter().next() else {
+            println!("[WARN][WALKFORWARD] Window {} had no valid trials", i);
+            continue;
+        };
+
+        let params = params_for::<P>(section, &sweep.base, &best.assignment)?;
+        let out_of_sample = Arc::new(candles[window.out_of_sample.clone()].to_vec());
+        let report = BackTester::create(make(params))
+            .quiet()
+            .with_risk_limits(sweep.risk_limits.clone())
+            .flat_at_end()
+            .with_history(&sweep.symbol, candles[window.pre_roll()].to_vec())
+            .run_candles(out_of_sample, &sweep.symbol, capital)
+            .await;
+
+        println!(
+            "[INFO][WALKFORWARD] Window {}: {} | in-sample {:.4} out-of-sample {:.4} equity {:.2}",
+            i,
+            describe(&best.assignment),
+            sweep.rank_by.score(&best.report),
+            sweep.rank_by.score(&report),
+            report.final_equity
+        );
+
+        capital = report.final_equity;
+        equity_curve.extend(report.equity_curve.iter().copied());
+        results.push(WindowResult {
+            window,
+            in_sample_score: sweep.rank_by.score(&best.report),
+            out_of_sample_score: sweep.rank_by.score(&report),
+            chosen: best.assignment,
+            report,
+        });
+    }
+
+    Ok(WalkForwardReport {
+        windows: results,
+        equity_curve,
+        initial_capital: sweep.initial_capital,
+    })
+}
+
+/// One row per window with the chosen parameters and both scores.
+pub fn write_csv<P: AsRef<Path>>(path: P, report: &WalkForwardReport) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path.as_ref())?;
+    let Some(first) = report.windows.first() else {
+        return Ok(());
+    };
+
+    let mut header = vec![
+        "window".to_string(),
+        "oos_start".to_string(),
+        "oos_end".to_string(),
+    ];
+    header.extend(first.chosen.iter().map(|(k, _)| k.clone()));
+    header.extend(
+        [
+            "in_sample_score",
+            "out_of_sample_score",
+            "oos_return_pct",
+            "oos_max_drawdown_pct",
+            "oos_trades",
+            "equity",
+        ]
+        .map(String::from),
+    );
+    writer.write_record(&header)?;
+
+    for (i, result) in report.windows.iter().enumerate() {
+        let curve = &result.report.equity_curve;
+        let mut row = vec![
+            i.to_string(),
+            curve
+                .first()
+                .map(|(t, _)| t.to_string())
+                .unwrap_or_default(),
+            curve.last().map(|(t, _)| t.to_string()).unwrap_or_default(),
+        ];
+        row.extend(result.chosen.iter().map(|(_, v)| value_label(v)));
+        row.extend([
+            result.in_sample_score.to_string(),
+            result.out_of_sample_score.to_string(),
+            result.report.total_return_pct.to_string(),
+            result.report.max_drawdown_pct.to_string(),
+            result.report.trade_count.to_string(),
+            result.report.final_equity.to_string(),
+        ]);
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Stitched out-of-sample equity curve
+pub fn write_equity_plot<P: AsRef<Path>>(path: P, report: &WalkForwardReport) {
+    let x: Vec<u64> = report.equity_curve.iter().map(|(t, _)| *t).collect();
+    let y: Vec<f64> = report.equity_curve.iter().map(|(_, e)| *e).collect();
+    let mut plot = Plot::new();
+    plot.add_trace(Scatter::new(x, y).name("out-of-sample equity"));
+    plot.set_layout(plotly::Layout::new().title("Walk-forward equity"));
+    plot.write_html(path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roostoo::{OrderSide, OrderType};
+    use crate::strategy::{ExecContext, Order, SharedState};
+    use async_trait::async_trait;
+    use tokio::sync::Mutex;
+
+    fn candles(count: u64) -> Vec<Candle> {
+        (0..count)
+            .map(|i| Candle {
+                close_time: (1_700_000_000 + i) * 1_000,
+                close: 100.0,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn out_of_sample_windows_tile_the_data() {
+        let data = candles(100);
+        let steps = windows(&data, 40, 20);
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].in_sample, 0..40);
+        assert_eq!(steps[0].out_of_sample, 40..60);
+        assert_eq!(steps[1].in_sample, 20..60);
+        assert_eq!(steps[1].out_of_sample, 60..80);
+        assert_eq!(steps[2].out_of_sample, 80..100);
+    }
+
+    #[test]
+    fn too_little_data_gives_no_windows() {
+        assert!(windows(&candles(30), 40, 20).is_empty());
+    }
+
+    // enters once it has seen 30 candles, and never exits
+    struct NeedsHistory;
+
+    #[async_trait]
+    impl Strategy for NeedsHistory {
+        async fn should_long(&self, ctx: &mut ExecContext, _: Arc<Mutex<SharedState>>) -> bool {
+            ctx.candles.len() >= 30 && !ctx.position.is_open()
+        }
+
+        async fn go_long(&self, ctx: &ExecContext, _: Arc<Mutex<SharedState>>) -> Option<Order> {
+            Some(Order {
+                pair: ctx.pair(),
+                side: OrderSide::Buy,
+                order_type: OrderType::Market,
+                quantity: 1.0,
+                price: None,
+            })
+        }
+
+        async fn update_position(&self, _: &mut ExecContext, _: Arc<Mutex<SharedState>>) -> bool {
+            false
+        }
+    }
+
+    #[tokio::test]
+    async fn out_of_sample_runs_start_warm() {
+        let data = candles(100);
+        let steps = windows(&data, 40, 20);
+        assert_eq!(steps[1].pre_roll(), 20..60);
+
+        let run = |history: Vec<Candle>| {
+            let out_of_sample = Arc::new(data[steps[1].out_of_sample.clone()].to_vec());
+            BackTester::create(NeedsHistory)
+                .quiet()
+                .with_history("BTC", history)
+                .run_candles(out_of_sample, "BTC", 1_000.0)
+        };
+        let cold = run(Vec::new()).await;
+        let warm = run(data[steps[1].pre_roll()].to_vec()).await;
+        // a cold start never sees 30 candles in a 20 candle window
+        assert_eq!(cold.final_equity, 1_000.0);
+        assert!(warm.final_equity < 1_000.0, "entry fees are paid");
+    }
+
+    #[tokio::test]
+    async fn out_of_sample_runs_end_flat() {
+        let data = candles(60);
+        let run = |flat: bool| {
+            let tester = BackTester::create(NeedsHistory)
+                .quiet()
+                .with_history("BTC", data[..30].to_vec());
+            let tester = if flat { tester.flat_at_end() } else { tester };
+            tester.run_candles(Arc::new(data[30..].to_vec()), "BTC", 1_000.0)
+        };
+        let held = run(false).await;
+        let flat = run(true).await;
+        assert_eq!(held.trade_count, 0);
+        // the position is sold at the last close and the exit fee paid
+        assert_eq!(flat.trade_count, 1);
+        assert_eq!(flat.trades[0].exit_price, 100.0);
+        assert!(flat.final_equity < held.final_equity);
+    }
+}
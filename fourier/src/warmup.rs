@@ -0,0 +1,162 @@
+use crate::config::{ConfigError, ConfigFile, Validate, check_range};
+use crate::fourier::Candle;
+use crate::strategy::MAX_CANDLE_HISTORY;
+use serde::{Deserialize, Serialize};
+
+/// History loaded into every symbol before the first live candle, from the
+/// `warmup` section:
+/// ```yaml
+/// warmup:
+///   candles: 2048
+///   page: 1000
+/// ```
+/// `candles: 0` starts cold and waits for live candles instead.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct WarmupConfig {
+    pub candles: usize, // per symbol, at most what a context keeps
+    pub page: usize,    // candles per request; Binance returns at most 1000
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            candles: MAX_CANDLE_HISTORY,
+            page: 1_000,
+        }
+    }
+}
+
+impl Validate for WarmupConfig {
+    fn validate(&self, scope: &str) -> Result<(), ConfigError> {
+        check_range(
+            scope,
+            "candles",
+            self.candles as f64,
+            0.0,
+            MAX_CANDLE_HISTORY as f64,
+        )?;
+        check_range(scope, "page", self.page as f64, 1.0, 1_000.0)
+    }
+}
+
+impl WarmupConfig {
+    pub const SECTION: &'static str = "warmup";
+
+    /// Warm-up settings; a missing section yields the defaults.
+    pub fn from_config(config: &ConfigFile) -> Result<Self, ConfigError> {
+        Ok(config.settings(Self::SECTION)?.unwrap_or_default())
+    }
+
+    /// Request windows `(start_ms, end_ms)` covering the `candles` intervals
+    /// before `end_ms`, plus one for the kline still forming, newest first
+    /// and `page` candles each
+    pub fn pages(&self, end_ms: u64, interval_ms: u64) -> Vec<(u64, u64)> {
+        let span = self.page as u64 * interval_ms;
+        let first = end_ms.saturating_sub((self.candles as u64 + 1) * interval_ms);
+        let mut pages = Vec::new();
+        let mut end = end_ms;
+        while end > first {
+            let start = end.saturating_sub(span).max(first);
+            pages.push((start, end - 1));
+            end = start;
+        }
+        pages
+    }
+
+    /// The last `candles` closed candles before `end_ms`, oldest first,
+    /// fetched a page at a time with `fetch(start_ms, end_ms)`. A failed
+    /// page ends the backfill so what is returned has no gaps.
+    pub fn backfill<F>(
+        &self,
+        symbol: &str,
+        end_ms: u64,
+        interval_ms: u64,
+        mut fetch: F,
+    ) -> Vec<Candle>
+    where
+        F: FnMut(u64, u64) -> Result<Vec<Candle>, String>,
+    {
+        let mut candles = Vec::with_capacity(self.candles);
+        for (start, end) in self.pages(end_ms, interval_ms) {
+            match fetch(start, end) {
+                Ok(page) if page.is_empty() => break,
+                Ok(page) => candles.extend(page),
+                Err(e) => {
+                    println!(
+                        "[WARN][WARMUP] {} history stops at {} candles: {}",
+                        symbol,
+                        candles.len(),
+                        e
+                    );
+                    break;
+                }
+            }
+        }
+        // the newest kline may still be forming
+        candles.retain(|candle| candle.close_time < end_ms);
+        candles.sort_by_key(|candle| candle.open_time);
+        candles.dedup_by_key(|candle| candle.open_time);
+        let excess = candles.len().saturating_sub(self.candles);
+        candles.drain(..excess);
+        candles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backfill_pages_back_and_keeps_closed_candles() {
+        let warmup = WarmupConfig {
+            candles: 25,
+            page: 10,
+        };
+        let end_ms = 100_500;
+        assert_eq!(
+            warmup.pages(end_ms, 1_000),
+            [(90_500, 100_499), (80_500, 90_499), (74_500, 80_499)]
+        );
+
+        // an exchange returning every 1s kline opening inside the window,
+        // including the one still forming at `end_ms`
+        let mut requests = 0;
+        let candles = warmup.backfill("BTC", end_ms, 1_000, |start, end| {
+            requests += 1;
+            Ok((start.div_ceil(1_000)..=end / 1_000)
+                .map(|second| Candle {
+                    open_time: second * 1_000,
+                    close_time: second * 1_000 + 999,
+                    close: second as f64,
+                    ..Default::default()
+                })
+                .collect())
+        });
+        assert_eq!(requests, 3);
+        assert_eq!(candles.len(), 25);
+        assert!(
+            candles
+                .windows(2)
+                .all(|w| w[0].open_time + 1_000 == w[1].open_time)
+        );
+        assert_eq!(candles.last().unwrap().close_time, 99_999);
+
+        // a failed page keeps only the newer, unbroken part
+        let mut pages = 0;
+        let candles = warmup.backfill("BTC", end_ms, 1_000, |start, end| {
+            pages += 1;
+            if pages == 2 {
+                return Err("rate limited".to_string());
+            }
+            Ok((start.div_ceil(1_000)..=end / 1_000)
+                .map(|second| Candle {
+                    open_time: second * 1_000,
+                    close_time: second * 1_000 + 999,
+                    ..Default::default()
+                })
+                .collect())
+        });
+        assert_eq!(candles.first().unwrap().open_time, 91_000);
+    }
+}